@@ -0,0 +1,60 @@
+//! Animates the first game of a PGN file as a GIF, or draws its final position as a PNG when the
+//! output name ends in `.png`.
+//!
+//! Usage: `cargo run --features raster --example game_gif <game.pgn> <out.gif> [delay ms]`
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Duration;
+
+use rs_chess::pgn::parse_games;
+use rs_chess::raster::{self, GifOptions};
+use rs_chess::svg::SvgRenderer;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: game_gif <game.pgn> <out.gif> [delay ms]");
+        process::exit(2);
+    }
+
+    let pgn = fs::read_to_string(&args[0]).unwrap_or_else(|error| {
+        eprintln!("cannot read {}: {}", args[0], error);
+        process::exit(1);
+    });
+    let Some(game) = parse_games(&pgn).into_iter().next() else {
+        eprintln!("no games in {}", args[0]);
+        process::exit(1);
+    };
+
+    let renderer = SvgRenderer::new();
+    let image = if args[1].ends_with(".png") {
+        game.start_position()
+            .and_then(|start| Ok((start, game.mainline()?)))
+            .map_err(raster::RasterError::Pgn)
+            .and_then(|(start, moves)| {
+                let end = moves
+                    .iter()
+                    .fold(start, |position, m| position.make_move(m));
+                raster::position_png(&renderer, &end, moves.last())
+            })
+    } else {
+        let mut options = GifOptions::default();
+        if let Some(delay) = args.get(2).and_then(|arg| arg.parse().ok()) {
+            options.delay = Duration::from_millis(delay);
+        }
+        raster::pgn_gif(&renderer, &game, &options)
+    };
+
+    let image = image.unwrap_or_else(|error| {
+        eprintln!("cannot draw the game: {}", error);
+        process::exit(1);
+    });
+    if let Err(error) = fs::write(&args[1], &image) {
+        eprintln!("cannot write {}: {}", args[1], error);
+        process::exit(1);
+    }
+
+    println!("{} bytes written to {}", image.len(), args[1]);
+}
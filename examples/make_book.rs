@@ -0,0 +1,40 @@
+//! Builds a Polyglot opening book from a PGN collection.
+//!
+//! Usage: `cargo run --release --example make_book <games.pgn> <book.bin> [max ply] [min games]`
+
+use std::env;
+use std::fs;
+use std::process;
+
+use rs_chess::book::BookBuilder;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: make_book <games.pgn> <book.bin> [max ply] [min games]");
+        process::exit(2);
+    }
+
+    let pgn = fs::read_to_string(&args[0]).unwrap_or_else(|error| {
+        eprintln!("cannot read {}: {}", args[0], error);
+        process::exit(1);
+    });
+
+    let mut builder = BookBuilder::new();
+    if let Some(max_ply) = args.get(2).and_then(|arg| arg.parse().ok()) {
+        builder = builder.max_ply(max_ply);
+    }
+    if let Some(min_games) = args.get(3).and_then(|arg| arg.parse().ok()) {
+        builder = builder.min_games(min_games);
+    }
+
+    let games = builder.add_pgn(&pgn);
+    let book = builder.build();
+
+    if let Err(error) = book.save(&args[1]) {
+        eprintln!("cannot write {}: {}", args[1], error);
+        process::exit(1);
+    }
+
+    println!("{} games, {} book entries", games, book.len());
+}
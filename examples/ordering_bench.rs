@@ -0,0 +1,86 @@
+//! Compares search node counts with different move ordering heuristics enabled.
+//!
+//! Usage: `cargo run --release --example ordering_bench [depth]` (default depth 3).
+
+use std::env;
+use std::time::Duration;
+
+use rs_chess::position::Position;
+use rs_chess::search::{MoveOrdering, SearchLimits, Searcher};
+
+const POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+];
+
+fn main() {
+    let depth: u32 = env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(3);
+
+    let configs = [
+        ("none", MoveOrdering::none()),
+        (
+            "hash move",
+            MoveOrdering {
+                hash_move: true,
+                ..MoveOrdering::none()
+            },
+        ),
+        (
+            "+ mvv-lva",
+            MoveOrdering {
+                hash_move: true,
+                mvv_lva: true,
+                ..MoveOrdering::none()
+            },
+        ),
+        (
+            "+ killers",
+            MoveOrdering {
+                history: false,
+                ..MoveOrdering::default()
+            },
+        ),
+        ("+ history", MoveOrdering::default()),
+    ];
+
+    println!("depth {depth}, {} positions", POSITIONS.len());
+    println!(
+        "{:<12} {:>14} {:>10} {:>10}",
+        "ordering", "nodes", "vs none", "time"
+    );
+
+    let mut baseline: Option<u64> = None;
+
+    for (name, ordering) in configs {
+        let mut nodes: u64 = 0;
+        let mut elapsed = Duration::ZERO;
+
+        for fen in POSITIONS {
+            let position = Position::from_fen(fen).expect("benchmark FEN is valid");
+            let mut searcher = Searcher::new(SearchLimits::depth(depth));
+            searcher.set_ordering(ordering);
+
+            let result = searcher.search(&position);
+            nodes += result.nodes;
+            elapsed += result.elapsed;
+        }
+
+        let ratio = nodes as f64 / *baseline.get_or_insert(nodes) as f64;
+        println!(
+            "{:<12} {:>14} {:>9.1}% {:>9.2}s",
+            name,
+            nodes,
+            ratio * 100.0,
+            elapsed.as_secs_f64()
+        );
+    }
+}
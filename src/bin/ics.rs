@@ -0,0 +1,36 @@
+//! ICS server binary for FICS-style interfaces. Listens on `127.0.0.1:5000` unless an address is
+//! given. See [`rs_chess::ics`] for the supported commands.
+
+use std::env;
+use std::process::ExitCode;
+
+use rs_chess::ics;
+
+const USAGE: &str = "usage: rs_chess-ics [<address>]";
+
+fn main() -> ExitCode {
+    let mut address = ics::DEFAULT_ADDRESS.to_string();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            other if !other.starts_with('-') => address = other.to_string(),
+            other => {
+                eprintln!("unknown option {}\n{}", other, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    println!("rs_chess-ics listening on telnet://{}", address);
+    match ics::serve(&address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rs_chess-ics: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! HTTP/JSON game server binary. Listens on `127.0.0.1:8080` unless an address is given. See
+//! [`rs_chess::server`] for the API. With the `websocket` feature, `--websocket <address>` also
+//! serves live games, see `rs_chess::live`.
+
+use std::env;
+use std::process::ExitCode;
+#[cfg(feature = "websocket")]
+use std::thread;
+
+#[cfg(feature = "websocket")]
+use rs_chess::live;
+use rs_chess::server;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+#[cfg(feature = "websocket")]
+const USAGE: &str = "usage: rs_chess-server [<address>] [--websocket <address>]";
+#[cfg(not(feature = "websocket"))]
+const USAGE: &str = "usage: rs_chess-server [<address>]";
+
+fn main() -> ExitCode {
+    let mut address = DEFAULT_ADDRESS.to_string();
+    let mut websocket: Option<String> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            "--websocket" if cfg!(feature = "websocket") => match args.next() {
+                Some(value) => websocket = Some(value),
+                None => {
+                    eprintln!("--websocket needs an address\n{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            other if !other.starts_with('-') => address = other.to_string(),
+            other => {
+                eprintln!("unknown option {}\n{}", other, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    #[cfg(feature = "websocket")]
+    if let Some(websocket) = websocket {
+        println!("rs_chess-server live games on ws://{}", websocket);
+        thread::spawn(move || {
+            if let Err(error) = live::serve(&websocket, live::RECONNECT_GRACE) {
+                eprintln!("rs_chess-server: {}", error);
+            }
+        });
+    }
+    #[cfg(not(feature = "websocket"))]
+    let _ = websocket;
+
+    println!("rs_chess-server listening on http://{}", address);
+    match server::serve(&address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rs_chess-server: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Terminal interface binary. Two players share the keyboard unless `--engine` picks a side for
+//! the built-in engine. `--time` sets each clock in minutes, `--increment` the seconds added per
+//! move and `--threads` the engine's search threads.
+
+use std::env;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use rs_chess::Color;
+use rs_chess::tui::{self, TuiOptions};
+
+const USAGE: &str = "usage: rs_chess-tui [--engine white|black] [--time <minutes>] \
+                     [--increment <seconds>] [--threads <n>]";
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match tui::run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rs_chess-tui: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// `None` when help was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<TuiOptions>, String> {
+    let mut options = TuiOptions::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--engine" => {
+                options.engine = match value()?.as_str() {
+                    "white" => Some(Color::White),
+                    "black" => Some(Color::Black),
+                    other => return Err(format!("unknown engine side {}", other)),
+                }
+            }
+            "--time" => {
+                let minutes: f64 = value()?.parse().map_err(|_| "bad --time".to_string())?;
+                options.time = Duration::from_secs_f64(minutes.max(0.0) * 60.0);
+            }
+            "--increment" => {
+                let seconds: f64 = value()?
+                    .parse()
+                    .map_err(|_| "bad --increment".to_string())?;
+                options.increment = Duration::from_secs_f64(seconds.max(0.0));
+            }
+            "--threads" => {
+                options.threads = value()?.parse().map_err(|_| "bad --threads".to_string())?;
+            }
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+
+    Ok(Some(options))
+}
@@ -0,0 +1,20 @@
+//! Engine binary. Speaks the Universal Chess Interface on stdin / stdout, or the xboard protocol
+//! when the first command is `xboard`.
+
+use std::io::{self, BufRead, Read};
+
+use rs_chess::{uci, xboard};
+
+fn main() {
+    let mut input = io::stdin().lock();
+    let mut first = String::new();
+    if input.read_line(&mut first).is_err() {
+        return;
+    }
+
+    if first.trim() == "xboard" {
+        xboard::run(input, io::stdout());
+    } else {
+        uci::run(first.as_bytes().chain(input), io::stdout());
+    }
+}
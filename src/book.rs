@@ -0,0 +1,379 @@
+//! Polyglot opening books.
+//!
+//! A `.bin` book is a list of 16-byte big-endian entries sorted by position key: the
+//! [`Position::hash`] key, a move, a weight and a learn value. [`Book`] reads and writes them
+//! and picks moves for a position; [`BookBuilder`] makes a book from PGN games.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use tracing::debug;
+
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::pgn::parse_games;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::Position;
+
+const ENTRY_BYTES: usize = 16;
+
+/// One raw entry of a Polyglot book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    /// Polyglot key of the position, see [`Position::hash`].
+    pub key: u64,
+    /// Move in Polyglot encoding. Castling is stored as the king taking its own rook.
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+/// A book move for a position, with its weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookMove {
+    pub chess_move: ChessMove,
+    pub weight: u16,
+}
+
+/// How to pick among the book moves of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSelection {
+    /// Always the move with the highest weight.
+    Best,
+    /// A random move, each with probability proportional to its weight.
+    WeightedRandom,
+}
+
+/// A Polyglot opening book held in memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Reads a `.bin` book file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Book::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a book from Polyglot `.bin` data. A trailing partial entry is an error.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Book> {
+        let mut bytes: Vec<u8> = vec![];
+        reader.read_to_end(&mut bytes)?;
+
+        if !bytes.len().is_multiple_of(ENTRY_BYTES) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "book size is not a multiple of 16 bytes",
+            ));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_BYTES)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().expect("8 bytes")),
+                raw_move: u16::from_be_bytes([chunk[8], chunk[9]]),
+                weight: u16::from_be_bytes([chunk[10], chunk[11]]),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().expect("4 bytes")),
+            })
+            .collect();
+
+        // Lookups rely on the order; well-formed books are sorted already.
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Book { entries })
+    }
+
+    /// Writes the book as a `.bin` file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the book in Polyglot `.bin` format.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for entry in &self.entries {
+            writer.write_all(&entry.key.to_be_bytes())?;
+            writer.write_all(&entry.raw_move.to_be_bytes())?;
+            writer.write_all(&entry.weight.to_be_bytes())?;
+            writer.write_all(&entry.learn.to_be_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Legal book moves for `position`, highest weight first. Entries whose move is not legal
+    /// in the position, e.g. from a key collision, are left out.
+    pub fn moves(&self, position: &Position) -> Vec<BookMove> {
+        let key = position.hash();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal = position.legal_moves();
+
+        let mut moves: Vec<BookMove> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                let chess_move = decode_move(position, entry.raw_move);
+                legal.contains(&chess_move).then_some(BookMove {
+                    chess_move,
+                    weight: entry.weight,
+                })
+            })
+            .collect();
+
+        moves.sort_by_key(|book_move| std::cmp::Reverse(book_move.weight));
+        moves
+    }
+
+    /// The highest weighted book move for `position`.
+    pub fn best_move(&self, position: &Position) -> Option<ChessMove> {
+        self.moves(position).first().map(|m| m.chess_move)
+    }
+
+    /// A book move for `position` picked by `seed`, so that uniformly random seeds choose moves
+    /// in proportion to their weights. The same seed always gives the same move.
+    pub fn weighted_move(&self, position: &Position, seed: u64) -> Option<ChessMove> {
+        let moves = self.moves(position);
+        let total: u32 = moves.iter().map(|m| m.weight as u32).sum();
+
+        if total == 0 {
+            return moves.first().map(|m| m.chess_move);
+        }
+
+        let mut roll = (seed % total as u64) as u32;
+        for book_move in &moves {
+            if roll < book_move.weight as u32 {
+                return Some(book_move.chess_move);
+            }
+            roll -= book_move.weight as u32;
+        }
+
+        None
+    }
+
+    /// Picks a book move for `position`.
+    pub fn choose(&self, position: &Position, selection: BookSelection) -> Option<ChessMove> {
+        match selection {
+            BookSelection::Best => self.best_move(position),
+            BookSelection::WeightedRandom => self.weighted_move(position, fastrand::u64(..)),
+        }
+    }
+}
+
+impl Game {
+    /// Book moves for the current position, highest weight first.
+    pub fn book_moves(&self, book: &Book) -> Vec<BookMove> {
+        book.moves(&self.to_position())
+    }
+
+    /// Picks a book move for the current player, if the position is in the book.
+    pub fn book_move(&self, book: &Book, selection: BookSelection) -> Option<ChessMove> {
+        book.choose(&self.to_position(), selection)
+    }
+}
+
+/// Builds a [`Book`] from games.
+///
+/// Every move played in the first `max_ply` plies of a game is counted along with the result
+/// from the mover's side: two points for a win, one for a draw or unknown result. Moves played
+/// in fewer than `min_games` games are dropped, and the points of the rest become their weight.
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    stats: HashMap<(u64, u16), MoveStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct MoveStats {
+    games: u32,
+    points: u32,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        BookBuilder::new()
+    }
+}
+
+impl BookBuilder {
+    /// A builder looking at the first 24 plies and keeping moves played in at least 3 games.
+    pub fn new() -> Self {
+        BookBuilder {
+            max_ply: 24,
+            min_games: 3,
+            stats: HashMap::new(),
+        }
+    }
+
+    /// Sets how many plies from the start of each game go into the book.
+    pub fn max_ply(mut self, max_ply: usize) -> Self {
+        self.max_ply = max_ply;
+        self
+    }
+
+    /// Sets how many games a move must appear in to be kept.
+    pub fn min_games(mut self, min_games: u32) -> Self {
+        self.min_games = min_games;
+        self
+    }
+
+    /// Adds every game in `pgn`. Games whose moves cannot be replayed are skipped. Returns the
+    /// number of games added.
+    pub fn add_pgn(&mut self, pgn: &str) -> usize {
+        let mut added = 0;
+
+        for game in parse_games(pgn) {
+            let replayed = game
+                .start_position()
+                .and_then(|start| Ok((start, game.mainline()?)));
+
+            match replayed {
+                Ok((start, moves)) => {
+                    let result = game.result.as_deref().or(game.tag("Result"));
+                    self.add_game(&start, &moves, result);
+                    added += 1;
+                }
+                Err(error) => debug!(%error, "skipping game"),
+            }
+        }
+
+        added
+    }
+
+    /// Adds one game played from `start`. `result` is a PGN result such as `1-0`.
+    pub fn add_game(&mut self, start: &Position, moves: &[ChessMove], result: Option<&str>) {
+        let winner = match result {
+            Some("1-0") => Some(Color::White),
+            Some("0-1") => Some(Color::Black),
+            _ => None,
+        };
+
+        let mut position = start.clone();
+        for chess_move in moves.iter().take(self.max_ply) {
+            let points = match winner {
+                Some(color) if color == position.side_to_move => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+
+            let stats = self
+                .stats
+                .entry((position.hash(), encode_move(&position, chess_move)))
+                .or_default();
+            stats.games += 1;
+            stats.points += points;
+
+            position = position.make_move(chess_move);
+        }
+    }
+
+    /// Makes the book. Weights are scaled down proportionally if any would not fit in 16 bits.
+    pub fn build(&self) -> Book {
+        let kept: Vec<(&(u64, u16), &MoveStats)> = self
+            .stats
+            .iter()
+            .filter(|(_, stats)| stats.games >= self.min_games && stats.points > 0)
+            .collect();
+
+        let max_points = kept.iter().map(|(_, s)| s.points).max().unwrap_or(0);
+        let scale = |points: u32| {
+            if max_points > u16::MAX as u32 {
+                ((points as u64 * u16::MAX as u64) / max_points as u64).max(1) as u16
+            } else {
+                points as u16
+            }
+        };
+
+        let mut entries: Vec<BookEntry> = kept
+            .into_iter()
+            .map(|(&(key, raw_move), stats)| BookEntry {
+                key,
+                raw_move,
+                weight: scale(stats.points),
+                learn: 0,
+            })
+            .collect();
+
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+
+        Book { entries }
+    }
+}
+
+// Polyglot squares count a1 = 0 to h8 = 63, while `y = 0` is rank 8 here.
+fn polyglot_square(square: LocationCoords) -> u16 {
+    ((7 - square.y) * 8 + square.x) as u16
+}
+
+fn from_polyglot_square(square: u16) -> LocationCoords {
+    LocationCoords {
+        x: (square % 8) as usize,
+        y: 7 - (square / 8) as usize,
+    }
+}
+
+// to (6 bits), from (6 bits), promotion (3 bits). Castling is written as the king moving onto
+// its rook.
+fn encode_move(position: &Position, chess_move: &ChessMove) -> u16 {
+    let mut to = chess_move.to;
+    if position.is_castling(chess_move) {
+        to.x = if chess_move.to.x == 6 { 7 } else { 0 };
+    }
+
+    let promotion = match chess_move.promotion {
+        None => 0,
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(_) => 4,
+    };
+
+    polyglot_square(to) | polyglot_square(chess_move.from) << 6 | promotion << 12
+}
+
+fn decode_move(position: &Position, raw_move: u16) -> ChessMove {
+    let from = from_polyglot_square((raw_move >> 6) & 0x3f);
+    let mut to = from_polyglot_square(raw_move & 0x3f);
+    let promotion = match (raw_move >> 12) & 0b111 {
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => None,
+    };
+
+    // King takes own rook from its home square: castling.
+    let king_on_home =
+        matches!(position.piece_at(&from), Some((_, PieceType::King))) && from.x == 4;
+    let onto_own_rook = matches!(
+        (position.piece_at(&from), position.piece_at(&to)),
+        (Some((king_color, _)), Some((rook_color, PieceType::Rook))) if king_color == rook_color
+    );
+    if king_on_home && onto_own_rook {
+        to.x = if to.x == 7 { 6 } else { 2 };
+    }
+
+    ChessMove {
+        from,
+        to,
+        promotion,
+    }
+}
@@ -0,0 +1,365 @@
+use std::fmt;
+use std::ops::{AddAssign, Sub};
+
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::{Position, piece_index};
+
+/// Game phase of the starting position. Knights and bishops count 1, rooks 2 and queens 4.
+pub const MAX_PHASE: i32 = 24;
+
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0];
+
+// Piece values indexed by `piece_index`: pawn, knight, bishop, rook, queen, king.
+const MG_VALUES: [i32; 6] = [82, 337, 365, 477, 1025, 0];
+const EG_VALUES: [i32; 6] = [94, 281, 297, 512, 936, 0];
+
+// Piece-square tables from White's point of view, indexed `y * 8 + x` so the first row is rank 8.
+// Black pieces look up the vertically mirrored square.
+#[rustfmt::skip]
+const MG_TABLES: [[i32; 64]; 6] = [
+    // Pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98, 134,  61,  95,  68, 126,  34, -11,
+         -6,   7,  26,  31,  65,  56,  25, -20,
+        -14,  13,   6,  21,  23,  12,  17, -23,
+        -27,  -2,  -5,  12,  17,   6,  10, -25,
+        -26,  -4,  -4, -10,   3,   3,  33, -12,
+        -35,  -1, -20, -23, -15,  24,  38, -22,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Knight
+    [
+        -167, -89, -34, -49,  61, -97, -15, -107,
+         -73, -41,  72,  36,  23,  62,   7,  -17,
+         -47,  60,  37,  65,  84, 129,  73,   44,
+          -9,  17,  19,  53,  37,  69,  18,   22,
+         -13,   4,  16,  13,  28,  19,  21,   -8,
+         -23,  -9,  12,  10,  19,  17,  25,  -16,
+         -29, -53, -12,  -3,  -1,  18, -14,  -19,
+        -105, -21, -58, -33, -17, -28, -19,  -23,
+    ],
+    // Bishop
+    [
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    // Rook
+    [
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    // Queen
+    [
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    // King
+    [
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[rustfmt::skip]
+const EG_TABLES: [[i32; 64]; 6] = [
+    // Pawn
+    [
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Knight
+    [
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    // Bishop
+    [
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+    // Rook
+    [
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+    // Queen
+    [
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    // King
+    [
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+/// A middlegame / endgame pair of centipawn values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    /// Blends the middlegame and endgame values by `phase`, from [`MAX_PHASE`] (all pieces on
+    /// the board) down to 0 (bare kings and pawns).
+    pub fn taper(&self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        self.mg += other.mg;
+        self.eg += other.eg;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score {
+            mg: self.mg - other.mg,
+            eg: self.eg - other.eg,
+        }
+    }
+}
+
+/// One evaluation term split by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Term {
+    pub white: Score,
+    pub black: Score,
+}
+
+impl Term {
+    /// White's score minus Black's score.
+    pub fn net(&self) -> Score {
+        self.white - self.black
+    }
+
+    fn add(&mut self, color: Color, score: Score) {
+        match color {
+            Color::White => self.white += score,
+            Color::Black => self.black += score,
+        }
+    }
+}
+
+/// Per-term breakdown of a static evaluation.
+///
+/// Every term is stored from each side's own point of view. [`Evaluation::score`] combines them
+/// into a single centipawn value for the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluation {
+    pub material: Term,
+    pub piece_square: Term,
+    /// Remaining non-pawn material, from [`MAX_PHASE`] at the start down to 0.
+    pub phase: i32,
+    pub side_to_move: Color,
+}
+
+impl Evaluation {
+    fn new(side_to_move: Color) -> Self {
+        Evaluation {
+            material: Term::default(),
+            piece_square: Term::default(),
+            phase: 0,
+            side_to_move,
+        }
+    }
+
+    fn add_piece(&mut self, color: Color, piece_type: PieceType, square: &LocationCoords) {
+        let index = piece_index(piece_type);
+
+        // Tables are written for White, so mirror the rank for Black.
+        let table_square = match color {
+            Color::White => square.y * 8 + square.x,
+            Color::Black => (7 - square.y) * 8 + square.x,
+        };
+
+        self.material.add(
+            color,
+            Score {
+                mg: MG_VALUES[index],
+                eg: EG_VALUES[index],
+            },
+        );
+        self.piece_square.add(
+            color,
+            Score {
+                mg: MG_TABLES[index][table_square],
+                eg: EG_TABLES[index][table_square],
+            },
+        );
+        self.phase += PHASE_WEIGHTS[index];
+    }
+
+    /// Phase clamped to [`MAX_PHASE`], since promotions can push it past the starting value.
+    pub fn clamped_phase(&self) -> i32 {
+        self.phase.min(MAX_PHASE)
+    }
+
+    /// Tapered score in centipawns from White's point of view.
+    pub fn white_score(&self) -> i32 {
+        let mut total = self.material.net();
+        total += self.piece_square.net();
+
+        total.taper(self.clamped_phase())
+    }
+
+    /// Tapered score in centipawns from the side to move's point of view.
+    pub fn score(&self) -> i32 {
+        match self.side_to_move {
+            Color::White => self.white_score(),
+            Color::Black => -self.white_score(),
+        }
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = self.clamped_phase();
+
+        writeln!(
+            f,
+            "{:<14}|{:>12}|{:>12}|{:>12}|{:>8}",
+            "term", "white mg eg", "black mg eg", "net mg eg", "tapered"
+        )?;
+
+        for (name, term) in [
+            ("material", &self.material),
+            ("piece-square", &self.piece_square),
+        ] {
+            let net = term.net();
+
+            writeln!(
+                f,
+                "{:<14}|{:>6}{:>6}|{:>6}{:>6}|{:>6}{:>6}|{:>8}",
+                name,
+                term.white.mg,
+                term.white.eg,
+                term.black.mg,
+                term.black.eg,
+                net.mg,
+                net.eg,
+                net.taper(phase)
+            )?;
+        }
+
+        writeln!(f, "phase {} / {}", phase, MAX_PHASE)?;
+        write!(
+            f,
+            "total {} (White), {} ({:?} to move)",
+            self.white_score(),
+            self.score(),
+            self.side_to_move
+        )
+    }
+}
+
+/// Evaluates the game from the active pieces in `Player::pieces`, with a per-term breakdown.
+pub fn evaluate_breakdown(game: &Game) -> Evaluation {
+    let mut evaluation = Evaluation::new(game.current_player.color);
+
+    for player in [&game.player1, &game.player2] {
+        for piece in player.pieces.borrow().iter() {
+            if let Some(coords) = piece.location.borrow().as_ref() {
+                evaluation.add_piece(player.color, piece.piece_type, coords);
+            }
+        }
+    }
+
+    evaluation
+}
+
+/// Static evaluation of the game in centipawns from the side to move's point of view.
+pub fn evaluate(game: &Game) -> i32 {
+    evaluate_breakdown(game).score()
+}
+
+/// Evaluates a plain position, with a per-term breakdown.
+pub fn evaluate_position_breakdown(position: &Position) -> Evaluation {
+    let mut evaluation = Evaluation::new(position.side_to_move);
+
+    for (y, row) in position.squares.iter().enumerate() {
+        for (x, square) in row.iter().enumerate() {
+            if let Some((color, piece_type)) = square {
+                evaluation.add_piece(*color, *piece_type, &LocationCoords { x, y });
+            }
+        }
+    }
+
+    evaluation
+}
+
+/// Static evaluation of a plain position in centipawns from the side to move's point of view.
+pub fn evaluate_position(position: &Position) -> i32 {
+    evaluate_position_breakdown(position).score()
+}
@@ -1,17 +1,40 @@
 use crate::location::{BoardLocation, LocationCoords, LocationState};
+use crate::movegen::ChessMove;
+use crate::observer::GameObserver;
 use crate::piece::{Piece, PieceType};
 use crate::player::{Color, Player};
+use crate::position::{CastlingRights, Position, PositionViolation};
 use crate::utils::gcd;
+use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
+#[cfg(debug_assertions)]
+use tracing::error;
+use tracing::{debug, debug_span, info};
+use uuid::Uuid;
 
 #[derive(Debug)]
 pub struct Game {
+    /// Random id, for telling games apart when hosting several.
+    pub id: Uuid,
     pub board: Vec<Vec<BoardLocation>>,
     pub player1: Rc<Player>,
     pub player2: Rc<Player>,
     pub current_player: Rc<Player>,
-    game_over: bool,
+    /// Square skipped by a pawn's double step on the last move, if any.
+    pub en_passant: Option<LocationCoords>,
+    /// Moves since the last capture or pawn move, for the fifty-move rule.
+    pub halfmove_clock: u32,
+    /// Starts at 1 and increases after every Black move.
+    pub fullmove_number: u32,
+    status: GameStatus,
+    // Position the game started from, then every move since and its SAN.
+    start: Position,
+    moves: Vec<ChessMove>,
+    san: Vec<String>,
+    // Position hashes from the start on, for repetitions.
+    hashes: Vec<u64>,
+    observers: Vec<Rc<RefCell<dyn GameObserver>>>,
 }
 
 impl Game {
@@ -81,12 +104,171 @@ impl Game {
 
         let current_player = Rc::clone(&player1);
 
-        Game {
+        let mut game = Game {
+            id: Uuid::new_v4(),
             board,
             player1,
             player2,
             current_player,
-            game_over: false,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            status: GameStatus::Ongoing,
+            start: Position::empty(),
+            moves: vec![],
+            san: vec![],
+            hashes: vec![],
+            observers: vec![],
+        };
+        game.start = game.to_position();
+        game.hashes.push(game.start.hash());
+        game
+    }
+
+    /// Builds a game from an arbitrary position, e.g. one imported from FEN or a puzzle.
+    ///
+    /// Positions that could not arise in a legal game are rejected with every violation found.
+    /// Any pieces the players already hold are discarded and replaced with the pieces of
+    /// `position`, and the player whose color is to move goes first.
+    pub fn from_position(
+        player1: Rc<Player>,
+        player2: Rc<Player>,
+        position: &Position,
+    ) -> Result<Game, Vec<PositionViolation>> {
+        position.validate()?;
+
+        for player in [&player1, &player2] {
+            player.pieces.borrow_mut().clear();
+            player.dead_pieces.borrow_mut().clear();
+            *player.king.borrow_mut() = None;
+        }
+
+        let mut board: Vec<Vec<BoardLocation>> = Vec::with_capacity(8);
+
+        for y in 0..8 {
+            board.push(Vec::with_capacity(8));
+
+            for x in 0..8 {
+                let coords = Rc::new(LocationCoords { x, y });
+                let mut board_location = BoardLocation {
+                    coords: Rc::clone(&coords),
+                    state: LocationState::Empty,
+                    piece: None,
+                    white_attackable: false,
+                    black_attackable: false,
+                };
+
+                if let Some((color, piece_type)) = position.squares[y][x] {
+                    let owner = if player1.color == color {
+                        &player1
+                    } else {
+                        &player2
+                    };
+
+                    let piece = Rc::new(Piece {
+                        piece_type,
+                        owner: Rc::clone(owner),
+                        id: Uuid::new_v4(),
+                        has_moved: RefCell::new(setup_has_moved(
+                            position, color, piece_type, &coords,
+                        )),
+                        location: RefCell::new(Some(Rc::clone(&coords))),
+                    });
+
+                    if let PieceType::King = piece_type {
+                        *owner.king.borrow_mut() = Some(Rc::clone(&piece));
+                    }
+
+                    owner.pieces.borrow_mut().push(Rc::clone(&piece));
+                    board_location.state = LocationState::Occupied;
+                    board_location.piece = Some(piece);
+                }
+
+                board[y].push(board_location);
+            }
+        }
+
+        let current_player = if player1.color == position.side_to_move {
+            Rc::clone(&player1)
+        } else {
+            Rc::clone(&player2)
+        };
+
+        Ok(Game {
+            id: Uuid::new_v4(),
+            board,
+            player1,
+            player2,
+            current_player,
+            en_passant: position.en_passant,
+            halfmove_clock: position.halfmove_clock,
+            fullmove_number: position.fullmove_number,
+            status: position_status(position, 1),
+            start: position.clone(),
+            moves: vec![],
+            san: vec![],
+            hashes: vec![position.hash()],
+            observers: vec![],
+        })
+    }
+
+    /// Takes a plain snapshot of the current position.
+    ///
+    /// Castling rights are derived from whether the kings and rooks have moved.
+    pub fn to_position(&self) -> Position {
+        let mut position = Position::empty();
+
+        for row in &self.board {
+            for location in row {
+                if let Some(piece) = &location.piece {
+                    position.squares[location.coords.y][location.coords.x] =
+                        Some((piece.owner.color, piece.piece_type));
+                }
+            }
+        }
+
+        position.side_to_move = self.current_player.color;
+        position.en_passant = self.en_passant;
+        position.halfmove_clock = self.halfmove_clock;
+        position.fullmove_number = self.fullmove_number;
+        position.castling = CastlingRights {
+            white_kingside: self.can_castle(Color::White, 7),
+            white_queenside: self.can_castle(Color::White, 0),
+            black_kingside: self.can_castle(Color::Black, 7),
+            black_queenside: self.can_castle(Color::Black, 0),
+        };
+
+        position
+    }
+
+    // Whether the king and the rook on `rook_x` of `color` are both unmoved on their home squares.
+    fn can_castle(&self, color: Color, rook_x: usize) -> bool {
+        let home_rank = match color {
+            Color::White => 7,
+            Color::Black => 0,
+        };
+
+        let unmoved = |x: usize, piece_type: PieceType| match &self.board[home_rank][x].piece {
+            Some(p) => {
+                p.owner.color == color && p.piece_type == piece_type && !*p.has_moved.borrow()
+            }
+            None => false,
+        };
+
+        unmoved(4, PieceType::King) && unmoved(rook_x, PieceType::Rook)
+    }
+
+    /// Registers an observer that is notified of every successful move.
+    pub fn add_observer(&mut self, observer: Rc<RefCell<dyn GameObserver>>) {
+        self.observers.push(observer);
+    }
+
+    fn notify_observers<F>(&self, mut event: F)
+    where
+        F: FnMut(&mut dyn GameObserver, &Game),
+    {
+        for observer in &self.observers {
+            event(&mut *observer.borrow_mut(), self);
         }
     }
 
@@ -94,14 +276,130 @@ impl Game {
         &self.board[location.y][location.x]
     }
 
+    /// Moves a piece, promoting pawns that reach the last rank to a queen.
     pub fn move_piece(&mut self, source: LocationCoords, dest: LocationCoords) {
-        if self.game_over {
-            println!("Game is over, no more moves can be made");
+        self.move_piece_with_promotion(source, dest, PieceType::Queen);
+    }
+
+    /// Plays a move produced by move generation or the engine.
+    pub fn make_move(&mut self, chess_move: &ChessMove) {
+        self.move_piece_with_promotion(
+            chess_move.from,
+            chess_move.to,
+            chess_move.promotion.unwrap_or(PieceType::Queen),
+        );
+    }
+
+    /// Whether the game goes on, or how the rules ended it.
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// Whether the rules ended the game, so no more moves are taken.
+    pub fn is_over(&self) -> bool {
+        self.status != GameStatus::Ongoing
+    }
+
+    /// The last move played, if any. Games set up from a position start without one.
+    pub fn last_move(&self) -> Option<ChessMove> {
+        self.moves.last().copied()
+    }
+
+    /// The position the game started from, before any of [`Game::moves`].
+    pub fn start_position(&self) -> &Position {
+        &self.start
+    }
+
+    /// Every move played since the start position, in order.
+    pub fn moves(&self) -> &[ChessMove] {
+        &self.moves
+    }
+
+    /// The SAN of each of [`Game::moves`], e.g. for writing PGN.
+    pub fn san_moves(&self) -> &[String] {
+        &self.san
+    }
+
+    /// The [`Position::hash`] of the start position and of the position after each of
+    /// [`Game::moves`].
+    pub fn position_hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    /// How often the current position has occurred, counting this time. Positions are the same
+    /// when their [`Position::hash`] is.
+    pub fn repetitions(&self) -> usize {
+        let current = self.hashes.last();
+        self.hashes
+            .iter()
+            .filter(|&hash| Some(hash) == current)
+            .count()
+    }
+
+    /// Takes back the last move, replaying the others from the start position. Returns the move
+    /// taken back, or `None` when there is none. The players, id and observers stay; observers
+    /// are not told about the replayed moves.
+    pub fn take_back(&mut self) -> Option<ChessMove> {
+        let last = self.moves.pop()?;
+        let mut replay = Game::from_position(
+            Rc::clone(&self.player1),
+            Rc::clone(&self.player2),
+            &self.start,
+        )
+        .expect("the start position was valid");
+        for chess_move in &self.moves {
+            replay.make_move(chess_move);
+        }
+
+        replay.id = self.id;
+        replay.observers = std::mem::take(&mut self.observers);
+        *self = replay;
+        Some(last)
+    }
+
+    /// All legal moves for the current player.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        self.to_position().legal_moves()
+    }
+
+    /// Moves a piece, promoting pawns that reach the last rank to `promotion`.
+    ///
+    /// Castling is requested by moving the king two squares towards the rook, and en passant by
+    /// moving the pawn diagonally onto the square the enemy pawn skipped.
+    pub fn move_piece_with_promotion(
+        &mut self,
+        source: LocationCoords,
+        dest: LocationCoords,
+        promotion: PieceType,
+    ) {
+        let _span = debug_span!(
+            "move_piece",
+            player = %self.current_player.name,
+            source = %source,
+            dest = %dest
+        )
+        .entered();
+
+        if self.is_over() {
+            debug!("game is over, no more moves can be made");
+            return;
+        }
+
+        if let PieceType::Pawn | PieceType::King = promotion {
+            debug!(promotion = ?promotion, "cannot promote to this piece type");
             return;
         }
 
-        let mut successful_move = false;
-        let mut piece_clone: Option<Rc<Piece>> = None;
+        // Check bounds
+        if source.x > 7 || source.y > 7 || dest.x > 7 || dest.y > 7 {
+            debug!("square out of bounds");
+            return;
+        }
+
+        if source == dest {
+            debug!("source and destination are the same square");
+            return;
+        }
 
         // Generate attack map for opponent
         match self.current_player.color {
@@ -115,197 +413,301 @@ impl Game {
             }
         }
 
-        // Check bounds
-        if dest.x > 7 || dest.y > 7 {
-            println!("Destination out of bounds");
+        let piece = match &self.board[source.y][source.x].piece {
+            Some(piece) => Rc::clone(piece),
+            None => {
+                debug!("source square is empty");
+                return;
+            }
+        };
+
+        debug!(piece = %piece.id, piece_type = ?piece.piece_type, "found piece");
+
+        // Verify ownership
+        if piece.owner.id != self.current_player.id {
+            debug!(owner = %piece.owner.name, "piece belongs to the other player");
             return;
         }
 
-        match self.board[source.y][source.x].state {
-            LocationState::Occupied => {
-                if let Some(piece) = &self.board[source.y][source.x].piece {
-                    println!("FOUND THE PIECE");
-                    println!("{:?}", piece);
+        let position = self.to_position();
+        let mut chess_move = ChessMove::new(source, dest);
+
+        if let PieceType::Pawn = piece.piece_type {
+            let last_rank = match piece.owner.color {
+                Color::White => 0,
+                Color::Black => 7,
+            };
+
+            if dest.y == last_rank {
+                chess_move.promotion = Some(promotion);
+            }
+        }
+
+        let san = position.san(&chess_move);
+        let castling = position.is_castling(&chess_move);
+        let en_passant = position.is_en_passant(&chess_move);
+        let move_vec: (i32, i32) = get_move_vector(&source, &dest);
 
-                    // Verify ownership
-                    if piece.owner.name != self.current_player.name {
-                        println!("YOU DO NOT OWN THIS PIECE");
+        // Check intermediate collisions
+        match piece.piece_type {
+            PieceType::Knight => (),
+            _ => {
+                // Collisions apply to all other pieces
+                let intermediate_coords =
+                    points_along_vector(&source, &move_vec, GatherPointsMode::Exclusive);
+
+                for coord in intermediate_coords {
+                    if let LocationState::Occupied = self.board[coord.y][coord.x].state {
+                        debug!(blocked_at = %coord, "collision detected");
                         return;
                     }
+                }
+            }
+        }
 
-                    // Check for check
-                    if let Some(king) = piece.owner.king.borrow().as_ref() {
-                        if self.king_check_checker(&king) {
-                            println!("KING IS IN CHECK YOU CANNOT MOVE OTHER PIECES");
-                            return;
-                        }
-                    }
+        match &self.get_loc_cartesian(&dest).piece {
+            Some(o) => {
+                // Check for friendly fire
+                if o.owner.id == piece.owner.id {
+                    debug!(target = %o.id, "cannot capture a friendly piece");
+                    return;
+                }
 
-                    let move_vec: (i32, i32) = get_move_vector(&source, &dest);
-
-                    // Check intermediate collisions
-                    match piece.piece_type {
-                        PieceType::Knight => (),
-                        _ => {
-                            // Collisions apply to all other pieces
-                            let intermediate_coords = points_along_vector(
-                                &source,
-                                &move_vec,
-                                GatherPointsMode::Exclusive,
-                            );
-
-                            for coord in intermediate_coords {
-                                if let LocationState::Occupied = self.board[coord.y][coord.x].state
-                                {
-                                    println!("COLLISION DETECTED. NOT VALID MOVE");
-                                    return;
-                                }
-                            }
-                        }
+                // Validate piece attack
+                if !piece.validate_attack(&move_vec) {
+                    debug!(piece = %piece.id, "not a valid attack for piece");
+                    return;
+                }
+            }
+            None => {
+                // Validate piece move. Castling and en passant are checked by the rules below.
+                if en_passant {
+                    if !piece.validate_attack(&move_vec) {
+                        debug!(piece = %piece.id, "not a valid attack for piece");
+                        return;
                     }
+                } else if !castling && !piece.validate_move(&move_vec) {
+                    debug!(piece = %piece.id, "not a valid move for piece");
+                    return;
+                }
 
-                    match self.get_loc_cartesian(&dest).state {
-                        LocationState::Occupied => {
-                            // Validate piece attack
-                            if !piece.validate_attack(&move_vec) {
-                                println!("NOT A VALID ATTACK FOR {:?}", piece);
-                            }
+                if let PieceType::King = piece.piece_type {
+                    // Check for check
+                    let dest_loc = &self.board[dest.y][dest.x];
 
-                            // Check for friendly fire / path open
-                            if let Some(o) = &self.get_loc_cartesian(&dest).piece {
-                                if o.owner.name == piece.owner.name {
-                                    println!("FRIENDLY FIRE!");
-                                    return;
-                                } else {
-                                    // Reconcile attack / move
-                                    let id = o.id;
-
-                                    let mut owner_board = o.owner.pieces.borrow_mut();
-                                    let mut dead_board = o.owner.dead_pieces.borrow_mut();
-                                    let mut destroyed: i32 = -1;
-                                    let mut found_destroyed: bool = false;
-
-                                    for (index, piece) in owner_board.iter().enumerate() {
-                                        if id == piece.id {
-                                            println!("FOUND ATTACKED PIECE: {:?}", piece);
-                                            found_destroyed = true;
-                                            destroyed = index as i32;
-                                        }
-                                    }
-
-                                    if found_destroyed {
-                                        let mut dead_piece_location = o.location.borrow_mut();
-                                        *dead_piece_location = None;
-
-                                        dead_board
-                                            .push(owner_board.swap_remove(destroyed as usize));
-                                    } else {
-                                        return;
-                                    }
-                                }
-                            }
-                        }
-                        _ => {
-                            // Validate piece move
-                            if !piece.validate_move(&move_vec) {
-                                println!("NOT A VALID MOVE FOR {:?}", piece);
-                                return;
-                            }
+                    let attacked = match self.current_player.color {
+                        Color::Black => dest_loc.white_attackable,
+                        Color::White => dest_loc.black_attackable,
+                    };
 
-                            match piece.piece_type {
-                                PieceType::King => {
-                                    // Check for check
-                                    let dest_loc = &self.board[dest.y][dest.x];
-
-                                    match self.current_player.color {
-                                        Color::Black => {
-                                            if dest_loc.white_attackable {
-                                                println!("CANNOT MOVE KING INTO CHECK");
-                                                return;
-                                            }
-                                        }
-                                        Color::White => {
-                                            if dest_loc.black_attackable {
-                                                println!("CANNOT MOVE KING INTO CHECK");
-                                                return;
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => (),
-                            }
-                        }
+                    if attacked {
+                        debug!("cannot move king into check");
+                        return;
                     }
-
-                    // Mark success
-                    successful_move = true;
-                    piece_clone = Some(Rc::clone(piece));
                 }
             }
-            _ => {
-                println!("EMPTY NOTHING TO DO");
-                return;
+        }
+
+        // Pins, checks and castling rights are settled against the full rules.
+        if !position.is_legal(&chess_move) {
+            if castling {
+                debug!("castling is not allowed here");
+            } else {
+                debug!("move would leave the king in check");
             }
+            return;
         }
 
-        if successful_move {
-            let dest_loc = &mut self.board[dest.y][dest.x];
+        // Take the captured piece off the board. En passant captures the pawn beside the source.
+        let captured_at = if en_passant {
+            LocationCoords {
+                x: dest.x,
+                y: source.y,
+            }
+        } else {
+            dest
+        };
+        let captured = self.capture_piece(&captured_at);
+
+        self.relocate_piece(&source, &dest);
+
+        // Castling also moves the rook over the king.
+        if castling {
+            let (rook_from, rook_to) = if dest.x > source.x { (7, 5) } else { (0, 3) };
+            self.relocate_piece(
+                &LocationCoords {
+                    x: rook_from,
+                    y: source.y,
+                },
+                &LocationCoords {
+                    x: rook_to,
+                    y: source.y,
+                },
+            );
+        }
 
-            // Set has_moved to true if this is the first time a piece has moved.
-            if let Some(p) = piece_clone.clone() {
-                let mut has_moved = p.has_moved.borrow_mut();
-                if !*has_moved {
-                    *has_moved = true;
-                }
+        // Promote pawns that reached the last rank.
+        let mut promoted: Option<Rc<Piece>> = None;
+        if let Some(promotion) = chess_move.promotion {
+            promoted = Some(self.promote_pawn(&piece, promotion));
+        }
 
-                let mut piece_location = p.location.borrow_mut();
-                *piece_location = Some(Rc::clone(&dest_loc.coords));
-            }
+        // Track the en passant square and the move counters.
+        self.en_passant = None;
+        if let PieceType::Pawn = piece.piece_type
+            && move_vec.1.abs() == 2
+        {
+            self.en_passant = Some(LocationCoords {
+                x: source.x,
+                y: (source.y + dest.y) / 2,
+            });
+        }
 
-            // Set dest loc to moved piece.
-            dest_loc.piece = piece_clone;
-            dest_loc.state = LocationState::Occupied;
+        if captured.is_some() || matches!(piece.piece_type, PieceType::Pawn) {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
 
-            // Set source board location's piece to None.
-            let source_loc = &mut self.board[source.y][source.x];
-            source_loc.piece = None;
-            source_loc.state = LocationState::Empty;
+        if let Color::Black = self.current_player.color {
+            self.fullmove_number += 1;
+        }
 
-            // Check for victory.
-            let mut victory = false;
-            self.generate_attack_map(Rc::clone(&self.current_player));
+        // Check for check / victory.
+        let mut checked_king: Option<Rc<Piece>> = None;
+        self.clear_attack_map(Rc::clone(&self.current_player));
+        self.generate_attack_map(Rc::clone(&self.current_player));
 
-            match self.current_player.color {
-                Color::Black => {
-                    if let Some(enemy_king) = self.player1.king.borrow().as_ref() {
-                        if self.king_check_checker(&enemy_king) {
-                            if self.king_checkmate_checker(&enemy_king) {
-                                victory = true;
-                            }
-                        }
-                    }
-                }
-                Color::White => {
-                    if let Some(enemy_king) = self.player2.king.borrow().as_ref() {
-                        if self.king_check_checker(&enemy_king) {
-                            if self.king_checkmate_checker(&enemy_king) {
-                                victory = true;
-                            }
-                        }
-                    }
-                }
+        let enemy = match self.current_player.color {
+            Color::Black => Rc::clone(&self.player1),
+            Color::White => Rc::clone(&self.player2),
+        };
+
+        if let Some(enemy_king) = enemy.king.borrow().as_ref()
+            && self.king_check_checker(enemy_king)
+        {
+            checked_king = Some(Rc::clone(enemy_king));
+        }
+
+        // The loser is left to move after checkmate, as in the position itself.
+        self.switch_turns();
+        let next_position = self.to_position();
+        self.hashes.push(next_position.hash());
+        self.status = position_status(&next_position, self.repetitions());
+
+        match self.status {
+            GameStatus::Checkmate { .. } => info!(winner = %piece.owner.name, "checkmate"),
+            GameStatus::Draw(reason) => info!(?reason, "draw"),
+            GameStatus::Ongoing => (),
+        }
+
+        // Cross-check board, pieces and players after every move in debug builds. Only logged:
+        // callers that want to stop on a violation can call `validate_invariants` themselves.
+        #[cfg(debug_assertions)]
+        if let Err(violations) = self.validate_invariants() {
+            error!(?violations, "board invariants violated after move");
+        }
+
+        self.moves.push(chess_move);
+        self.san.push(san);
+
+        // Notify observers now that the board is settled.
+        self.notify_observers(|o, game| o.on_move(game, &piece, &source, &dest));
+
+        if let Some(c) = &captured {
+            self.notify_observers(|o, game| o.on_capture(game, &piece, c));
+        }
+
+        if let Some(n) = &promoted {
+            self.notify_observers(|o, game| o.on_promotion(game, &piece, n));
+        }
+
+        if let Some(king) = &checked_king {
+            self.notify_observers(|o, game| o.on_check(game, king));
+        }
+
+        match self.status {
+            GameStatus::Checkmate { .. } => {
+                let winner = Rc::clone(&piece.owner);
+                self.notify_observers(|o, game| o.on_game_over(game, &winner));
+            }
+            GameStatus::Draw(reason) => {
+                self.notify_observers(|o, game| o.on_draw(game, reason));
             }
+            GameStatus::Ongoing => {
+                let next = Rc::clone(&self.current_player);
+                self.notify_observers(|o, game| o.on_turn_change(game, &next));
+            }
+        }
+    }
 
-            if victory {
-                println!(
-                    "WINNER! {} has put the enemy in checkmate!",
-                    &self.current_player.name
-                );
-                self.game_over = true;
-            } else {
-                self.switch_turns();
+    // Moves the captured piece on `square` from its owner's pieces into their dead pieces.
+    fn capture_piece(&mut self, square: &LocationCoords) -> Option<Rc<Piece>> {
+        let captured = self.board[square.y][square.x].piece.take()?;
+        self.board[square.y][square.x].state = LocationState::Empty;
+
+        let mut owner_board = captured.owner.pieces.borrow_mut();
+        let mut dead_board = captured.owner.dead_pieces.borrow_mut();
+
+        if let Some(index) = owner_board.iter().position(|p| p.id == captured.id) {
+            debug!(captured = %captured.id, "found attacked piece");
+            dead_board.push(owner_board.swap_remove(index));
+        }
+
+        *captured.location.borrow_mut() = None;
+        drop(owner_board);
+        drop(dead_board);
+
+        Some(captured)
+    }
+
+    // Moves the piece on `source` to the empty square `dest` and marks it as moved.
+    fn relocate_piece(&mut self, source: &LocationCoords, dest: &LocationCoords) {
+        let piece = self.board[source.y][source.x].piece.take();
+        self.board[source.y][source.x].state = LocationState::Empty;
+
+        let dest_loc = &mut self.board[dest.y][dest.x];
+
+        // Set has_moved to true if this is the first time a piece has moved.
+        if let Some(p) = &piece {
+            let mut has_moved = p.has_moved.borrow_mut();
+            if !*has_moved {
+                *has_moved = true;
             }
+
+            let mut piece_location = p.location.borrow_mut();
+            *piece_location = Some(Rc::clone(&dest_loc.coords));
         }
+
+        dest_loc.piece = piece;
+        dest_loc.state = LocationState::Occupied;
+    }
+
+    // Replaces a pawn that reached the last rank with a new piece of the requested type.
+    fn promote_pawn(&mut self, pawn: &Rc<Piece>, promotion: PieceType) -> Rc<Piece> {
+        let coords = pawn.location.borrow().as_ref().map(Rc::clone);
+
+        let promoted = Rc::new(Piece {
+            piece_type: promotion,
+            owner: Rc::clone(&pawn.owner),
+            id: Uuid::new_v4(),
+            has_moved: RefCell::new(true),
+            location: RefCell::new(coords.clone()),
+        });
+
+        // Swap the pawn out of its owner's active pieces.
+        let mut pieces = pawn.owner.pieces.borrow_mut();
+        if let Some(index) = pieces.iter().position(|p| p.id == pawn.id) {
+            pieces[index] = Rc::clone(&promoted);
+        }
+
+        *pawn.location.borrow_mut() = None;
+
+        if let Some(c) = coords {
+            self.board[c.y][c.x].piece = Some(Rc::clone(&promoted));
+        }
+
+        promoted
     }
 
     fn switch_turns(&mut self) {
@@ -319,53 +721,127 @@ impl Game {
     // Generates white_attackable / black_attackable fields for check / victory condition checks.
     pub fn generate_attack_map(&mut self, player: Rc<Player>) {
         for piece in player.pieces.borrow().iter() {
-            match piece.piece_type {
-                PieceType::Pawn => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs: Vec<(i32, i32)> =
-                        self.get_attack_vecs_in_bounds(&piece, attack_unit_vecs);
+            let attack_points = self.attack_points(piece);
+            self.set_attack_flags(piece, &attack_points);
+        }
+    }
 
-                    self.set_attack_flags(&piece, &attack_vecs);
-                }
-                PieceType::Rook => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs: Vec<(i32, i32)> =
-                        self.get_attack_vecs_to_edge(&piece, attack_unit_vecs);
-                    self.set_attack_flags(&piece, &attack_vecs);
-                }
+    /// Squares the piece on `square` can legally move to, once each however many promotions lead
+    /// there. Empty unless the square holds a piece of the current player.
+    pub fn legal_destinations(&self, square: LocationCoords) -> Vec<Destination> {
+        let position = self.to_position();
+        let mut destinations: Vec<Destination> = vec![];
+
+        for chess_move in position.legal_moves() {
+            if chess_move.from != square || destinations.iter().any(|d| d.square == chess_move.to) {
+                continue;
+            }
+
+            let kind = if position.is_castling(&chess_move)
+                || position.is_en_passant(&chess_move)
+                || chess_move.promotion.is_some()
+            {
+                MoveKind::Special
+            } else if position.is_capture(&chess_move) {
+                MoveKind::Capture
+            } else {
+                MoveKind::Quiet
+            };
+            destinations.push(Destination {
+                square: chess_move.to,
+                kind,
+            });
+        }
+
+        destinations
+    }
+
+    /// Squares of the pieces of `color` that attack `square`, whatever stands on it. When it holds
+    /// a piece of `color` these are its defenders.
+    ///
+    /// Uses the same attack rules as the `white_attackable` / `black_attackable` flags, computed
+    /// afresh without touching the board, so pinned pieces still count.
+    pub fn attackers(&self, square: LocationCoords, color: Color) -> Vec<LocationCoords> {
+        let player = if self.player1.color == color {
+            &self.player1
+        } else {
+            &self.player2
+        };
+
+        player
+            .pieces
+            .borrow()
+            .iter()
+            .filter(|piece| self.attack_points(piece).contains(&square))
+            .filter_map(|piece| piece.location.borrow().as_deref().copied())
+            .collect()
+    }
+
+    /// Squares of the pieces of `color` that the other side attacks, from the top of the board.
+    pub fn attacked_pieces(&self, color: Color) -> Vec<LocationCoords> {
+        self.board
+            .iter()
+            .flatten()
+            .filter(|location| {
+                location
+                    .piece
+                    .as_ref()
+                    .is_some_and(|piece| piece.owner.color == color)
+            })
+            .map(|location| *location.coords)
+            .filter(|square| !self.attackers(*square, color.opponent()).is_empty())
+            .collect()
+    }
+
+    // Squares `piece` attacks, up to and including the first piece of either color in each
+    // direction. Squares with friendly pieces are defended rather than attacked.
+    fn attack_points(&self, piece: &Piece) -> Vec<LocationCoords> {
+        let Some(source) = piece.location.borrow().as_deref().copied() else {
+            return vec![];
+        };
+
+        let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(piece);
+        let attack_vecs: Vec<(i32, i32)> = match piece.piece_type {
+            PieceType::Rook | PieceType::Bishop | PieceType::Queen => {
+                self.get_attack_vecs_to_edge(piece, attack_unit_vecs)
+            }
+            PieceType::Pawn | PieceType::Knight | PieceType::King => {
+                self.get_attack_vecs_in_bounds(piece, attack_unit_vecs)
+            }
+        };
+
+        let mut points: Vec<LocationCoords> = vec![];
+        for attack in &attack_vecs {
+            let attack_points = match piece.piece_type {
+                // Knight is special due to mad hops.
                 PieceType::Knight => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs: Vec<(i32, i32)> =
-                        self.get_attack_vecs_in_bounds(&piece, attack_unit_vecs);
-                    self.set_attack_flags(&piece, &attack_vecs);
-                }
-                PieceType::Bishop => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs: Vec<(i32, i32)> =
-                        self.get_attack_vecs_to_edge(&piece, attack_unit_vecs);
-                    self.set_attack_flags(&piece, &attack_vecs);
+                    vec![LocationCoords {
+                        x: <i32 as TryInto<usize>>::try_into(source.x as i32 + attack.0).unwrap(),
+                        y: <i32 as TryInto<usize>>::try_into(source.y as i32 + attack.1).unwrap(),
+                    }]
                 }
-                PieceType::Queen => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs: Vec<(i32, i32)> =
-                        self.get_attack_vecs_to_edge(&piece, attack_unit_vecs);
-                    self.set_attack_flags(&piece, &attack_vecs);
-                }
-                PieceType::King => {
-                    let attack_unit_vecs: Vec<(i32, i32)> = self.get_piece_attack_vecs(&piece);
-                    let attack_vecs = self.get_attack_vecs_in_bounds(&piece, attack_unit_vecs);
-                    self.set_attack_flags(&piece, &attack_vecs);
+                _ => points_along_vector(&source, attack, GatherPointsMode::Inclusive),
+            };
+
+            for point in attack_points {
+                points.push(point);
+
+                // Any piece shields what lies behind it.
+                if self.board[point.y][point.x].state == LocationState::Occupied {
+                    break;
                 }
             }
         }
+
+        points
     }
 
     fn get_piece_attack_vecs(&self, piece: &Piece) -> Vec<(i32, i32)> {
         match piece.piece_type {
             PieceType::Pawn => {
                 vec![
-                    (1, 1 * piece.owner.pawn_direction),
-                    (-1, 1 * piece.owner.pawn_direction),
+                    (1, piece.owner.pawn_direction),
+                    (-1, piece.owner.pawn_direction),
                 ]
             }
             PieceType::Rook => {
@@ -413,50 +889,12 @@ impl Game {
         }
     }
 
-    fn king_checkmate_checker(&self, king: &Piece) -> bool {
-        // attack vecs can be used in general for potential moves
-        let unit_attack_vecs = self.get_piece_attack_vecs(&king);
-        let attack_vecs = self.get_attack_vecs_in_bounds(&king, unit_attack_vecs);
-        let mut checkmate: bool = true;
-
-        for vec in attack_vecs {
-            if let Some(king_coords) = king.location.borrow().as_ref() {
-                let location = points_along_vector(&king_coords, &vec, GatherPointsMode::Inclusive);
-                let board_loc = self.get_loc_cartesian(&location[0]);
-
-                match board_loc.state {
-                    LocationState::Occupied => {
-                        continue;
-                    }
-                    LocationState::Empty => {
-                        match king.owner.color {
-                            Color::Black => {
-                                if !board_loc.white_attackable {
-                                    checkmate = false;
-                                    break;
-                                }
-                            }
-                            Color::White => {
-                                //do stuff
-                                if !board_loc.black_attackable {
-                                    checkmate = false;
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        checkmate
-    }
-
     // Checks if king is in check based on current state of attack map of opponent
     fn king_check_checker(&self, king: &Piece) -> bool {
         let mut is_in_check: bool = false;
 
         if let Some(king_coords) = king.location.borrow().as_ref() {
-            let board_location = self.get_loc_cartesian(&king_coords);
+            let board_location = self.get_loc_cartesian(king_coords);
             match king.owner.color {
                 Color::Black => {
                     if board_location.white_attackable {
@@ -523,7 +961,7 @@ impl Game {
                 let mut curr_y = location.y as i32 + dy;
                 let mut last_valid: Option<(i32, i32)> = None;
 
-                while curr_x >= 0 && curr_x <= 7 && curr_y >= 0 && curr_y <= 7 {
+                while (0..=7).contains(&curr_x) && (0..=7).contains(&curr_y) {
                     last_valid = Some((curr_x, curr_y));
                     curr_x += dx;
                     curr_y += dy;
@@ -541,53 +979,23 @@ impl Game {
         vecs_to_edge
     }
 
-    fn set_attack_flags(&mut self, piece: &Piece, attack_vecs: &Vec<(i32, i32)>) {
-        if let Some(source) = piece.location.borrow().as_ref() {
-            for attack in attack_vecs {
-                let attack_points = match piece.piece_type {
-                    // Knight is special due to mad hops.
-                    PieceType::Knight => {
-                        vec![LocationCoords {
-                            x: <i32 as TryInto<usize>>::try_into(source.x as i32 + attack.0)
-                                .unwrap(),
-                            y: <i32 as TryInto<usize>>::try_into(source.y as i32 + attack.1)
-                                .unwrap(),
-                        }]
-                    }
-                    _ => points_along_vector(&source, &attack, GatherPointsMode::Inclusive),
-                };
-
-                for point in attack_points {
-                    let location = &mut self.board[point.y][point.x];
-                    let mut attackable: bool = false;
+    fn set_attack_flags(&mut self, piece: &Piece, attack_points: &[LocationCoords]) {
+        for point in attack_points {
+            let location = &mut self.board[point.y][point.x];
 
-                    match location.state {
-                        LocationState::Empty => {
-                            attackable = true;
-                        }
-                        LocationState::Occupied => {
-                            // if friendly do nothing. If enemy populate attack map.
-                            if let Some(other_piece) = &location.piece {
-                                if other_piece.owner.color != piece.owner.color {
-                                    attackable = true;
-                                } else {
-                                    // Friendly collision detected. Exit loop.
-                                    break;
-                                }
-                            }
-                        }
-                    }
+            // Friendly pieces are defended, not attacked.
+            if let Some(other_piece) = &location.piece
+                && other_piece.owner.color == piece.owner.color
+            {
+                continue;
+            }
 
-                    if attackable {
-                        match piece.owner.color {
-                            Color::White => {
-                                location.white_attackable = true;
-                            }
-                            Color::Black => {
-                                location.black_attackable = true;
-                            }
-                        }
-                    }
+            match piece.owner.color {
+                Color::White => {
+                    location.white_attackable = true;
+                }
+                Color::Black => {
+                    location.black_attackable = true;
                 }
             }
         }
@@ -609,6 +1017,9 @@ impl Game {
     }
 }
 
+/// Each square as ` OP `: the owner's piece character and the piece letter, or ` __ ` when
+/// empty, with Black at the top. See [`BoardRenderer`](crate::render::BoardRenderer) for
+/// coordinates, glyphs, colors and highlights.
 impl fmt::Display for Game {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut text = "".to_string();
@@ -656,6 +1067,63 @@ impl fmt::Display for Game {
     }
 }
 
+// Derives has_moved for a piece placed from a position: pawns off their starting rank have moved,
+// and kings and rooks have moved unless a castling right still depends on them.
+fn setup_has_moved(
+    position: &Position,
+    color: Color,
+    piece_type: PieceType,
+    coords: &LocationCoords,
+) -> bool {
+    let (kingside, queenside, home_rank, pawn_rank) = match color {
+        Color::White => (
+            position.castling.white_kingside,
+            position.castling.white_queenside,
+            7,
+            6,
+        ),
+        Color::Black => (
+            position.castling.black_kingside,
+            position.castling.black_queenside,
+            0,
+            1,
+        ),
+    };
+
+    match piece_type {
+        PieceType::Pawn => coords.y != pawn_rank,
+        PieceType::King => !(coords.y == home_rank && coords.x == 4 && (kingside || queenside)),
+        PieceType::Rook => {
+            let kingside_rook = coords.y == home_rank && coords.x == 7 && kingside;
+            let queenside_rook = coords.y == home_rank && coords.x == 0 && queenside;
+            !(kingside_rook || queenside_rook)
+        }
+        _ => false,
+    }
+}
+
+// How the game stands with `position` on the board for the `repetitions`-th time, by the rules
+// alone.
+pub(crate) fn position_status(position: &Position, repetitions: usize) -> GameStatus {
+    if position.legal_moves().is_empty() {
+        if position.is_in_check(position.side_to_move) {
+            GameStatus::Checkmate {
+                winner: position.side_to_move.opponent(),
+            }
+        } else {
+            GameStatus::Draw(DrawReason::Stalemate)
+        }
+    } else if position.is_insufficient_material() {
+        GameStatus::Draw(DrawReason::InsufficientMaterial)
+    } else if repetitions >= 5 {
+        GameStatus::Draw(DrawReason::FivefoldRepetition)
+    } else if position.halfmove_clock >= 150 {
+        GameStatus::Draw(DrawReason::SeventyFiveMoveRule)
+    } else {
+        GameStatus::Ongoing
+    }
+}
+
 fn get_move_vector(source: &LocationCoords, dest: &LocationCoords) -> (i32, i32) {
     (
         dest.x as i32 - source.x as i32,
@@ -663,6 +1131,49 @@ fn get_move_vector(source: &LocationCoords, dest: &LocationCoords) -> (i32, i32)
     )
 }
 
+/// Whether a game goes on, or how the rules ended it. Resignations, time losses and agreed or
+/// claimed draws are up to the front end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameStatus {
+    /// The side to move has legal moves and no automatic draw applies.
+    Ongoing,
+    /// `winner` checkmated the other side, which is left to move.
+    Checkmate { winner: Color },
+    /// The game is drawn without a claim.
+    Draw(DrawReason),
+}
+
+/// Draws the rules impose without a claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawReason {
+    /// The side to move has no legal moves and is not in check.
+    Stalemate,
+    /// Neither side has the material to checkmate.
+    InsufficientMaterial,
+    /// The same position occurred for the fifth time.
+    FivefoldRepetition,
+    /// 75 moves by each side without a capture or pawn move.
+    SeventyFiveMoveRule,
+}
+
+/// How a legal move to a [`Destination`] changes the board, for move hints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveKind {
+    /// A move to an empty square.
+    Quiet,
+    /// Takes the enemy piece on the destination square.
+    Capture,
+    /// Castling, en passant or a promotion, capturing or not.
+    Special,
+}
+
+/// A square a piece can legally move to. See [`Game::legal_destinations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Destination {
+    pub square: LocationCoords,
+    pub kind: MoveKind,
+}
+
 // Mode set for regular moves vs attack map (check / victory conditions)
 pub enum GatherPointsMode {
     Exclusive,
@@ -699,7 +1210,7 @@ pub fn points_along_vector(
         let y = source.y as i32 + k as i32 * step_y;
 
         // Ensure any point returned is in the bounds of the board
-        if (x >= 0 && x <= 7) && (y >= 0 && y <= 7) {
+        if (0..=7).contains(&x) && (0..=7).contains(&y) {
             points.push(LocationCoords {
                 x: x as usize,
                 y: y as usize,
@@ -709,3 +1220,85 @@ pub fn points_along_vector(
 
     points
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_from_fen(fen: &str) -> Game {
+        let position = Position::from_fen(fen).unwrap();
+        Game::from_position(
+            Player::with_rc("White", Color::White),
+            Player::with_rc("Black", Color::Black),
+            &position,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn stalemate_ends_the_game() {
+        // Qc7 leaves the black king on a8 without a move.
+        let mut game = game_from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1");
+        game.move_piece(
+            LocationCoords::from_algebraic("c1").unwrap(),
+            LocationCoords::from_algebraic("c7").unwrap(),
+        );
+
+        assert_eq!(game.status(), GameStatus::Draw(DrawReason::Stalemate));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn checkmate_names_the_winner() {
+        let mut game = game_from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1");
+        game.move_piece(
+            LocationCoords::from_algebraic("c1").unwrap(),
+            LocationCoords::from_algebraic("c8").unwrap(),
+        );
+
+        assert_eq!(
+            game.status(),
+            GameStatus::Checkmate {
+                winner: Color::White
+            }
+        );
+        assert_eq!(game.current_player.color, Color::Black);
+        assert!(game.to_position().is_checkmate());
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut game = game_from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1");
+        let shuffle = ["c1c2", "a8b8", "c2c1", "b8a8"];
+
+        for (i, uci) in shuffle.iter().cycle().take(16).enumerate() {
+            assert_eq!(game.status(), GameStatus::Ongoing, "before move {}", i + 1);
+            game.make_move(&ChessMove::from_uci(uci).unwrap());
+        }
+
+        assert_eq!(game.repetitions(), 5);
+        assert_eq!(
+            game.status(),
+            GameStatus::Draw(DrawReason::FivefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn take_back_replays_from_the_start() {
+        let mut game = game_from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1");
+        let id = game.id;
+        for uci in ["c1c2", "a8b8", "c2c8"] {
+            game.make_move(&ChessMove::from_uci(uci).unwrap());
+        }
+        assert_eq!(game.san_moves(), ["Qc2", "Kb8", "Qc8+"]);
+
+        assert_eq!(game.take_back(), ChessMove::from_uci("c2c8"));
+        assert_eq!(game.status(), GameStatus::Ongoing);
+        assert_eq!(game.id, id);
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(
+            game.to_position().to_fen(),
+            "1k6/8/1K6/8/8/8/2Q5/8 w - - 2 2"
+        );
+    }
+}
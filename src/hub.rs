@@ -0,0 +1,63 @@
+// The threads of the servers that keep every game on one thread, as games are not `Send`. One
+// thread accepts connections, each connection gets a thread that passes what it reads to the
+// hub as events, and the hub handles them all on the thread that called `serve`.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+// What connection threads tell the hub. `S` is how the hub writes back to the connection.
+#[derive(Debug)]
+pub(crate) enum Event<S> {
+    Connected(u64, S),
+    Message(u64, String),
+    Disconnected(u64),
+}
+
+// Owns the sessions and games of a server.
+pub(crate) trait Hub {
+    type Sink;
+
+    fn handle(&mut self, event: Event<Self::Sink>);
+
+    // Runs after every event, and at least once per tick without any, for clocks and timeouts.
+    fn expire(&mut self, now: Instant);
+}
+
+// Serves each connection to `listener` with `connection` on a thread of its own, and runs `hub`
+// on this thread until the process ends.
+pub(crate) fn serve<H>(
+    listener: TcpListener,
+    mut hub: H,
+    tick: Duration,
+    connection: fn(u64, TcpStream, Sender<Event<H::Sink>>),
+) where
+    H: Hub,
+    H::Sink: Send + 'static,
+{
+    let (events, hub_events) = mpsc::channel();
+
+    thread::spawn(move || {
+        for (id, stream) in (1..).zip(listener.incoming()) {
+            match stream {
+                Ok(stream) => {
+                    let events = events.clone();
+                    thread::spawn(move || connection(id, stream, events));
+                }
+                Err(error) => warn!(%error, "could not accept a connection"),
+            }
+        }
+    });
+
+    loop {
+        match hub_events.recv_timeout(tick) {
+            Ok(event) => hub.handle(event),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        hub.expire(Instant::now());
+    }
+}
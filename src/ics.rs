@@ -0,0 +1,1208 @@
+//! A telnet server speaking a subset of the FICS protocol, for ICS interfaces.
+//!
+//! Clients made for the Free Internet Chess Server, or a plain `telnet`, connect to [`serve`] and
+//! log in with any free name, or `guest` for a generated one. There are no accounts or ratings,
+//! so every game is unrated. The commands are:
+//!
+//! * `seek [<time> [<inc>]] [white|black]` to post a seek, `unseek`, `sought` to list them, and
+//!   `play <n|name>` to answer one.
+//! * `match <name> [<time> [<inc>]] [white|black]`, answered with `accept` or `decline`.
+//! * Moves in coordinates or SAN while playing, and `resign`.
+//! * `observe <n|name>`, `unobserve [<n>]`, `refresh`, `games` and `who`.
+//! * `set style 12`, `help` and `quit`.
+//!
+//! Boards are always sent as style 12 lines. Moves are checked against the [`Game`] and only
+//! taken from the player whose color matches [`Game::current_player`]. Clocks run on the server,
+//! and a time control of `0 0` plays untimed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use crate::game::{DrawReason, Game, GameStatus};
+use crate::hub::{self, Event};
+use crate::movegen::ChessMove;
+use crate::player::{Color, Player};
+use crate::position::{Position, STARTING_FEN, piece_to_char};
+use crate::repl::{automatic_outcome, parse_move};
+use crate::san::SanError;
+
+/// Address [`serve`] listens on unless told otherwise. 5000 is the usual ICS port.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:5000";
+
+const PROMPT: &str = "fics% ";
+
+// Time control of seeks and match offers that give none, in minutes and seconds.
+const DEFAULT_TIME: u32 = 5;
+const DEFAULT_INCREMENT: u32 = 0;
+// Largest time or increment a seek or match offer may ask for.
+const MAX_TIME: u32 = 999;
+const MAX_SEEKS: usize = 3;
+
+// How often the hub looks for fallen flags.
+const HUB_TICK: Duration = Duration::from_millis(100);
+// Longest a slow client may hold up the hub when written to.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Telnet commands, which clients may send to negotiate options.
+const IAC: u8 = 255;
+const WILL: u8 = 251;
+const DONT: u8 = 254;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const BANNER: &str = "\
+Welcome to rs_chess ICS, a FICS-style chess server.
+
+Log in with any name of letters only, or \"guest\" for a generated one.
+All games are unrated.
+
+login: ";
+
+const HELP: &str = "\
+Commands:
+  seek [<time> [<inc>]] [white|black]           post a seek; 0 0 plays untimed
+  unseek                                        remove your seeks
+  sought                                        list the seeks
+  play <n|name>                                 answer a seek
+  match <name> [<time> [<inc>]] [white|black]   offer a match
+  accept [<name>], decline [<name>]             answer a match offer
+  <move>                                        move, in coordinates (e2e4) or SAN (Nf3)
+  resign                                        resign your game
+  observe <n|name>, unobserve [<n>]             watch games
+  refresh                                       show your boards again
+  games, who                                    list the games and players
+  set style 12                                  boards are always sent in style 12
+  quit                                          log out";
+
+#[derive(Debug)]
+enum Login {
+    Name,
+    // Waiting for return to enter as the name.
+    Confirm(String),
+    Done(String),
+}
+
+#[derive(Debug)]
+struct Session {
+    stream: TcpStream,
+    login: Login,
+    game: Option<usize>,
+    observing: Vec<usize>,
+}
+
+// Minutes on each clock and seconds added per move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TimeControl {
+    minutes: u32,
+    increment: u32,
+}
+
+impl TimeControl {
+    fn timed(&self) -> bool {
+        self.minutes > 0 || self.increment > 0
+    }
+
+    // FICS categories, by the expected time per player: the minutes and 40 moves of increment.
+    fn category(&self) -> &'static str {
+        let expected = u64::from(self.minutes) * 60 + u64::from(self.increment) * 40;
+        if !self.timed() {
+            "untimed"
+        } else if expected < 180 {
+            "lightning"
+        } else if expected < 900 {
+            "blitz"
+        } else {
+            "standard"
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Seek {
+    number: usize,
+    owner: u64,
+    time: TimeControl,
+    // The color the seeker plays, if they asked for one.
+    color: Option<Color>,
+}
+
+#[derive(Debug)]
+struct Challenge {
+    from: u64,
+    to: u64,
+    time: TimeControl,
+    // The color the challenger plays, if they asked for one.
+    color: Option<Color>,
+}
+
+#[derive(Debug)]
+struct LastMove {
+    verbose: String,
+    san: String,
+    took: Duration,
+}
+
+struct IcsGame {
+    game: Game,
+    // Connections and names of white and black, by `Color::index`.
+    players: [u64; 2],
+    names: [String; 2],
+    time: TimeControl,
+    // Time left for each side at the start of its turn.
+    clocks: [Duration; 2],
+    turn_started: Instant,
+    last_move: Option<LastMove>,
+    observers: Vec<u64>,
+}
+
+impl IcsGame {
+    fn color_of(&self, connection: u64) -> Option<Color> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| self.players[color.index()] == connection)
+    }
+
+    // As on FICS, clocks only run once both sides have made their first move.
+    fn ticking(&self) -> bool {
+        self.time.timed() && self.game.fullmove_number > 1
+    }
+
+    fn remaining(&self, color: Color, now: Instant) -> Duration {
+        let clock = self.clocks[color.index()];
+        if self.ticking() && self.game.current_player.color == color {
+            clock.saturating_sub(now.duration_since(self.turn_started))
+        } else {
+            clock
+        }
+    }
+
+    // The side to move, once its time has run out.
+    fn flagged(&self, now: Instant) -> Option<Color> {
+        let color = self.game.current_player.color;
+        (self.ticking() && self.remaining(color, now).is_zero()).then_some(color)
+    }
+
+    fn play(&mut self, chess_move: &ChessMove, now: Instant) {
+        let color = self.game.current_player.color;
+        let took = now.duration_since(self.turn_started);
+        let ticking = self.ticking();
+        let clock = &mut self.clocks[color.index()];
+        if ticking {
+            *clock = clock.saturating_sub(took);
+        }
+        *clock += Duration::from_secs(self.time.increment.into());
+
+        let verbose = verbose_move(&self.game.to_position(), chess_move);
+        self.game.make_move(chess_move);
+        self.last_move = Some(LastMove {
+            verbose,
+            san: self.game.san_moves().last().cloned().unwrap_or_default(),
+            took,
+        });
+        self.turn_started = now;
+    }
+
+    // The result and FICS reason, if the last move ended the game.
+    fn outcome(&self) -> Option<(&'static str, String)> {
+        let outcome = automatic_outcome(&self.game)?;
+        let reason = match self.game.status() {
+            GameStatus::Ongoing => return None,
+            GameStatus::Checkmate { winner } => {
+                format!("{} checkmated", self.names[winner.opponent().index()])
+            }
+            GameStatus::Draw(reason) => match reason {
+                DrawReason::Stalemate => "Game drawn by stalemate",
+                DrawReason::InsufficientMaterial => "Neither player has mating material",
+                DrawReason::FivefoldRepetition => "Game drawn by repetition",
+                DrawReason::SeventyFiveMoveRule => "Game drawn by the 75 move rule",
+            }
+            .to_string(),
+        };
+
+        Some((outcome.result, reason))
+    }
+
+    fn title(&self, number: usize) -> String {
+        format!("Game {} ({} vs. {})", number, self.names[0], self.names[1])
+    }
+
+    // The board as a style 12 line. `relation` is 1 or -1 for a player whose move it is or not,
+    // and 0 for an observer; `flip` puts black at the bottom.
+    fn style12(&self, number: usize, relation: i32, flip: bool, now: Instant) -> String {
+        let position = &self.game.to_position();
+        let ranks: Vec<String> = position
+            .squares
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|square| square.map_or('-', |(color, kind)| piece_to_char(color, kind)))
+                    .collect()
+            })
+            .collect();
+        let side = match position.side_to_move {
+            Color::White => 'W',
+            Color::Black => 'B',
+        };
+        // The file of a pawn that just moved two squares.
+        let double_push = self.game.en_passant.map_or(-1, |square| square.x as i64);
+        let castling = position.castling;
+        let (verbose, took, san) = match &self.last_move {
+            Some(last) => (last.verbose.as_str(), last.took, last.san.as_str()),
+            None => ("none", Duration::ZERO, "none"),
+        };
+
+        format!(
+            concat!(
+                "<12> {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} ",
+                "{} {} {} {} ({}:{:02}) {} {} {} 0"
+            ),
+            ranks.join(" "),
+            side,
+            double_push,
+            u8::from(castling.white_kingside),
+            u8::from(castling.white_queenside),
+            u8::from(castling.black_kingside),
+            u8::from(castling.black_queenside),
+            position.halfmove_clock,
+            number,
+            self.names[0],
+            self.names[1],
+            relation,
+            self.time.minutes,
+            self.time.increment,
+            material(position, Color::White),
+            material(position, Color::Black),
+            self.remaining(Color::White, now).as_secs(),
+            self.remaining(Color::Black, now).as_secs(),
+            position.fullmove_number,
+            verbose,
+            took.as_secs() / 60,
+            took.as_secs() % 60,
+            san,
+            u8::from(flip),
+            u8::from(self.ticking()),
+        )
+    }
+}
+
+// Owns every session and game.
+#[derive(Default)]
+struct Hub {
+    sessions: HashMap<u64, Session>,
+    seeks: Vec<Seek>,
+    challenges: Vec<Challenge>,
+    games: BTreeMap<usize, IcsGame>,
+}
+
+impl hub::Hub for Hub {
+    type Sink = TcpStream;
+
+    fn handle(&mut self, event: Event<TcpStream>) {
+        match event {
+            Event::Connected(connection, stream) => {
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                let session = Session {
+                    stream,
+                    login: Login::Name,
+                    game: None,
+                    observing: vec![],
+                };
+                self.sessions.insert(connection, session);
+                self.write(connection, BANNER);
+            }
+            Event::Message(connection, line) => {
+                debug!(connection, line, "received");
+                let Some(session) = self.sessions.get(&connection) else {
+                    return;
+                };
+                match &session.login {
+                    Login::Name => self.log_in(connection, line.trim()),
+                    Login::Confirm(name) => {
+                        let name = name.clone();
+                        self.confirm(connection, name);
+                    }
+                    Login::Done(_) => {
+                        let line = line.trim();
+                        if matches!(line, "quit" | "exit" | "bye") {
+                            self.write(connection, "Logging you out.\n");
+                            let _ = session.stream.shutdown(Shutdown::Both);
+                            return;
+                        }
+                        let reply = self.command(connection, line);
+                        self.reply(connection, &reply);
+                    }
+                }
+            }
+            Event::Disconnected(connection) => {
+                self.leave(connection);
+                self.sessions.remove(&connection);
+            }
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let flagged: Vec<(usize, Color)> = self
+            .games
+            .iter()
+            .filter_map(|(number, game)| game.flagged(now).map(|color| (*number, color)))
+            .collect();
+        for (number, color) in flagged {
+            self.forfeit_on_time(number, color, None);
+        }
+    }
+}
+
+impl Hub {
+    fn log_in(&mut self, connection: u64, name: &str) {
+        let name = if name.eq_ignore_ascii_case("guest") {
+            loop {
+                let letters: String = (0..4).map(|_| fastrand::uppercase()).collect();
+                let name = format!("Guest{}", letters);
+                if !self.taken(&name) {
+                    break name;
+                }
+            }
+        } else if name.is_empty() {
+            self.write(connection, "login: ");
+            return;
+        } else if !(3..=17).contains(&name.len()) || !name.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            let text = concat!(
+                "Sorry, names may only use the letters a to z and be 3 to 17 characters long.",
+                "  Try again.\nlogin: "
+            );
+            self.write(connection, text);
+            return;
+        } else if self.taken(name) {
+            let text = format!(
+                "Sorry, \"{}\" is already logged in.  Try another name.\nlogin: ",
+                name
+            );
+            self.write(connection, &text);
+            return;
+        } else {
+            name.to_string()
+        };
+
+        let text = format!(
+            concat!(
+                "\"{}\" may be used to play unrated games.\n\n",
+                "Press return to enter the server as \"{}\": "
+            ),
+            name, name
+        );
+        self.write(connection, &text);
+        if let Some(session) = self.sessions.get_mut(&connection) {
+            session.login = Login::Confirm(name);
+        }
+    }
+
+    fn confirm(&mut self, connection: u64, name: String) {
+        let Some(session) = self.sessions.get_mut(&connection) else {
+            return;
+        };
+        session.login = Login::Done(name.clone());
+        info!(connection, name, "logged in");
+
+        let text = format!(
+            "\n**** Starting FICS session as {}(U) ****\n\n{}",
+            name, PROMPT
+        );
+        self.write(connection, &text);
+    }
+
+    fn command(&mut self, connection: u64, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((first, args)) = words.split_first() else {
+            return String::new();
+        };
+
+        match first.to_lowercase().as_str() {
+            "seek" => self.seek(connection, args),
+            "unseek" => self.unseek(connection),
+            "sought" => self.sought(),
+            "play" => self.play(connection, args),
+            "match" => self.challenge(connection, args),
+            "accept" => self.accept(connection, args),
+            "decline" => self.decline(connection, args),
+            "resign" => self.resign(connection),
+            "observe" => self.observe(connection, args),
+            "unobserve" => self.unobserve(connection, args),
+            "refresh" => self.refresh(connection),
+            "games" => self.list_games(),
+            "who" => self.who(),
+            "set" | "iset" => match args {
+                ["style", value] => set_style(value),
+                _ => String::new(),
+            },
+            "style" => set_style(args.first().unwrap_or(&"")),
+            "help" => HELP.to_string(),
+            _ => self.try_move(connection, line, first),
+        }
+    }
+
+    fn seek(&mut self, connection: u64, args: &[&str]) -> String {
+        if self.playing(connection).is_some() {
+            return "You are playing a game.".to_string();
+        }
+        let (time, color) = match parse_terms(args) {
+            Ok(terms) => terms,
+            Err(message) => return message,
+        };
+        if self.seeks.iter().filter(|s| s.owner == connection).count() >= MAX_SEEKS {
+            return format!("You can only have {} active seeks.", MAX_SEEKS);
+        }
+
+        let number = (1..)
+            .find(|n| self.seeks.iter().all(|seek| seek.number != *n))
+            .expect("there is a free seek number");
+        self.seeks.push(Seek {
+            number,
+            owner: connection,
+            time,
+            color,
+        });
+
+        let ad = format!(
+            "{} (++++) seeking {} {} unrated {}{} (\"play {}\" to respond)",
+            self.name(connection),
+            time.minutes,
+            time.increment,
+            time.category(),
+            color_term(color),
+            number
+        );
+        let others: Vec<u64> = self
+            .logged_in()
+            .filter(|other| *other != connection && self.playing(*other).is_none())
+            .collect();
+        for other in &others {
+            self.notify(*other, &ad);
+        }
+
+        format!(
+            "Your seek has been posted with index {}.\n({} player(s) saw the seek.)",
+            number,
+            others.len()
+        )
+    }
+
+    fn unseek(&mut self, connection: u64) -> String {
+        let before = self.seeks.len();
+        self.seeks.retain(|seek| seek.owner != connection);
+        if self.seeks.len() == before {
+            "You have no active seeks.".to_string()
+        } else {
+            "Your seeks have been removed.".to_string()
+        }
+    }
+
+    fn sought(&self) -> String {
+        let mut lines: Vec<String> = self
+            .seeks
+            .iter()
+            .map(|seek| {
+                format!(
+                    "{:3} ++++ {:<17} {:3} {:3} unrated {}{}",
+                    seek.number,
+                    self.name(seek.owner),
+                    seek.time.minutes,
+                    seek.time.increment,
+                    seek.time.category(),
+                    color_term(seek.color)
+                )
+            })
+            .collect();
+        lines.push(count(self.seeks.len(), "ad", "ads"));
+        lines.join("\n")
+    }
+
+    fn play(&mut self, connection: u64, args: &[&str]) -> String {
+        let [wanted] = args else {
+            return "Usage: play <n|name>".to_string();
+        };
+        let found = match wanted.parse::<usize>() {
+            Ok(number) => self.seeks.iter().position(|seek| seek.number == number),
+            Err(_) => self
+                .find(wanted)
+                .and_then(|owner| self.seeks.iter().position(|seek| seek.owner == owner)),
+        };
+        let Some(index) = found else {
+            return "That seek is not available.".to_string();
+        };
+        if self.seeks[index].owner == connection {
+            return "You cannot respond to your own seek ad.".to_string();
+        }
+        if self.playing(connection).is_some() {
+            return "You are playing a game.".to_string();
+        }
+
+        let seek = self.seeks.remove(index);
+        let owner_white = seek
+            .color
+            .map_or_else(fastrand::bool, |c| c == Color::White);
+        let white = if owner_white { seek.owner } else { connection };
+        self.start_game(connection, seek.owner, white, seek.time)
+    }
+
+    fn challenge(&mut self, connection: u64, args: &[&str]) -> String {
+        let Some((wanted, terms)) = args.split_first() else {
+            return "Usage: match <name> [<time> [<inc>]] [white|black]".to_string();
+        };
+        let Some(to) = self.find(wanted) else {
+            return format!("No player named \"{}\" is logged in.", wanted);
+        };
+        if to == connection {
+            return "You can't match yourself.".to_string();
+        }
+        if self.playing(connection).is_some() {
+            return "You are playing a game.".to_string();
+        }
+        if self.playing(to).is_some() {
+            return format!("{} is playing a game.", self.name(to));
+        }
+        let (time, color) = match parse_terms(terms) {
+            Ok(terms) => terms,
+            Err(message) => return message,
+        };
+
+        self.challenges
+            .retain(|c| !(c.from == connection && c.to == to));
+        self.challenges.push(Challenge {
+            from: connection,
+            to,
+            time,
+            color,
+        });
+
+        let offer = format!(
+            "{} (++++){} {} (++++) unrated {} {} {}.",
+            self.name(connection),
+            color_term(color),
+            self.name(to),
+            time.category(),
+            time.minutes,
+            time.increment
+        );
+        let text = format!(
+            "Challenge: {}\nYou can \"accept\" or \"decline\", or propose different parameters.",
+            offer
+        );
+        self.notify(to, &text);
+        format!("Issuing: {}", offer)
+    }
+
+    // The index of the match offer to `connection` from `args`' name, or the only one.
+    fn offer(&self, connection: u64, args: &[&str]) -> Result<usize, String> {
+        let from = match args {
+            [] => None,
+            [name] => match self.find(name) {
+                Some(from) => Some(from),
+                None => return Err(format!("No player named \"{}\" is logged in.", name)),
+            },
+            _ => return Err("Give at most one name.".to_string()),
+        };
+        let offers: Vec<usize> = (0..self.challenges.len())
+            .filter(|i| self.challenges[*i].to == connection)
+            .filter(|i| from.is_none_or(|from| self.challenges[*i].from == from))
+            .collect();
+        match offers[..] {
+            [] => Err("You have no matching offers.".to_string()),
+            [index] => Ok(index),
+            _ => Err("You have more than one offer; give the name of the player.".to_string()),
+        }
+    }
+
+    fn accept(&mut self, connection: u64, args: &[&str]) -> String {
+        let index = match self.offer(connection, args) {
+            Ok(index) => index,
+            Err(message) => return message,
+        };
+        let challenge = self.challenges.remove(index);
+        let challenger_white = challenge
+            .color
+            .map_or_else(fastrand::bool, |c| c == Color::White);
+        let white = if challenger_white {
+            challenge.from
+        } else {
+            connection
+        };
+
+        let text = format!("{} accepts your match offer.", self.name(connection));
+        self.notify(challenge.from, &text);
+        self.start_game(connection, challenge.from, white, challenge.time)
+    }
+
+    fn decline(&mut self, connection: u64, args: &[&str]) -> String {
+        let index = match self.offer(connection, args) {
+            Ok(index) => index,
+            Err(message) => return message,
+        };
+        let challenge = self.challenges.remove(index);
+        let text = format!("{} declines your match offer.", self.name(connection));
+        self.notify(challenge.from, &text);
+        format!(
+            "You decline the match offer from {}.",
+            self.name(challenge.from)
+        )
+    }
+
+    // Starts a game between `initiator` and `other` and tells `other`. Returns what to tell
+    // `initiator`.
+    fn start_game(&mut self, initiator: u64, other: u64, white: u64, time: TimeControl) -> String {
+        let black = if white == initiator { other } else { initiator };
+        let involved = |connection: u64| connection == initiator || connection == other;
+        self.seeks.retain(|seek| !involved(seek.owner));
+        self.challenges
+            .retain(|c| !involved(c.from) && !involved(c.to));
+
+        let number = (1..)
+            .find(|n| !self.games.contains_key(n))
+            .expect("there is a free game number");
+        let names = [self.name(white), self.name(black)];
+        let position = Position::from_fen(STARTING_FEN).expect("the starting FEN is valid");
+        let game = Game::from_position(
+            Player::with_rc(&names[0], Color::White),
+            Player::with_rc(&names[1], Color::Black),
+            &position,
+        )
+        .expect("the starting position is valid");
+        let clock = Duration::from_secs(u64::from(time.minutes) * 60);
+        info!(number, white = names[0], black = names[1], "game started");
+
+        let ics_game = IcsGame {
+            game,
+            players: [white, black],
+            names,
+            time,
+            clocks: [clock, clock],
+            turn_started: Instant::now(),
+            last_move: None,
+            observers: vec![],
+        };
+        let creating = format!(
+            "Creating: {} (++++) {} (++++) unrated {} {} {}\n{{{} Creating unrated {} match.}}",
+            ics_game.names[0],
+            ics_game.names[1],
+            time.category(),
+            time.minutes,
+            time.increment,
+            ics_game.title(number),
+            time.category()
+        );
+        self.games.insert(number, ics_game);
+        for player in [white, black] {
+            if let Some(session) = self.sessions.get_mut(&player) {
+                session.game = Some(number);
+            }
+        }
+
+        let now = Instant::now();
+        let text = format!("{}\n\n{}", creating, self.board(number, other, now));
+        self.notify(other, &text);
+        format!("{}\n\n{}", creating, self.board(number, initiator, now))
+    }
+
+    fn try_move(&mut self, connection: u64, line: &str, first: &str) -> String {
+        let not_found = format!("{}: Command not found.", first);
+        let Some(number) = self.playing(connection) else {
+            return not_found;
+        };
+        let now = Instant::now();
+        let game = self.games.get_mut(&number).expect("players' games exist");
+        let color = game.color_of(connection).expect("players have a color");
+
+        let parsed = parse_move(&game.game.to_position(), line);
+        if let Err(SanError::Invalid(_)) = parsed {
+            return not_found;
+        }
+        if game.game.current_player.color != color {
+            return "It is not your move.".to_string();
+        }
+        let Ok(chess_move) = parsed else {
+            return format!("Illegal move ({}).", line);
+        };
+        if game.flagged(now) == Some(color) {
+            return self.forfeit_on_time(number, color, Some(connection));
+        }
+
+        game.play(&chess_move, now);
+        let outcome = game.outcome();
+        let others: Vec<u64> = game
+            .players
+            .iter()
+            .chain(&game.observers)
+            .copied()
+            .filter(|other| *other != connection)
+            .collect();
+        for other in others {
+            let board = self.board(number, other, now);
+            self.notify(other, &board);
+        }
+
+        let board = self.board(number, connection, now);
+        match outcome {
+            Some((result, reason)) => {
+                let end = self.finish(number, result, &reason, Some(connection));
+                format!("{}\n\n{}", board, end)
+            }
+            None => board,
+        }
+    }
+
+    fn resign(&mut self, connection: u64) -> String {
+        let Some(number) = self.playing(connection) else {
+            return "You are not playing a game.".to_string();
+        };
+        let game = &self.games[&number];
+        let color = game.color_of(connection).expect("players have a color");
+        let reason = format!("{} resigns", game.names[color.index()]);
+        self.finish(number, win(color.opponent()), &reason, Some(connection))
+    }
+
+    // Ends a game lost on time by `color`, or drawn if the other side could never mate.
+    fn forfeit_on_time(&mut self, number: usize, color: Color, except: Option<u64>) -> String {
+        let game = &self.games[&number];
+        let winner = color.opponent();
+        let loser_name = &game.names[color.index()];
+
+        if game.game.to_position().has_mating_material(winner) {
+            let reason = format!("{} forfeits on time", loser_name);
+            self.finish(number, win(winner), &reason, except)
+        } else {
+            let reason = format!(
+                "{} ran out of time and {} has no material to mate",
+                loser_name,
+                game.names[winner.index()]
+            );
+            self.finish(number, "1/2-1/2", &reason, except)
+        }
+    }
+
+    // Ends a game and tells its players and observers, except `except`. Returns what to tell
+    // `except`.
+    fn finish(&mut self, number: usize, result: &str, reason: &str, except: Option<u64>) -> String {
+        let Some(game) = self.games.remove(&number) else {
+            return String::new();
+        };
+        info!(number, result, reason, "game over");
+
+        let message = format!("{{{} {}}} {}", game.title(number), reason, result);
+        let player_message = format!("{}\nNo ratings adjustment done.", message);
+        for player in game.players {
+            if let Some(session) = self.sessions.get_mut(&player) {
+                session.game = None;
+            }
+        }
+        for observer in &game.observers {
+            if let Some(session) = self.sessions.get_mut(observer) {
+                session.observing.retain(|n| *n != number);
+            }
+        }
+
+        let participants = game.players.iter().map(|p| (p, &player_message));
+        let observers = game.observers.iter().map(|o| (o, &message));
+        let mut own = String::new();
+        for (connection, text) in participants.chain(observers) {
+            if Some(*connection) == except {
+                own = text.clone();
+            } else {
+                self.notify(*connection, text);
+            }
+        }
+        own
+    }
+
+    fn observe(&mut self, connection: u64, args: &[&str]) -> String {
+        let [wanted] = args else {
+            return "Usage: observe <n|name>".to_string();
+        };
+        let number = match wanted.parse::<usize>() {
+            Ok(number) => Some(number).filter(|n| self.games.contains_key(n)),
+            Err(_) => self.find(wanted).and_then(|player| self.playing(player)),
+        };
+        let Some(number) = number else {
+            return "There is no such game.".to_string();
+        };
+        if self.playing(connection) == Some(number) {
+            return format!("You are playing game {}.", number);
+        }
+        let game = self.games.get_mut(&number).expect("the game exists");
+        if game.observers.contains(&connection) {
+            return format!("You are already observing game {}.", number);
+        }
+
+        game.observers.push(connection);
+        if let Some(session) = self.sessions.get_mut(&connection) {
+            session.observing.push(number);
+        }
+        let game = &self.games[&number];
+        format!(
+            "You are now observing game {}.\nGame {}: {} (++++) {} (++++) unrated {} {} {}\n\n{}",
+            number,
+            number,
+            game.names[0],
+            game.names[1],
+            game.time.category(),
+            game.time.minutes,
+            game.time.increment,
+            self.board(number, connection, Instant::now())
+        )
+    }
+
+    fn unobserve(&mut self, connection: u64, args: &[&str]) -> String {
+        let Some(session) = self.sessions.get_mut(&connection) else {
+            return String::new();
+        };
+        let numbers: Vec<usize> = match args {
+            [] => session.observing.drain(..).collect(),
+            [wanted] => match wanted.parse::<usize>() {
+                Ok(number) if session.observing.contains(&number) => {
+                    session.observing.retain(|n| *n != number);
+                    vec![number]
+                }
+                _ => vec![],
+            },
+            _ => return "Usage: unobserve [<n>]".to_string(),
+        };
+        if numbers.is_empty() {
+            return "You are not observing that game.".to_string();
+        }
+
+        let mut lines = vec![];
+        for number in numbers {
+            if let Some(game) = self.games.get_mut(&number) {
+                game.observers.retain(|o| *o != connection);
+            }
+            lines.push(format!("Removing game {} from observation list.", number));
+        }
+        lines.join("\n")
+    }
+
+    fn refresh(&self, connection: u64) -> String {
+        let Some(session) = self.sessions.get(&connection) else {
+            return String::new();
+        };
+        let now = Instant::now();
+        let boards: Vec<String> = session
+            .game
+            .iter()
+            .chain(&session.observing)
+            .map(|number| self.board(*number, connection, now))
+            .collect();
+        if boards.is_empty() {
+            "You are neither playing nor observing a game.".to_string()
+        } else {
+            boards.join("\n")
+        }
+    }
+
+    fn list_games(&self) -> String {
+        let now = Instant::now();
+        let mut lines: Vec<String> = self
+            .games
+            .iter()
+            .map(|(number, game)| {
+                let position = game.game.to_position();
+                let side = match position.side_to_move {
+                    Color::White => 'W',
+                    Color::Black => 'B',
+                };
+                format!(
+                    "{:3} ++++ {:<11} ++++ {:<11} [ {}u{:3} {:3}] {} -{} ({:2}-{:2}) {}: {:2}",
+                    number,
+                    game.names[0],
+                    game.names[1],
+                    &game.time.category()[..1],
+                    game.time.minutes,
+                    game.time.increment,
+                    clock(game.remaining(Color::White, now)),
+                    clock(game.remaining(Color::Black, now)),
+                    material(&position, Color::White),
+                    material(&position, Color::Black),
+                    side,
+                    position.fullmove_number
+                )
+            })
+            .collect();
+        lines.push(count(self.games.len(), "game", "games"));
+        lines.join("\n")
+    }
+
+    fn who(&self) -> String {
+        let mut names: Vec<(String, bool)> = self
+            .logged_in()
+            .map(|connection| (self.name(connection), self.playing(connection).is_some()))
+            .collect();
+        names.sort_by_key(|(name, _)| name.to_lowercase());
+        let mut lines: Vec<String> = names
+            .iter()
+            .map(|(name, playing)| format!("++++{}{}", if *playing { '^' } else { ' ' }, name))
+            .collect();
+        lines.push(count(names.len(), "player", "players"));
+        lines.join("\n")
+    }
+
+    // A player that leaves loses their game, and their seeks and offers go with them.
+    fn leave(&mut self, connection: u64) {
+        self.seeks.retain(|seek| seek.owner != connection);
+        self.challenges
+            .retain(|c| c.from != connection && c.to != connection);
+        for game in self.games.values_mut() {
+            game.observers.retain(|o| *o != connection);
+        }
+
+        if let Some(number) = self.playing(connection) {
+            let game = &self.games[&number];
+            let color = game.color_of(connection).expect("players have a color");
+            let reason = format!("{} forfeits by disconnection", game.names[color.index()]);
+            self.finish(number, win(color.opponent()), &reason, Some(connection));
+        }
+        if let Some(Session {
+            login: Login::Done(name),
+            ..
+        }) = self.sessions.get(&connection)
+        {
+            info!(connection, name, "logged out");
+        }
+    }
+
+    // The board of game `number` as `connection` sees it.
+    fn board(&self, number: usize, connection: u64, now: Instant) -> String {
+        let game = &self.games[&number];
+        match game.color_of(connection) {
+            Some(color) => {
+                let relation = if game.game.current_player.color == color {
+                    1
+                } else {
+                    -1
+                };
+                game.style12(number, relation, color == Color::Black, now)
+            }
+            None => game.style12(number, 0, false, now),
+        }
+    }
+
+    fn playing(&self, connection: u64) -> Option<usize> {
+        self.sessions.get(&connection).and_then(|s| s.game)
+    }
+
+    fn name(&self, connection: u64) -> String {
+        match self.sessions.get(&connection).map(|s| &s.login) {
+            Some(Login::Done(name)) => name.clone(),
+            _ => String::new(),
+        }
+    }
+
+    fn logged_in(&self) -> impl Iterator<Item = u64> + '_ {
+        self.sessions
+            .iter()
+            .filter(|(_, session)| matches!(session.login, Login::Done(_)))
+            .map(|(connection, _)| *connection)
+    }
+
+    // The logged in player called `name`, in any case.
+    fn find(&self, name: &str) -> Option<u64> {
+        self.logged_in()
+            .find(|connection| self.name(*connection).eq_ignore_ascii_case(name))
+    }
+
+    // Whether `name` is in use or about to be.
+    fn taken(&self, name: &str) -> bool {
+        self.sessions.values().any(|session| match &session.login {
+            Login::Confirm(other) | Login::Done(other) => other.eq_ignore_ascii_case(name),
+            Login::Name => false,
+        })
+    }
+
+    // Telnet wants carriage returns before line feeds.
+    fn write(&self, connection: u64, text: &str) {
+        if let Some(session) = self.sessions.get(&connection) {
+            let _ = (&session.stream).write_all(text.replace('\n', "\r\n").as_bytes());
+        }
+    }
+
+    // Answers a command, then prompts for the next.
+    fn reply(&self, connection: u64, text: &str) {
+        if text.is_empty() {
+            self.write(connection, PROMPT);
+        } else {
+            self.write(connection, &format!("{}\n{}", text, PROMPT));
+        }
+    }
+
+    // Sends a message the player did not ask for, on its own lines.
+    fn notify(&self, connection: u64, text: &str) {
+        if matches!(
+            self.sessions.get(&connection).map(|s| &s.login),
+            Some(Login::Done(_))
+        ) {
+            self.write(connection, &format!("\n{}\n{}", text, PROMPT));
+        }
+    }
+}
+
+/// Serves ICS clients on `address`, e.g. `127.0.0.1:5000`, until the process ends.
+pub fn serve(address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    info!(address, "serving ICS");
+
+    hub::serve(listener, Hub::default(), HUB_TICK, run_connection);
+    Ok(())
+}
+
+// Passes the client's lines to the hub, which writes to the client directly.
+fn run_connection(connection: u64, stream: TcpStream, events: Sender<Event<TcpStream>>) {
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    if events.send(Event::Connected(connection, writer)).is_err() {
+        return;
+    }
+
+    let mut reader = BufReader::new(stream);
+    let mut bytes = vec![];
+    loop {
+        bytes.clear();
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if events
+                    .send(Event::Message(connection, telnet_text(&bytes)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    }
+    let _ = events.send(Event::Disconnected(connection));
+}
+
+// The text of a line from a telnet client, without option negotiation or control characters.
+fn telnet_text(bytes: &[u8]) -> String {
+    let mut text = Vec::with_capacity(bytes.len());
+    let mut rest = bytes.iter().copied();
+    while let Some(byte) = rest.next() {
+        match byte {
+            IAC => match rest.next() {
+                Some(IAC) => text.push(IAC),
+                // Subnegotiation runs until IAC SE.
+                Some(SB) => {
+                    let mut previous = 0;
+                    for byte in rest.by_ref() {
+                        if previous == IAC && byte == SE {
+                            break;
+                        }
+                        previous = byte;
+                    }
+                }
+                Some(WILL..=DONT) => {
+                    rest.next();
+                }
+                _ => {}
+            },
+            byte if byte.is_ascii_control() => {}
+            byte => text.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+// Reads the time control and color of a seek or match offer.
+fn parse_terms(words: &[&str]) -> Result<(TimeControl, Option<Color>), String> {
+    let mut numbers = vec![];
+    let mut color = None;
+    for word in words {
+        match word.to_lowercase().as_str() {
+            "white" | "w" => color = Some(Color::White),
+            "black" | "b" => color = Some(Color::Black),
+            "unrated" | "u" => {}
+            "rated" | "r" => return Err("Only unrated games are played here.".to_string()),
+            other => match other.parse::<u32>() {
+                Ok(number) if number <= MAX_TIME && numbers.len() < 2 => numbers.push(number),
+                _ => return Err(format!("Bad parameter \"{}\".", word)),
+            },
+        }
+    }
+
+    let time = TimeControl {
+        minutes: numbers.first().copied().unwrap_or(DEFAULT_TIME),
+        increment: numbers.get(1).copied().unwrap_or(DEFAULT_INCREMENT),
+    };
+    Ok((time, color))
+}
+
+fn set_style(value: &str) -> String {
+    match value {
+        "12" => "Style 12 set.".to_string(),
+        _ => "Only style 12 is supported.".to_string(),
+    }
+}
+
+// FICS verbose notation, e.g. `P/e2-e4`, `N/g8-f6`, `P/e7-e8=Q` or `o-o`.
+fn verbose_move(position: &Position, chess_move: &ChessMove) -> String {
+    if position.is_castling(chess_move) {
+        let side = if chess_move.to.x > chess_move.from.x {
+            "o-o"
+        } else {
+            "o-o-o"
+        };
+        return side.to_string();
+    }
+
+    let piece = position
+        .piece_at(&chess_move.from)
+        .map_or('P', |(_, kind)| piece_to_char(Color::White, kind));
+    let mut text = format!("{}/{}-{}", piece, chess_move.from, chess_move.to);
+    if let Some(promotion) = chess_move.promotion {
+        text.push('=');
+        text.push(piece_to_char(Color::White, promotion));
+    }
+    text
+}
+
+// Material of `color` in pawns, as style 12 and `games` show it.
+fn material(position: &Position, color: Color) -> u32 {
+    position
+        .squares
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|(owner, _)| *owner == color)
+        .map(|(_, kind)| kind.points())
+        .sum()
+}
+
+fn clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{:3}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn color_term(color: Option<Color>) -> &'static str {
+    match color {
+        Some(Color::White) => " [white]",
+        Some(Color::Black) => " [black]",
+        None => "",
+    }
+}
+
+fn count(count: usize, one: &str, many: &str) -> String {
+    format!(
+        "{} {} displayed.",
+        count,
+        if count == 1 { one } else { many }
+    )
+}
+
+fn win(color: Color) -> &'static str {
+    match color {
+        Color::White => "1-0",
+        Color::Black => "0-1",
+    }
+}
@@ -0,0 +1,317 @@
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::game::Game;
+use crate::location::{LocationCoords, LocationState};
+use crate::piece::PieceType;
+use crate::player::{Color, Player};
+
+/// A single inconsistency between the three places that record where pieces are:
+/// `BoardLocation.piece` / `state`, `Piece.location`, and `Player.pieces` / `dead_pieces`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// `BoardLocation.state` disagrees with whether `BoardLocation.piece` is set.
+    StateMismatch {
+        square: LocationCoords,
+        state: LocationState,
+    },
+    /// The square a piece sits on disagrees with its own `Piece.location`.
+    LocationMismatch {
+        piece: Uuid,
+        square: LocationCoords,
+        recorded: Option<LocationCoords>,
+    },
+    /// A piece on the board is missing from its owner's active pieces.
+    NotInOwnerPieces { piece: Uuid, square: LocationCoords },
+    /// A piece on the board is listed in its owner's dead pieces.
+    DeadPieceOnBoard { piece: Uuid, square: LocationCoords },
+    /// A piece listed under a player is owned by someone else.
+    WrongOwner { piece: Uuid, player: Uuid },
+    /// A piece is listed more than once across a player's active and dead pieces.
+    DuplicatePiece { piece: Uuid, player: Uuid },
+    /// An active piece is not on the board square its `Piece.location` points to.
+    NotOnBoard {
+        piece: Uuid,
+        recorded: Option<LocationCoords>,
+    },
+    /// A dead piece still has a location.
+    DeadPieceHasLocation {
+        piece: Uuid,
+        recorded: LocationCoords,
+    },
+    /// A player does not have exactly one active king.
+    KingCount { player: Uuid, count: usize },
+    /// `Player.king` is unset, is not a king, or is not one of the player's active pieces.
+    KingNotInPieces { player: Uuid },
+    /// A piece's `has_moved` flag contradicts where it stands.
+    HasMovedMismatch {
+        piece: Uuid,
+        square: LocationCoords,
+        has_moved: bool,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::StateMismatch { square, state } => {
+                write!(
+                    f,
+                    "square {} is {:?} but its piece says otherwise",
+                    square, state
+                )
+            }
+            InvariantViolation::LocationMismatch {
+                piece,
+                square,
+                recorded,
+            } => write!(
+                f,
+                "piece {} is on {} but records its location as {:?}",
+                piece, square, recorded
+            ),
+            InvariantViolation::NotInOwnerPieces { piece, square } => write!(
+                f,
+                "piece {} on {} is missing from its owner's pieces",
+                piece, square
+            ),
+            InvariantViolation::DeadPieceOnBoard { piece, square } => {
+                write!(f, "dead piece {} is still on {}", piece, square)
+            }
+            InvariantViolation::WrongOwner { piece, player } => {
+                write!(
+                    f,
+                    "piece {} is listed under player {} but owned by another",
+                    piece, player
+                )
+            }
+            InvariantViolation::DuplicatePiece { piece, player } => {
+                write!(
+                    f,
+                    "piece {} is listed more than once for player {}",
+                    piece, player
+                )
+            }
+            InvariantViolation::NotOnBoard { piece, recorded } => write!(
+                f,
+                "active piece {} is not on the board at its location {:?}",
+                piece, recorded
+            ),
+            InvariantViolation::DeadPieceHasLocation { piece, recorded } => {
+                write!(f, "dead piece {} still has location {}", piece, recorded)
+            }
+            InvariantViolation::KingCount { player, count } => {
+                write!(f, "player {} has {} kings", player, count)
+            }
+            InvariantViolation::KingNotInPieces { player } => {
+                write!(
+                    f,
+                    "king of player {} is not one of their active pieces",
+                    player
+                )
+            }
+            InvariantViolation::HasMovedMismatch {
+                piece,
+                square,
+                has_moved,
+            } => write!(
+                f,
+                "piece {} on {} has has_moved = {}",
+                piece, square, has_moved
+            ),
+        }
+    }
+}
+
+impl Error for InvariantViolation {}
+
+impl Game {
+    /// Cross-checks the board, the pieces and the players against each other.
+    ///
+    /// Returns every violation found rather than stopping at the first one. Debug builds run this
+    /// after every successful move and log violations as errors; whether to stop is up to the
+    /// caller.
+    pub fn validate_invariants(&self) -> Result<(), Vec<InvariantViolation>> {
+        let mut violations: Vec<InvariantViolation> = vec![];
+
+        self.check_board(&mut violations);
+        self.check_player(&self.player1, &mut violations);
+        self.check_player(&self.player2, &mut violations);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    // Board -> pieces / players direction.
+    fn check_board(&self, violations: &mut Vec<InvariantViolation>) {
+        for row in &self.board {
+            for location in row {
+                let square = *location.coords;
+
+                match (&location.state, &location.piece) {
+                    (LocationState::Empty, None) => continue,
+                    (LocationState::Occupied, Some(_)) => (),
+                    (state, _) => violations.push(InvariantViolation::StateMismatch {
+                        square,
+                        state: *state,
+                    }),
+                }
+
+                let Some(piece) = &location.piece else {
+                    continue;
+                };
+
+                let recorded = piece.location.borrow().as_ref().map(|c| **c);
+                if recorded != Some(square) {
+                    violations.push(InvariantViolation::LocationMismatch {
+                        piece: piece.id,
+                        square,
+                        recorded,
+                    });
+                }
+
+                if !piece.owner.pieces.borrow().iter().any(|p| p.id == piece.id) {
+                    violations.push(InvariantViolation::NotInOwnerPieces {
+                        piece: piece.id,
+                        square,
+                    });
+                }
+
+                if piece
+                    .owner
+                    .dead_pieces
+                    .borrow()
+                    .iter()
+                    .any(|p| p.id == piece.id)
+                {
+                    violations.push(InvariantViolation::DeadPieceOnBoard {
+                        piece: piece.id,
+                        square,
+                    });
+                }
+
+                if !has_moved_consistent(
+                    &piece.piece_type,
+                    piece.owner.color,
+                    &square,
+                    *piece.has_moved.borrow(),
+                ) {
+                    violations.push(InvariantViolation::HasMovedMismatch {
+                        piece: piece.id,
+                        square,
+                        has_moved: *piece.has_moved.borrow(),
+                    });
+                }
+            }
+        }
+    }
+
+    // Players -> pieces / board direction.
+    fn check_player(&self, player: &Rc<Player>, violations: &mut Vec<InvariantViolation>) {
+        let pieces = player.pieces.borrow();
+        let dead_pieces = player.dead_pieces.borrow();
+        let mut seen: Vec<Uuid> = vec![];
+        let mut king_count: usize = 0;
+
+        for piece in pieces.iter().chain(dead_pieces.iter()) {
+            if seen.contains(&piece.id) {
+                violations.push(InvariantViolation::DuplicatePiece {
+                    piece: piece.id,
+                    player: player.id,
+                });
+            }
+            seen.push(piece.id);
+
+            if piece.owner.id != player.id {
+                violations.push(InvariantViolation::WrongOwner {
+                    piece: piece.id,
+                    player: player.id,
+                });
+            }
+        }
+
+        for piece in pieces.iter() {
+            if let PieceType::King = piece.piece_type {
+                king_count += 1;
+            }
+
+            let recorded = piece.location.borrow().as_ref().map(|c| **c);
+            let on_board = match recorded {
+                Some(coords) if coords.x <= 7 && coords.y <= 7 => {
+                    match &self.board[coords.y][coords.x].piece {
+                        Some(p) => p.id == piece.id,
+                        None => false,
+                    }
+                }
+                _ => false,
+            };
+
+            if !on_board {
+                violations.push(InvariantViolation::NotOnBoard {
+                    piece: piece.id,
+                    recorded,
+                });
+            }
+        }
+
+        for piece in dead_pieces.iter() {
+            if let Some(coords) = piece.location.borrow().as_ref() {
+                violations.push(InvariantViolation::DeadPieceHasLocation {
+                    piece: piece.id,
+                    recorded: **coords,
+                });
+            }
+        }
+
+        if king_count != 1 {
+            violations.push(InvariantViolation::KingCount {
+                player: player.id,
+                count: king_count,
+            });
+        }
+
+        let king_ok = match player.king.borrow().as_ref() {
+            Some(king) => {
+                matches!(king.piece_type, PieceType::King) && pieces.iter().any(|p| p.id == king.id)
+            }
+            None => false,
+        };
+
+        if !king_ok {
+            violations.push(InvariantViolation::KingNotInPieces { player: player.id });
+        }
+    }
+}
+
+// Pawns are on their starting rank exactly until they first move. Kings and rooks that left
+// their home squares must have moved.
+fn has_moved_consistent(
+    piece_type: &PieceType,
+    color: Color,
+    square: &LocationCoords,
+    has_moved: bool,
+) -> bool {
+    let home_rank = match color {
+        Color::White => 7,
+        Color::Black => 0,
+    };
+
+    match piece_type {
+        PieceType::Pawn => {
+            let start_rank = match color {
+                Color::White => 6,
+                Color::Black => 1,
+            };
+
+            has_moved == (square.y != start_rank)
+        }
+        PieceType::King => has_moved || (square.y == home_rank && square.x == 4),
+        PieceType::Rook => has_moved || (square.y == home_rank && (square.x == 0 || square.x == 7)),
+        _ => true,
+    }
+}
@@ -0,0 +1,481 @@
+//! Two-player games over TCP on a local network.
+//!
+//! One side runs [`host`], which waits for a connection and owns the [`Game`]. The other runs
+//! [`join`]. Each player types moves and commands at their own terminal and sees the board after
+//! every move. The host checks every move against [`Game::current_player`], so each side can only
+//! move its own color.
+//!
+//! The protocol is one line per message. The joining side sends:
+//!
+//! * `HELLO rs_chess-lan 1 <name>` once, on connecting.
+//! * `MOVE <move>` with a move in coordinates or SAN.
+//! * `RESIGN` to give up, and `QUIT` before leaving.
+//!
+//! The host answers:
+//!
+//! * `WELCOME <color> <name>` with the joining side's color and the host's name.
+//! * `STATE <last move or -> <FEN>` after the game starts and after every move.
+//! * `ERROR <text>` when a move or resignation is turned down.
+//! * `OVER <result> <reason>` when the game ends, e.g. `OVER 1-0 White mates`, just before the
+//!   final `STATE`. `OVER * <reason>` means the host left before the end.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use tracing::{debug, info};
+
+use crate::game::Game;
+use crate::movegen::ChessMove;
+use crate::player::{Color, Player};
+use crate::position::{Position, STARTING_FEN};
+use crate::render::BoardRenderer;
+use crate::repl::{Outcome, automatic_outcome, color_name, parse_move};
+use crate::san::SanError;
+
+/// Address [`host`] listens on unless told otherwise.
+pub const DEFAULT_ADDRESS: &str = "0.0.0.0:7878";
+
+const HELLO: &str = "HELLO rs_chess-lan 1";
+
+const HELP: &str = "\
+Moves are written in coordinates (e2e4, e7e8q) or SAN (Nf3, O-O, exd8=Q).
+
+Commands:
+  board         show the board
+  moves         list the legal moves
+  resign        resign the game
+  help          show this text
+  quit          leave";
+
+/// How one side of a network game plays.
+#[derive(Debug, Clone)]
+pub struct LanOptions {
+    /// Name shown to the other player.
+    pub name: String,
+    /// Color of the hosting player. The joining side gets the other one.
+    pub color: Color,
+    pub renderer: BoardRenderer,
+}
+
+impl Default for LanOptions {
+    fn default() -> Self {
+        LanOptions {
+            name: "Player".to_string(),
+            color: Color::White,
+            renderer: BoardRenderer::plain(),
+        }
+    }
+}
+
+// Lines from the local player and the network, merged for one loop.
+#[derive(Debug)]
+enum Event {
+    Local(String),
+    LocalClosed,
+    Remote(String),
+    RemoteClosed,
+}
+
+// What both sides show: the board from their side, and how the game ended.
+#[derive(Debug)]
+struct View<W: Write> {
+    out: W,
+    color: Color,
+    renderer: BoardRenderer,
+    // The host's game as of its last move, or the last `STATE` the joining side received.
+    position: Position,
+    last_move: Option<ChessMove>,
+    outcome: Option<Outcome>,
+}
+
+impl<W: Write> View<W> {
+    fn new(out: W, color: Color, renderer: BoardRenderer) -> Self {
+        View {
+            out,
+            color,
+            renderer: renderer.orientation(color),
+            position: Position::from_fen(STARTING_FEN).expect("the starting FEN is valid"),
+            last_move: None,
+            outcome: None,
+        }
+    }
+
+    fn show_board(&mut self) -> io::Result<()> {
+        let board = self
+            .renderer
+            .render_position(&self.position, self.last_move.as_ref());
+        write!(self.out, "{}", board)?;
+
+        if let Some(outcome) = &self.outcome {
+            writeln!(
+                self.out,
+                "Game over: {} ({})",
+                outcome.result, outcome.reason
+            )
+        } else if self.position.side_to_move == self.color {
+            let check = if self.position.is_in_check(self.color) {
+                " You are in check."
+            } else {
+                ""
+            };
+            writeln!(self.out, "Your move.{}", check)
+        } else {
+            writeln!(self.out, "Waiting for the other player.")
+        }
+    }
+
+    fn show_moves(&mut self) -> io::Result<()> {
+        let position = &self.position;
+        let mut moves: Vec<String> = position
+            .legal_moves()
+            .iter()
+            .map(|m| position.san(m))
+            .collect();
+        moves.sort();
+        writeln!(self.out, "{}", moves.join(" "))
+    }
+
+    // Commands shared by both sides. Returns false for anything else.
+    fn command(&mut self, command: &str) -> io::Result<bool> {
+        match command {
+            "board" => self.show_board()?,
+            "moves" => self.show_moves()?,
+            "help" | "?" => writeln!(self.out, "{}", HELP)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+// The hosting side's game.
+struct Host<W: Write> {
+    view: View<W>,
+    game: Game,
+    remote: TcpStream,
+}
+
+impl<W: Write> Host<W> {
+    // Plays `text` for `color` if it is that color's turn. Errors are for the player who moved.
+    fn play(&mut self, color: Color, text: &str) -> io::Result<Result<(), String>> {
+        if self.view.outcome.is_some() {
+            return Ok(Err("the game is over".to_string()));
+        }
+        if self.game.current_player.color != color {
+            return Ok(Err("it is not your turn".to_string()));
+        }
+
+        let chess_move = match parse_move(&self.view.position, text) {
+            Ok(chess_move) => chess_move,
+            Err(error) => return Ok(Err(error.to_string())),
+        };
+
+        self.game.make_move(&chess_move);
+        self.view.position = self.game.to_position();
+        self.view.last_move = self.game.last_move();
+        self.view.outcome = automatic_outcome(&self.game);
+
+        self.send_state()?;
+        self.view.show_board()?;
+        Ok(Ok(()))
+    }
+
+    // Gives up the game for `color`. Errors are for the player who resigned.
+    fn resign(&mut self, color: Color) -> io::Result<Result<(), String>> {
+        if self.view.outcome.is_some() {
+            return Ok(Err("the game is over".to_string()));
+        }
+
+        self.view.outcome = Some(Outcome {
+            result: match color {
+                Color::White => "0-1",
+                Color::Black => "1-0",
+            },
+            reason: format!("{} resigns", color_name(color)),
+        });
+        self.send_state()?;
+        self.view.show_board()?;
+        Ok(Ok(()))
+    }
+
+    fn send_state(&mut self) -> io::Result<()> {
+        let last_move = self
+            .view
+            .last_move
+            .map_or("-".to_string(), |m| m.to_string());
+        if let Some(outcome) = &self.view.outcome {
+            writeln!(self.remote, "OVER {} {}", outcome.result, outcome.reason)?;
+        }
+        writeln!(
+            self.remote,
+            "STATE {} {}",
+            last_move,
+            self.view.position.to_fen()
+        )
+    }
+}
+
+/// Waits for one player to join on `address`, then plays the game reading the local player's
+/// lines from `input` and writing to `output`.
+pub fn host<R, W>(address: &str, options: LanOptions, input: R, mut output: W) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    let listener = TcpListener::bind(address)?;
+    writeln!(
+        output,
+        "Waiting for the other player on {}...",
+        listener.local_addr()?
+    )?;
+
+    let (stream, peer) = listener.accept()?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut hello = String::new();
+    reader.read_line(&mut hello)?;
+    let Some(name) = hello.trim_end().strip_prefix(HELLO) else {
+        let mut stream = stream;
+        writeln!(stream, "ERROR expected {} <name>", HELLO)?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} does not speak the rs_chess-lan protocol", peer),
+        ));
+    };
+    let name = match name.trim() {
+        "" => "Guest".to_string(),
+        name => name.to_string(),
+    };
+    info!(%peer, name, "player joined");
+
+    let color = options.color;
+    let remote_color = color.opponent();
+    let (white, black) = match color {
+        Color::White => (options.name.clone(), name.clone()),
+        Color::Black => (name.clone(), options.name.clone()),
+    };
+    let view = View::new(output, color, options.renderer);
+    let game = Game::from_position(
+        Player::with_rc(&white, Color::White),
+        Player::with_rc(&black, Color::Black),
+        &view.position,
+    )
+    .expect("the starting position is valid");
+
+    let mut host = Host {
+        view,
+        game,
+        remote: stream,
+    };
+
+    writeln!(
+        host.remote,
+        "WELCOME {} {}",
+        color_name(remote_color),
+        options.name
+    )?;
+    writeln!(
+        host.view.out,
+        "{} joined and plays {}.",
+        name,
+        color_name(remote_color)
+    )?;
+    host.send_state()?;
+    host.view.show_board()?;
+
+    let (events, received) = mpsc::channel();
+    spawn_lines(input, events.clone(), Event::Local, Event::LocalClosed);
+    spawn_lines(reader, events, Event::Remote, Event::RemoteClosed);
+
+    for event in received {
+        match event {
+            Event::Local(line) => {
+                let line = line.trim();
+                if line.is_empty() || host.view.command(line)? {
+                    continue;
+                }
+                match line {
+                    "resign" => {
+                        if let Err(message) = host.resign(color)? {
+                            writeln!(host.view.out, "{}.", capitalize(&message))?;
+                        }
+                    }
+                    "quit" | "exit" => {
+                        let _ = writeln!(host.remote, "OVER * host left");
+                        return Ok(());
+                    }
+                    text => {
+                        if let Err(message) = host.play(color, text)? {
+                            writeln!(host.view.out, "{}.", capitalize(&message))?;
+                        }
+                    }
+                }
+            }
+            Event::Remote(line) => {
+                debug!(line, "received");
+                let (kind, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                match kind {
+                    "MOVE" => {
+                        if let Err(message) = host.play(remote_color, rest)? {
+                            writeln!(host.remote, "ERROR {}", message)?;
+                        }
+                    }
+                    "RESIGN" => {
+                        if let Err(message) = host.resign(remote_color)? {
+                            writeln!(host.remote, "ERROR {}", message)?;
+                        }
+                    }
+                    "QUIT" => {}
+                    _ => writeln!(host.remote, "ERROR unknown message {}", kind)?,
+                }
+            }
+            Event::RemoteClosed => {
+                writeln!(host.view.out, "{} left the game.", name)?;
+                return Ok(());
+            }
+            Event::LocalClosed => return Ok(()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Joins the game hosted at `address`, reading the local player's lines from `input` and writing
+/// to `output`. The color in `options` is ignored; the host picks.
+pub fn join<R, W>(address: &str, options: LanOptions, input: R, mut output: W) -> io::Result<()>
+where
+    R: BufRead + Send + 'static,
+    W: Write,
+{
+    let mut remote = TcpStream::connect(address)?;
+    writeln!(remote, "{} {}", HELLO, options.name)?;
+
+    let mut reader = BufReader::new(remote.try_clone()?);
+    let mut welcome = String::new();
+    reader.read_line(&mut welcome)?;
+    let mut words = welcome.trim_end().splitn(3, ' ');
+    let (Some("WELCOME"), Some(color), host_name) = (words.next(), words.next(), words.next())
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected greeting from the host: {}", welcome),
+        ));
+    };
+    let color = match color {
+        "Black" => Color::Black,
+        _ => Color::White,
+    };
+    writeln!(
+        output,
+        "Joined {}'s game as {}.",
+        host_name.unwrap_or("the host"),
+        color_name(color)
+    )?;
+
+    let mut view = View::new(output, color, options.renderer);
+    let (events, received) = mpsc::channel();
+    spawn_lines(input, events.clone(), Event::Local, Event::LocalClosed);
+    spawn_lines(reader, events, Event::Remote, Event::RemoteClosed);
+
+    for event in received {
+        match event {
+            Event::Local(line) => {
+                let line = line.trim();
+                if line.is_empty() || view.command(line)? {
+                    continue;
+                }
+                match line {
+                    "resign" => writeln!(remote, "RESIGN")?,
+                    "quit" | "exit" => {
+                        let _ = writeln!(remote, "QUIT");
+                        return Ok(());
+                    }
+                    text => {
+                        // Catch typos here; the host has the last word on legality and turns.
+                        match parse_move(&view.position, text) {
+                            Err(SanError::Invalid(_)) => writeln!(
+                                view.out,
+                                "Unknown command or move: {}. Type `help` for the commands.",
+                                text
+                            )?,
+                            _ => writeln!(remote, "MOVE {}", text)?,
+                        }
+                    }
+                }
+            }
+            Event::Remote(line) => {
+                debug!(line, "received");
+                let (kind, rest) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                match kind {
+                    "STATE" => {
+                        let (last_move, fen) = rest.split_once(' ').unwrap_or(("-", rest));
+                        match Position::from_fen(fen) {
+                            Ok(position) => {
+                                view.position = position;
+                                view.last_move = ChessMove::from_uci(last_move);
+                                view.show_board()?;
+                            }
+                            Err(error) => {
+                                writeln!(view.out, "Bad position from the host: {}", error)?
+                            }
+                        }
+                    }
+                    "OVER" => {
+                        let (result, reason) = rest.split_once(' ').unwrap_or((rest, ""));
+                        if result == "*" {
+                            if view.outcome.is_some() {
+                                writeln!(view.out, "The host left the game.")?;
+                            } else {
+                                writeln!(view.out, "The game was abandoned: {}.", reason)?;
+                            }
+                            return Ok(());
+                        }
+                        view.outcome = Some(Outcome {
+                            result: match result {
+                                "1-0" => "1-0",
+                                "0-1" => "0-1",
+                                _ => "1/2-1/2",
+                            },
+                            reason: reason.to_string(),
+                        });
+                    }
+                    "ERROR" => writeln!(view.out, "{}.", capitalize(rest))?,
+                    _ => debug!(kind, "unknown message"),
+                }
+            }
+            Event::RemoteClosed => {
+                writeln!(view.out, "The host left the game.")?;
+                return Ok(());
+            }
+            Event::LocalClosed => {
+                let _ = writeln!(remote, "QUIT");
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Forwards every line of `reader` as an event, then `closed` once it runs out.
+fn spawn_lines<R>(reader: R, events: Sender<Event>, line: fn(String) -> Event, closed: Event)
+where
+    R: BufRead + Send + 'static,
+{
+    thread::spawn(move || {
+        for text in reader.lines().map_while(Result::ok) {
+            if events.send(line(text)).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(closed);
+    });
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
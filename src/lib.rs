@@ -0,0 +1,91 @@
+//! Chess game state, rules and pieces.
+//!
+//! The crate is split into a small set of public modules:
+//!
+//! * `book` - Polyglot opening books: lookup, move selection and building from PGN (feature
+//!   `engine`).
+//! * `eval` - static evaluation of positions (feature `engine`).
+//! * [`game`] - the [`Game`] board and move handling.
+//! * `ics` - a FICS-style telnet server for ICS interfaces (feature `ics`).
+//! * [`invariants`] - consistency checks across board, piece and player state.
+//! * [`lan`] - two-player games over TCP on a local network.
+//! * `live` - live games for players and spectators over WebSocket (feature `websocket`).
+//! * [`location`] - board squares and coordinates.
+//! * [`movegen`] - legal move generation for positions.
+//! * [`observer`] - callbacks for reacting to game events.
+//! * [`pgn`] - reading and writing games in Portable Game Notation.
+//! * [`piece`] - pieces and their movement patterns.
+//! * [`player`] - players, colors and their piece sets.
+//! * [`position`] - plain position snapshots, FEN and legality checks.
+//! * `raster` - PNG images and animated GIFs of boards and games (feature `raster`).
+//! * [`render`] - text rendering of boards for terminals and logs.
+//! * [`repl`] - interactive command-line play.
+//! * [`san`] - standard algebraic notation for moves.
+//! * `search` - alpha-beta search for the best move (feature `engine`).
+//! * `server` - HTTP/JSON API hosting many games (feature `server`).
+//! * [`svg`] - SVG board diagrams.
+//! * `syzygy` - Syzygy endgame tablebase probing (feature `engine`).
+//! * `time` - time budgets for searches under a clock (feature `engine`).
+//! * `tt` - transposition table shared between searches (feature `engine`).
+//! * `tui` - full-screen terminal interface for playing and reviewing games (feature `tui`).
+//! * `uci` - Universal Chess Interface protocol driver (feature `engine`).
+//! * `xboard` - xboard / CECP protocol driver (feature `engine`).
+//! * [`zobrist`] - Polyglot-compatible Zobrist hashing of positions.
+//!
+//! Optional subsystems are gated behind cargo features so consumers only compile what they use.
+//! See the `[features]` table in `Cargo.toml` for the full list.
+
+#[cfg(feature = "engine")]
+pub mod book;
+#[cfg(feature = "engine")]
+pub mod eval;
+pub mod game;
+#[cfg(feature = "ics")]
+pub mod ics;
+pub mod invariants;
+pub mod lan;
+#[cfg(feature = "websocket")]
+pub mod live;
+pub mod location;
+pub mod movegen;
+pub mod observer;
+pub mod pgn;
+pub mod piece;
+pub mod player;
+pub mod position;
+#[cfg(feature = "raster")]
+pub mod raster;
+pub mod render;
+pub mod repl;
+pub mod san;
+#[cfg(feature = "engine")]
+pub mod search;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod svg;
+#[cfg(feature = "engine")]
+pub mod syzygy;
+#[cfg(feature = "engine")]
+pub mod time;
+#[cfg(feature = "engine")]
+pub mod tt;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "engine")]
+pub mod uci;
+#[cfg(feature = "engine")]
+pub mod xboard;
+pub mod zobrist;
+
+#[cfg(any(feature = "ics", feature = "websocket"))]
+mod hub;
+mod utils;
+
+pub use game::Game;
+pub use invariants::InvariantViolation;
+pub use location::{BoardLocation, LocationCoords, LocationState};
+pub use movegen::ChessMove;
+pub use observer::GameObserver;
+pub use piece::{Piece, PieceType};
+pub use player::{Color, Player};
+pub use position::{CastlingRights, FenError, Position, PositionViolation};
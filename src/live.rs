@@ -0,0 +1,668 @@
+//! Live games over WebSocket.
+//!
+//! Clients connect to [`serve`] and exchange JSON text messages tagged by `type`. A
+//! [`ClientMessage::Create`] opens a game and takes a seat; a second client takes the other seat
+//! with [`ClientMessage::Join`], and anyone can [`ClientMessage::Watch`]. Moves are only taken
+//! from the player whose color matches the [`Player::color`](crate::player::Player) of the
+//! game's current player, are checked against the [`Game`](crate::game::Game), and every move
+//! sends a [`ServerMessage::State`] to both players and all spectators.
+//!
+//! Seats come with a token. A player whose connection drops can take the seat back with
+//! [`ClientMessage::Rejoin`] within the reconnect grace period; after that the game is lost by
+//! abandonment, or drawn if both players are gone. Games nobody is connected to are dropped once
+//! they are over or still waiting for an opponent.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use tungstenite::Message;
+use uuid::Uuid;
+
+use crate::hub::{self, Event};
+use crate::player::Color;
+use crate::server::{ApiError, GameState, GameSummary, HostedGame, NewGame, player_name};
+
+/// How long a player may stay disconnected before losing the game, by default.
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(60);
+
+// How often the hub looks for abandoned games, and connections for messages to send.
+const HUB_TICK: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// Longest wait for a client to finish the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Messages sent by clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Opens a game and sits down as `color`, White if missing.
+    Create {
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        color: Option<Color>,
+        /// Starting position, the standard one if missing.
+        #[serde(default)]
+        fen: Option<String>,
+    },
+    /// Takes a free seat in a game, the one given by `color` if set.
+    Join {
+        game: Uuid,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        color: Option<Color>,
+    },
+    /// Takes back a seat after a lost connection, with the token from [`ServerMessage::Joined`].
+    Rejoin { game: Uuid, token: Uuid },
+    /// Follows a game as a spectator.
+    Watch { game: Uuid },
+    /// Plays a move in coordinates or SAN.
+    Move {
+        #[serde(rename = "move")]
+        notation: String,
+    },
+    /// Gives up the game.
+    Resign,
+    /// Asks for [`ServerMessage::Games`].
+    List,
+}
+
+/// Messages sent to clients.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// The client sits at `color` with `token` for reconnecting, or watches when both are null.
+    Joined {
+        game: Uuid,
+        color: Option<Color>,
+        token: Option<Uuid>,
+    },
+    /// The game after a change, including who is connected.
+    State {
+        #[serde(flatten)]
+        state: GameState,
+        white_connected: bool,
+        black_connected: bool,
+        /// False until both seats have been taken; no moves are played before that.
+        started: bool,
+        spectators: usize,
+    },
+    /// Every live game, oldest first.
+    Games { games: Vec<LiveGameSummary> },
+    /// A request failed. `error` is a machine-readable kind such as `not_your_turn`.
+    Error { error: String, message: String },
+}
+
+/// One entry of [`ServerMessage::Games`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveGameSummary {
+    #[serde(flatten)]
+    pub summary: GameSummary,
+    /// Seats nobody has taken yet.
+    pub open_seats: Vec<Color>,
+}
+
+#[derive(Debug)]
+struct Seat {
+    token: Uuid,
+    connection: Option<u64>,
+    // When the connection was lost, while it is.
+    left_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct LiveGame {
+    hosted: HostedGame,
+    // Indexed by `Color::index`.
+    seats: [Option<Seat>; 2],
+    // Seat names, indexed like `seats`. They are kept here rather than in the game, which is
+    // made before the second player joins.
+    names: [String; 2],
+    spectators: Vec<u64>,
+    // When the last connected client left, while nobody is connected.
+    empty_since: Option<Instant>,
+}
+
+impl LiveGame {
+    fn started(&self) -> bool {
+        self.seats.iter().all(Option::is_some)
+    }
+
+    fn connected(&self, color: Color) -> bool {
+        self.seats[color.index()]
+            .as_ref()
+            .is_some_and(|seat| seat.connection.is_some())
+    }
+
+    fn connections(&self) -> Vec<u64> {
+        let seated = self
+            .seats
+            .iter()
+            .flatten()
+            .filter_map(|seat| seat.connection);
+        seated.chain(self.spectators.iter().copied()).collect()
+    }
+
+    fn summary(&self) -> LiveGameSummary {
+        let [white, black] = self.names.clone();
+        LiveGameSummary {
+            summary: GameSummary {
+                white,
+                black,
+                ..self.hosted.summary()
+            },
+            open_seats: self.open_seats(),
+        }
+    }
+
+    fn open_seats(&self) -> Vec<Color> {
+        [Color::White, Color::Black]
+            .into_iter()
+            .filter(|color| self.seats[color.index()].is_none())
+            .collect()
+    }
+
+    fn state_message(&self) -> ServerMessage {
+        let [white, black] = self.names.clone();
+        ServerMessage::State {
+            state: GameState {
+                white,
+                black,
+                ..self.hosted.state()
+            },
+            white_connected: self.connected(Color::White),
+            black_connected: self.connected(Color::Black),
+            started: self.started(),
+            spectators: self.spectators.len(),
+        }
+    }
+}
+
+// Where a connection is in a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Player(Color),
+    Spectator,
+}
+
+#[derive(Debug)]
+struct Connection {
+    sender: Sender<String>,
+    game: Option<(Uuid, Role)>,
+}
+
+// Owns every live game.
+#[derive(Debug)]
+struct Hub {
+    games: HashMap<Uuid, LiveGame>,
+    // Ids in the order the games were created, for listing.
+    order: Vec<Uuid>,
+    connections: HashMap<u64, Connection>,
+    reconnect_grace: Duration,
+}
+
+impl hub::Hub for Hub {
+    type Sink = Sender<String>;
+
+    fn handle(&mut self, event: Event<Sender<String>>) {
+        match event {
+            Event::Connected(connection, sender) => {
+                let entry = Connection { sender, game: None };
+                self.connections.insert(connection, entry);
+            }
+            Event::Message(connection, text) => match serde_json::from_str(&text) {
+                Ok(message) => {
+                    if let Err(refusal) = self.handle_message(connection, message) {
+                        self.send(connection, &refusal.into());
+                    }
+                }
+                Err(error) => {
+                    let refusal = refuse("bad_request", &error.to_string());
+                    self.send(connection, &refusal.into());
+                }
+            },
+            Event::Disconnected(connection) => {
+                self.leave(connection);
+                self.connections.remove(&connection);
+            }
+        }
+    }
+
+    // Ends games whose players stayed away too long and drops games nobody is connected to.
+    fn expire(&mut self, now: Instant) {
+        let grace = self.reconnect_grace;
+        let gone = |seat: &Option<Seat>| {
+            seat.as_ref()
+                .and_then(|seat| seat.left_at)
+                .is_some_and(|left_at| now.duration_since(left_at) >= grace)
+        };
+
+        let mut changed = vec![];
+        let mut dropped = vec![];
+
+        for (id, game) in &mut self.games {
+            let white_gone = gone(&game.seats[0]);
+            let black_gone = gone(&game.seats[1]);
+
+            if game.started() && game.hosted.outcome.is_none() {
+                let end = match (white_gone, black_gone) {
+                    (true, true) => Some(("1/2-1/2", "both players left")),
+                    (true, false) => Some(("0-1", "White abandoned the game")),
+                    (false, true) => Some(("1-0", "Black abandoned the game")),
+                    (false, false) => None,
+                };
+                if let Some((result, reason)) = end {
+                    info!(%id, reason, "live game abandoned");
+                    game.hosted.end(result, reason);
+                    changed.push(*id);
+                }
+            }
+
+            let over_or_waiting = game.hosted.outcome.is_some() || !game.started();
+            if over_or_waiting
+                && game
+                    .empty_since
+                    .is_some_and(|since| now.duration_since(since) >= grace)
+            {
+                dropped.push(*id);
+            }
+        }
+
+        for id in changed {
+            self.broadcast(id);
+        }
+        for id in dropped {
+            debug!(%id, "live game dropped");
+            self.games.remove(&id);
+            self.order.retain(|other| *other != id);
+        }
+    }
+}
+
+impl Hub {
+    fn new(reconnect_grace: Duration) -> Self {
+        Hub {
+            games: HashMap::new(),
+            order: vec![],
+            connections: HashMap::new(),
+            reconnect_grace,
+        }
+    }
+
+    fn handle_message(&mut self, connection: u64, message: ClientMessage) -> Result<(), Refusal> {
+        match message {
+            ClientMessage::Create { name, color, fen } => {
+                self.create(connection, name, color.unwrap_or(Color::White), fen)
+            }
+            ClientMessage::Join { game, name, color } => self.join(connection, game, name, color),
+            ClientMessage::Rejoin { game, token } => self.rejoin(connection, game, token),
+            ClientMessage::Watch { game } => self.watch(connection, game),
+            ClientMessage::Move { notation } => self.play(connection, &notation),
+            ClientMessage::Resign => self.resign(connection),
+            ClientMessage::List => {
+                let games = self
+                    .order
+                    .iter()
+                    .filter_map(|id| self.games.get(id))
+                    .map(LiveGame::summary)
+                    .collect();
+                self.send(connection, &ServerMessage::Games { games });
+                Ok(())
+            }
+        }
+    }
+
+    fn create(
+        &mut self,
+        connection: u64,
+        name: String,
+        color: Color,
+        fen: Option<String>,
+    ) -> Result<(), Refusal> {
+        let hosted = HostedGame::new(NewGame {
+            white: String::new(),
+            black: String::new(),
+            fen,
+        })?;
+        let mut names = [Color::White, Color::Black].map(|color| player_name("", color));
+        names[color.index()] = player_name(&name, color);
+
+        self.leave(connection);
+        let id = hosted.game.id;
+        let mut seats = [None, None];
+        let token = Uuid::new_v4();
+        seats[color.index()] = Some(Seat {
+            token,
+            connection: Some(connection),
+            left_at: None,
+        });
+
+        self.games.insert(
+            id,
+            LiveGame {
+                hosted,
+                seats,
+                names,
+                spectators: vec![],
+                empty_since: None,
+            },
+        );
+        self.order.push(id);
+        info!(%id, ?color, "live game created");
+
+        self.seat(connection, id, color, token);
+        Ok(())
+    }
+
+    fn join(
+        &mut self,
+        connection: u64,
+        id: Uuid,
+        name: String,
+        color: Option<Color>,
+    ) -> Result<(), Refusal> {
+        let game = self.games.get(&id).ok_or(ApiError::UnknownGame(id))?;
+        let open = game.open_seats();
+        let color = match color {
+            Some(color) if open.contains(&color) => color,
+            Some(color) => {
+                let message = format!("the {:?} seat is taken", color);
+                return Err(refuse("seat_taken", &message));
+            }
+            None => *open
+                .first()
+                .ok_or_else(|| refuse("game_full", "both seats are taken"))?,
+        };
+
+        self.leave(connection);
+        let game = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+
+        game.names[color.index()] = player_name(&name, color);
+
+        let token = Uuid::new_v4();
+        game.seats[color.index()] = Some(Seat {
+            token,
+            connection: Some(connection),
+            left_at: None,
+        });
+        info!(%id, ?color, "player joined");
+
+        self.seat(connection, id, color, token);
+        Ok(())
+    }
+
+    fn rejoin(&mut self, connection: u64, id: Uuid, token: Uuid) -> Result<(), Refusal> {
+        let game = self.games.get(&id).ok_or(ApiError::UnknownGame(id))?;
+        let color = [Color::White, Color::Black]
+            .into_iter()
+            .find(|color| {
+                game.seats[color.index()]
+                    .as_ref()
+                    .is_some_and(|seat| seat.token == token)
+            })
+            .ok_or_else(|| refuse("bad_token", "no seat in this game has that token"))?;
+
+        self.leave(connection);
+        let game = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+        let seat = game.seats[color.index()]
+            .as_mut()
+            .expect("the seat was found above");
+
+        // A second connection with the token takes over from the first.
+        if let Some(previous) = seat.connection.replace(connection)
+            && let Some(previous) = self.connections.get_mut(&previous)
+        {
+            previous.game = None;
+        }
+        seat.left_at = None;
+        info!(%id, ?color, "player reconnected");
+
+        self.seat(connection, id, color, token);
+        Ok(())
+    }
+
+    fn watch(&mut self, connection: u64, id: Uuid) -> Result<(), Refusal> {
+        if !self.games.contains_key(&id) {
+            return Err(ApiError::UnknownGame(id).into());
+        }
+
+        self.leave(connection);
+        let game = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+        game.spectators.push(connection);
+        game.empty_since = None;
+        if let Some(entry) = self.connections.get_mut(&connection) {
+            entry.game = Some((id, Role::Spectator));
+        }
+
+        let joined = ServerMessage::Joined {
+            game: id,
+            color: None,
+            token: None,
+        };
+        self.send(connection, &joined);
+        self.broadcast(id);
+        Ok(())
+    }
+
+    fn play(&mut self, connection: u64, notation: &str) -> Result<(), Refusal> {
+        let (id, color) = self.player(connection)?;
+        let game = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+
+        if game.hosted.outcome.is_some() {
+            return Err(ApiError::GameOver(id).into());
+        }
+        if !game.started() {
+            return Err(refuse("waiting", "the other seat is still empty"));
+        }
+        if game.hosted.game.current_player.color != color {
+            return Err(refuse("not_your_turn", "it is the other player's turn"));
+        }
+
+        let chess_move = game.hosted.play(notation)?;
+        debug!(%id, chess_move = %chess_move, "live move played");
+
+        self.broadcast(id);
+        Ok(())
+    }
+
+    fn resign(&mut self, connection: u64) -> Result<(), Refusal> {
+        let (id, color) = self.player(connection)?;
+        let game = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+
+        if game.hosted.outcome.is_some() {
+            return Err(ApiError::GameOver(id).into());
+        }
+        match color {
+            Color::White => game.hosted.end("0-1", "White resigns"),
+            Color::Black => game.hosted.end("1-0", "Black resigns"),
+        }
+
+        self.broadcast(id);
+        Ok(())
+    }
+
+    // Records `connection` in its new seat and tells everyone.
+    fn seat(&mut self, connection: u64, id: Uuid, color: Color, token: Uuid) {
+        if let Some(game) = self.games.get_mut(&id) {
+            game.empty_since = None;
+        }
+        if let Some(entry) = self.connections.get_mut(&connection) {
+            entry.game = Some((id, Role::Player(color)));
+        }
+
+        let joined = ServerMessage::Joined {
+            game: id,
+            color: Some(color),
+            token: Some(token),
+        };
+        self.send(connection, &joined);
+        self.broadcast(id);
+    }
+
+    // Takes `connection` out of its game. A seat stays reserved for a reconnect.
+    fn leave(&mut self, connection: u64) {
+        let Some((id, role)) = self
+            .connections
+            .get_mut(&connection)
+            .and_then(|entry| entry.game.take())
+        else {
+            return;
+        };
+        let Some(game) = self.games.get_mut(&id) else {
+            return;
+        };
+
+        match role {
+            Role::Player(color) => {
+                if let Some(seat) = &mut game.seats[color.index()]
+                    && seat.connection == Some(connection)
+                {
+                    seat.connection = None;
+                    seat.left_at = Some(Instant::now());
+                    info!(%id, ?color, "player disconnected");
+                }
+            }
+            Role::Spectator => game.spectators.retain(|other| *other != connection),
+        }
+
+        if game.connections().is_empty() {
+            game.empty_since = Some(Instant::now());
+        }
+        self.broadcast(id);
+    }
+
+    fn player(&self, connection: u64) -> Result<(Uuid, Color), Refusal> {
+        match self
+            .connections
+            .get(&connection)
+            .and_then(|entry| entry.game)
+        {
+            Some((id, Role::Player(color))) => Ok((id, color)),
+            _ => Err(refuse("not_seated", "join a game as a player first")),
+        }
+    }
+
+    // Sends the state of game `id` to its players and spectators.
+    fn broadcast(&self, id: Uuid) {
+        if let Some(game) = self.games.get(&id) {
+            let state = game.state_message();
+            for connection in game.connections() {
+                self.send(connection, &state);
+            }
+        }
+    }
+
+    fn send(&self, connection: u64, message: &ServerMessage) {
+        if let Some(entry) = self.connections.get(&connection) {
+            let text = serde_json::to_string(message).expect("messages serialize to JSON");
+            // A closed connection is cleaned up by its own disconnect event.
+            let _ = entry.sender.send(text);
+        }
+    }
+}
+
+// Why the hub turned a message down, sent back as a `ServerMessage::Error`.
+#[derive(Debug)]
+struct Refusal {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<ApiError> for Refusal {
+    fn from(error: ApiError) -> Self {
+        refuse(error.kind(), &error.to_string())
+    }
+}
+
+impl From<Refusal> for ServerMessage {
+    fn from(refusal: Refusal) -> Self {
+        ServerMessage::Error {
+            error: refusal.kind.to_string(),
+            message: refusal.message,
+        }
+    }
+}
+
+/// Serves live games on `address`, e.g. `127.0.0.1:8081`, until the process ends. Players that
+/// stay disconnected for longer than `reconnect_grace` lose their game.
+pub fn serve(address: &str, reconnect_grace: Duration) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    info!(address, "serving live games");
+
+    hub::serve(
+        listener,
+        Hub::new(reconnect_grace),
+        HUB_TICK,
+        run_connection,
+    );
+    Ok(())
+}
+
+// Relays messages between one client and the hub until either side goes away.
+fn run_connection(connection: u64, stream: TcpStream, events: Sender<Event<Sender<String>>>) {
+    let _ = stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT));
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(error) => {
+            debug!(%error, "WebSocket handshake failed");
+            return;
+        }
+    };
+    // Short reads let the loop below also forward the hub's messages.
+    if socket
+        .get_mut()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .is_err()
+    {
+        return;
+    }
+
+    let (sender, outgoing) = mpsc::channel();
+    if events.send(Event::Connected(connection, sender)).is_err() {
+        return;
+    }
+
+    'relay: loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if events.send(Event::Message(connection, text)).is_err() {
+                    break;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(error))
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+
+        loop {
+            match outgoing.try_recv() {
+                Ok(text) => {
+                    if socket.send(Message::Text(text)).is_err() {
+                        break 'relay;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'relay,
+            }
+        }
+    }
+
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    let _ = events.send(Event::Disconnected(connection));
+}
+
+fn refuse(kind: &'static str, message: &str) -> Refusal {
+    Refusal {
+        kind,
+        message: message.to_string(),
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::rc::Rc;
 
 use crate::piece::Piece;
@@ -11,14 +12,53 @@ pub struct BoardLocation {
     pub black_attackable: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocationCoords {
     pub x: usize,
     pub y: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LocationState {
     Empty,
     Occupied,
 }
+
+impl LocationCoords {
+    /// Parses a square in algebraic notation, e.g. "e2" is x: 4, y: 6.
+    pub fn from_algebraic(square: &str) -> Option<Self> {
+        let bytes = square.as_bytes();
+        if bytes.len() != 2 {
+            return None;
+        }
+
+        let file = bytes[0];
+        let rank = bytes[1];
+
+        if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+            return None;
+        }
+
+        Some(LocationCoords {
+            x: (file - b'a') as usize,
+            y: (b'8' - rank) as usize,
+        })
+    }
+}
+
+impl fmt::Display for LocationCoords {
+    // Formats coordinates in algebraic notation, e.g. x: 4, y: 6 is "e2". Squares off the board
+    // are shown as "(x,y)", as they still turn up in logs of rejected moves.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.x > 7 || self.y > 7 {
+            return write!(f, "({},{})", self.x, self.y);
+        }
+
+        let file = (b'a' + self.x as u8) as char;
+        let rank = 8 - self.y;
+
+        write!(f, "{}{}", file, rank)
+    }
+}
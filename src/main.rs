@@ -1,87 +1,99 @@
-mod game;
-mod location;
-mod piece;
-mod player;
-mod utils;
-
-use game::Game;
-use location::LocationCoords;
-use player::{Color, Player};
-
-fn main() {
-    let player1 = Player::with_rc("bob", Color::White);
-    let player2 = Player::with_rc("alice", Color::Black);
-
-    let mut game = Game::new(player1, player2);
-
-    println!("{}", &game);
-
-    game.move_piece(LocationCoords { x: 7, y: 6 }, LocationCoords { x: 7, y: 5 });
-    game.move_piece(LocationCoords { x: 6, y: 1 }, LocationCoords { x: 6, y: 2 });
-
-    game.move_piece(LocationCoords { x: 7, y: 5 }, LocationCoords { x: 7, y: 4 });
-    game.move_piece(LocationCoords { x: 6, y: 2 }, LocationCoords { x: 6, y: 3 });
-
-    println!("{}", &game);
-
-    game.move_piece(LocationCoords { x: 7, y: 4 }, LocationCoords { x: 6, y: 3 });
-
-    println!("{}", &game);
-
-    println!(
-        "Player {:?}\nActive pieces: {:?}\nDead pieces: {:?}",
-        &game.player2.name, &game.player2.pieces, &game.player2.dead_pieces
-    );
-
-    let pieces = game.player2.pieces.borrow();
-    let dead = game.player2.dead_pieces.borrow();
-
-    println!(
-        "Alive count: {}\nDead count: {}",
-        &pieces.len(),
-        &dead.len()
-    );
-
-    drop(pieces);
-    drop(dead);
-
-    game.move_piece(LocationCoords { x: 6, y: 0 }, LocationCoords { x: 5, y: 2 });
-    println!("{}", &game);
-
-    game.move_piece(LocationCoords { x: 4, y: 6 }, LocationCoords { x: 4, y: 5 });
-    game.move_piece(LocationCoords { x: 5, y: 2 }, LocationCoords { x: 3, y: 3 });
-    game.move_piece(LocationCoords { x: 4, y: 7 }, LocationCoords { x: 4, y: 6 });
-    println!("{}", &game);
-
-    game.move_piece(LocationCoords { x: 1, y: 1 }, LocationCoords { x: 1, y: 2 });
-    game.move_piece(LocationCoords { x: 7, y: 7 }, LocationCoords { x: 7, y: 1 });
-    game.move_piece(LocationCoords { x: 7, y: 0 }, LocationCoords { x: 7, y: 1 });
-
-    // Move pawn to make room for bishop test
-    game.move_piece(LocationCoords { x: 6, y: 6 }, LocationCoords { x: 6, y: 5 });
-
-    // Throwaway move for turn
-    game.move_piece(LocationCoords { x: 7, y: 1 }, LocationCoords { x: 7, y: 2 });
-
-    // Bishop test
-    game.move_piece(LocationCoords { x: 5, y: 7 }, LocationCoords { x: 7, y: 5 });
-
-    // throwaway
-    game.move_piece(LocationCoords { x: 7, y: 2 }, LocationCoords { x: 5, y: 2 });
-
-    // Bishop reverse test
-    game.move_piece(LocationCoords { x: 7, y: 5 }, LocationCoords { x: 5, y: 7 });
-
-    // Pawn double step test
-    game.move_piece(LocationCoords { x: 0, y: 1 }, LocationCoords { x: 0, y: 3 });
-
-    // Invalid double move for pawn
-    game.move_piece(LocationCoords { x: 4, y: 5 }, LocationCoords { x: 4, y: 3 });
-    // Single move to validate after failed move
-    game.move_piece(LocationCoords { x: 4, y: 5 }, LocationCoords { x: 4, y: 4 });
+//! Command-line binary: two players take turns at the keyboard. See [`rs_chess::repl`].
+//!
+//! `rs_chess host [<address>]` and `rs_chess join <address>` play across two terminals instead,
+//! see [`rs_chess::lan`].
+
+use std::env;
+use std::io::{self, BufReader, IsTerminal};
+use std::process::ExitCode;
+
+use rs_chess::lan::{self, LanOptions};
+use rs_chess::player::Color;
+use rs_chess::render::BoardRenderer;
+use rs_chess::repl;
+
+const USAGE: &str = "\
+usage: rs_chess
+       rs_chess host [<address>] [--black] [--name <name>]
+       rs_chess join <address> [--name <name>]";
+
+fn main() -> ExitCode {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+
+    let mut args = env::args().skip(1);
+    let Some(mode) = args.next() else {
+        if let Err(error) = repl::run(stdin.lock(), io::stdout(), interactive, renderer()) {
+            eprintln!("rs_chess: {}", error);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    };
+
+    let mut options = LanOptions {
+        renderer: renderer(),
+        ..LanOptions::default()
+    };
+    let mut address: Option<String> = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--black" if mode == "host" => options.color = Color::Black,
+            "--name" => match args.next() {
+                Some(value) => options.name = value,
+                None => {
+                    eprintln!("--name needs a value\n{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            other if !other.starts_with('-') && address.is_none() => {
+                address = Some(other.to_string())
+            }
+            other => {
+                eprintln!("unknown option {}\n{}", other, USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let input = BufReader::new(stdin);
+    let result = match (mode.as_str(), address) {
+        ("-h" | "--help", _) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        ("host", address) => lan::host(
+            address.as_deref().unwrap_or(lan::DEFAULT_ADDRESS),
+            options,
+            input,
+            io::stdout(),
+        ),
+        ("join", Some(address)) => lan::join(&address, options, input, io::stdout()),
+        ("join", None) => {
+            eprintln!("join needs the host's address\n{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        (other, _) => {
+            eprintln!("unknown command {}\n{}", other, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("rs_chess: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // Test failed collision
-    game.move_piece(LocationCoords { x: 0, y: 0 }, LocationCoords { x: 0, y: 6 });
+// Glyphs and colors when the board goes to a terminal, without colors if `NO_COLOR` is set and not
+// empty, and plain ASCII otherwise.
+fn renderer() -> BoardRenderer {
+    if !io::stdout().is_terminal() {
+        return BoardRenderer::plain();
+    }
 
-    println!("{}", &game);
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    BoardRenderer::new().colors(!no_color)
 }
@@ -0,0 +1,507 @@
+use std::fmt;
+
+use crate::location::LocationCoords;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::{KING_VECS, KNIGHT_VECS, Position};
+
+const ROOK_VECS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_VECS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// A move from one square to another, with the piece a pawn promotes to.
+///
+/// Castling is written as the king's two square move, e.g. e1 to g1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChessMove {
+    pub from: LocationCoords,
+    pub to: LocationCoords,
+    pub promotion: Option<PieceType>,
+}
+
+impl ChessMove {
+    pub fn new(from: LocationCoords, to: LocationCoords) -> Self {
+        ChessMove {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    /// Parses long algebraic coordinate notation as used by UCI, e.g. "e2e4" or "e7e8q".
+    pub fn from_uci(text: &str) -> Option<Self> {
+        if text.len() != 4 && text.len() != 5 {
+            return None;
+        }
+
+        let from = LocationCoords::from_algebraic(text.get(0..2)?)?;
+        let to = LocationCoords::from_algebraic(text.get(2..4)?)?;
+        let promotion = match text.get(4..) {
+            Some("") => None,
+            Some("q") => Some(PieceType::Queen),
+            Some("r") => Some(PieceType::Rook),
+            Some("b") => Some(PieceType::Bishop),
+            Some("n") => Some(PieceType::Knight),
+            _ => return None,
+        };
+
+        Some(ChessMove {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+impl fmt::Display for ChessMove {
+    // Formats the move in UCI coordinate notation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+
+        if let Some(promotion) = self.promotion {
+            let c = match promotion {
+                PieceType::Queen => 'q',
+                PieceType::Rook => 'r',
+                PieceType::Bishop => 'b',
+                PieceType::Knight => 'n',
+                PieceType::Pawn => 'p',
+                PieceType::King => 'k',
+            };
+            write!(f, "{}", c)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Position {
+    /// All legal moves for the side to move.
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mover = self.side_to_move;
+
+        self.pseudo_legal_moves()
+            .into_iter()
+            .filter(|m| !self.make_move(m).is_in_check(mover))
+            .collect()
+    }
+
+    /// Whether `chess_move` is one of the legal moves in this position.
+    pub fn is_legal(&self, chess_move: &ChessMove) -> bool {
+        self.legal_moves().contains(chess_move)
+    }
+
+    /// Whether the side to move has no legal moves while in check.
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check(self.side_to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Whether the side to move has no legal moves but is not in check.
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_in_check(self.side_to_move) && self.legal_moves().is_empty()
+    }
+
+    /// Whether neither side has the material to mate: bare kings, a single minor piece, or
+    /// bishops that all stand on one square color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minors: Vec<(PieceType, usize)> = vec![];
+
+        for (y, row) in self.squares.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                match square {
+                    None | Some((_, PieceType::King)) => (),
+                    Some((_, piece_type @ (PieceType::Knight | PieceType::Bishop))) => {
+                        minors.push((*piece_type, (x + y) % 2))
+                    }
+                    Some(_) => return false,
+                }
+            }
+        }
+
+        match minors.as_slice() {
+            [] | [_] => true,
+            [(PieceType::Bishop, shade), rest @ ..] => rest
+                .iter()
+                .all(|(piece_type, s)| *piece_type == PieceType::Bishop && s == shade),
+            _ => false,
+        }
+    }
+
+    /// Whether `color` could mate if the other side had nothing but its king, as a win on time
+    /// needs.
+    pub fn has_mating_material(&self, color: Color) -> bool {
+        let mut position = self.clone();
+        for square in position.squares.iter_mut().flatten() {
+            if matches!(square, Some((owner, piece_type)) if *owner != color && *piece_type != PieceType::King)
+            {
+                *square = None;
+            }
+        }
+
+        !position.is_insufficient_material()
+    }
+
+    /// Whether `chess_move` takes a piece, including en passant.
+    pub fn is_capture(&self, chess_move: &ChessMove) -> bool {
+        self.piece_at(&chess_move.to).is_some() || self.is_en_passant(chess_move)
+    }
+
+    /// Whether `chess_move` is a pawn taking en passant.
+    pub fn is_en_passant(&self, chess_move: &ChessMove) -> bool {
+        matches!(self.piece_at(&chess_move.from), Some((_, PieceType::Pawn)))
+            && Some(chess_move.to) == self.en_passant
+            && chess_move.from.x != chess_move.to.x
+    }
+
+    /// Whether `chess_move` is a king castling.
+    pub fn is_castling(&self, chess_move: &ChessMove) -> bool {
+        matches!(self.piece_at(&chess_move.from), Some((_, PieceType::King)))
+            && chess_move.from.x.abs_diff(chess_move.to.x) == 2
+    }
+
+    /// Moves that follow the movement rules of each piece but may leave the own king in check.
+    pub fn pseudo_legal_moves(&self) -> Vec<ChessMove> {
+        let mut moves: Vec<ChessMove> = Vec::with_capacity(64);
+        let mover = self.side_to_move;
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let Some((color, piece_type)) = self.squares[y][x] else {
+                    continue;
+                };
+
+                if color != mover {
+                    continue;
+                }
+
+                let from = LocationCoords { x, y };
+
+                match piece_type {
+                    PieceType::Pawn => self.pawn_moves(&from, &mut moves),
+                    PieceType::Knight => self.step_moves(&from, &KNIGHT_VECS, &mut moves),
+                    PieceType::Bishop => self.slide_moves(&from, &BISHOP_VECS, &mut moves),
+                    PieceType::Rook => self.slide_moves(&from, &ROOK_VECS, &mut moves),
+                    PieceType::Queen => {
+                        self.slide_moves(&from, &BISHOP_VECS, &mut moves);
+                        self.slide_moves(&from, &ROOK_VECS, &mut moves);
+                    }
+                    PieceType::King => {
+                        self.step_moves(&from, &KING_VECS, &mut moves);
+                        self.castling_moves(&from, &mut moves);
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Plays a move and returns the resulting position.
+    ///
+    /// The move is assumed to be at least pseudo-legal. Captures, en passant, castling,
+    /// promotion, castling rights, the en passant square and both move counters are updated.
+    pub fn make_move(&self, chess_move: &ChessMove) -> Position {
+        let mut next = self.clone();
+        let from = chess_move.from;
+        let to = chess_move.to;
+
+        let Some((color, piece_type)) = self.piece_at(&from) else {
+            return next;
+        };
+
+        let capture = self.is_capture(chess_move);
+
+        if self.is_en_passant(chess_move) {
+            next.squares[from.y][to.x] = None;
+        }
+
+        if self.is_castling(chess_move) {
+            let (rook_from, rook_to) = if to.x > from.x { (7, 5) } else { (0, 3) };
+            next.squares[from.y][rook_to] = next.squares[from.y][rook_from].take();
+        }
+
+        next.squares[from.y][from.x] = None;
+        next.squares[to.y][to.x] = match chess_move.promotion {
+            Some(promotion) if piece_type == PieceType::Pawn => Some((color, promotion)),
+            _ => Some((color, piece_type)),
+        };
+
+        // A king move gives up both castling rights. Moving or capturing a rook on its corner
+        // gives up that side.
+        if piece_type == PieceType::King {
+            match color {
+                Color::White => {
+                    next.castling.white_kingside = false;
+                    next.castling.white_queenside = false;
+                }
+                Color::Black => {
+                    next.castling.black_kingside = false;
+                    next.castling.black_queenside = false;
+                }
+            }
+        }
+
+        for corner in [from, to] {
+            match (corner.x, corner.y) {
+                (7, 7) => next.castling.white_kingside = false,
+                (0, 7) => next.castling.white_queenside = false,
+                (7, 0) => next.castling.black_kingside = false,
+                (0, 0) => next.castling.black_queenside = false,
+                _ => (),
+            }
+        }
+
+        next.en_passant = None;
+        if piece_type == PieceType::Pawn && from.y.abs_diff(to.y) == 2 {
+            next.en_passant = Some(LocationCoords {
+                x: from.x,
+                y: (from.y + to.y) / 2,
+            });
+        }
+
+        if piece_type == PieceType::Pawn || capture {
+            next.halfmove_clock = 0;
+        } else {
+            next.halfmove_clock += 1;
+        }
+
+        if color == Color::Black {
+            next.fullmove_number += 1;
+        }
+
+        next.side_to_move = color.opponent();
+
+        next
+    }
+
+    fn pawn_moves(&self, from: &LocationCoords, moves: &mut Vec<ChessMove>) {
+        let Some((color, _)) = self.piece_at(from) else {
+            return;
+        };
+
+        let (direction, start_rank, last_rank) = match color {
+            Color::White => (-1, 6, 0),
+            Color::Black => (1, 1, 7),
+        };
+
+        let x = from.x as i32;
+        let y = from.y as i32;
+        let one_step = y + direction;
+
+        if !(0..=7).contains(&one_step) {
+            return;
+        }
+
+        let mut targets: Vec<LocationCoords> = vec![];
+
+        // Pushes, including the double step from the starting rank.
+        if self.squares[one_step as usize][from.x].is_none() {
+            targets.push(LocationCoords {
+                x: from.x,
+                y: one_step as usize,
+            });
+
+            let two_step = y + 2 * direction;
+            if from.y == start_rank && self.squares[two_step as usize][from.x].is_none() {
+                targets.push(LocationCoords {
+                    x: from.x,
+                    y: two_step as usize,
+                });
+            }
+        }
+
+        // Diagonal captures, including en passant.
+        for dx in [-1, 1] {
+            let target_x = x + dx;
+            if !(0..=7).contains(&target_x) {
+                continue;
+            }
+
+            let target = LocationCoords {
+                x: target_x as usize,
+                y: one_step as usize,
+            };
+
+            let enemy = match self.piece_at(&target) {
+                Some((target_color, _)) => target_color != color,
+                None => Some(target) == self.en_passant,
+            };
+
+            if enemy {
+                targets.push(target);
+            }
+        }
+
+        for to in targets {
+            if to.y == last_rank {
+                for promotion in PROMOTIONS {
+                    moves.push(ChessMove {
+                        from: *from,
+                        to,
+                        promotion: Some(promotion),
+                    });
+                }
+            } else {
+                moves.push(ChessMove::new(*from, to));
+            }
+        }
+    }
+
+    // Knight and king moves: a single hop along each vector.
+    fn step_moves(&self, from: &LocationCoords, vecs: &[(i32, i32)], moves: &mut Vec<ChessMove>) {
+        let Some((color, _)) = self.piece_at(from) else {
+            return;
+        };
+
+        for (dx, dy) in vecs {
+            let x = from.x as i32 + dx;
+            let y = from.y as i32 + dy;
+
+            if !(0..=7).contains(&x) || !(0..=7).contains(&y) {
+                continue;
+            }
+
+            let to = LocationCoords {
+                x: x as usize,
+                y: y as usize,
+            };
+
+            match self.piece_at(&to) {
+                Some((target_color, _)) if target_color == color => (),
+                _ => moves.push(ChessMove::new(*from, to)),
+            }
+        }
+    }
+
+    // Rook, bishop and queen moves: slide along each vector until blocked.
+    fn slide_moves(&self, from: &LocationCoords, vecs: &[(i32, i32)], moves: &mut Vec<ChessMove>) {
+        let Some((color, _)) = self.piece_at(from) else {
+            return;
+        };
+
+        for (dx, dy) in vecs {
+            let mut x = from.x as i32 + dx;
+            let mut y = from.y as i32 + dy;
+
+            while (0..=7).contains(&x) && (0..=7).contains(&y) {
+                let to = LocationCoords {
+                    x: x as usize,
+                    y: y as usize,
+                };
+
+                match self.piece_at(&to) {
+                    None => moves.push(ChessMove::new(*from, to)),
+                    Some((target_color, _)) => {
+                        if target_color != color {
+                            moves.push(ChessMove::new(*from, to));
+                        }
+                        break;
+                    }
+                }
+
+                x += dx;
+                y += dy;
+            }
+        }
+    }
+
+    fn castling_moves(&self, from: &LocationCoords, moves: &mut Vec<ChessMove>) {
+        let color = self.side_to_move;
+        let (kingside, queenside, home_rank) = match color {
+            Color::White => (
+                self.castling.white_kingside,
+                self.castling.white_queenside,
+                7,
+            ),
+            Color::Black => (
+                self.castling.black_kingside,
+                self.castling.black_queenside,
+                0,
+            ),
+        };
+
+        if from.y != home_rank || from.x != 4 {
+            return;
+        }
+
+        if !kingside && !queenside {
+            return;
+        }
+
+        let enemy = color.opponent();
+        if self.is_square_attacked(from, enemy) {
+            return;
+        }
+
+        let empty = |x: usize| self.squares[home_rank][x].is_none();
+        let safe = |x: usize| !self.is_square_attacked(&LocationCoords { x, y: home_rank }, enemy);
+        let rook_home = |x: usize| self.squares[home_rank][x] == Some((color, PieceType::Rook));
+
+        if kingside && rook_home(7) && empty(5) && empty(6) && safe(5) && safe(6) {
+            moves.push(ChessMove::new(*from, LocationCoords { x: 6, y: home_rank }));
+        }
+
+        if queenside && rook_home(0) && empty(1) && empty(2) && empty(3) && safe(3) && safe(2) {
+            moves.push(ChessMove::new(*from, LocationCoords { x: 2, y: home_rank }));
+        }
+    }
+}
+
+/// Counts the leaf nodes of the legal move tree to `depth`. Used to verify move generation
+/// against published perft results.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .iter()
+        .map(|m| perft(&position.make_move(m), depth - 1))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::STARTING_FEN;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn perft_start_position() {
+        let position = Position::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(perft(&position, 1), 20);
+        assert_eq!(perft(&position, 2), 400);
+        assert_eq!(perft(&position, 3), 8_902);
+        assert_eq!(perft(&position, 4), 197_281);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let position = Position::from_fen(KIWIPETE).unwrap();
+        assert_eq!(perft(&position, 1), 48);
+        assert_eq!(perft(&position, 2), 2_039);
+        assert_eq!(perft(&position, 3), 97_862);
+    }
+
+    #[test]
+    fn mating_material_ignores_the_other_side() {
+        // Only the side's own pieces count, so a knight is not enough next to a queen.
+        let position = Position::from_fen("4k3/8/8/8/8/8/3q4/1N2K3 w - - 0 1").unwrap();
+        assert!(!position.has_mating_material(Color::White));
+        assert!(position.has_mating_material(Color::Black));
+
+        let position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert!(position.has_mating_material(Color::White));
+        assert!(!position.has_mating_material(Color::Black));
+    }
+}
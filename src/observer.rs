@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::game::{DrawReason, Game};
+use crate::location::LocationCoords;
+use crate::piece::Piece;
+use crate::player::Player;
+
+/// Callbacks fired by [`Game`] while moves are played.
+///
+/// Register an observer with [`Game::add_observer`]. Every callback has an empty default
+/// implementation so observers only need to implement the events they care about.
+///
+/// Events are only fired for successful moves, after the board, pieces and players have been
+/// fully updated. For a single move they always arrive in this order:
+///
+/// 1. [`on_move`](GameObserver::on_move)
+/// 2. [`on_capture`](GameObserver::on_capture), if an enemy piece was taken
+/// 3. [`on_promotion`](GameObserver::on_promotion), if a pawn reached the last rank
+/// 4. [`on_check`](GameObserver::on_check), if the enemy king is now attacked
+/// 5. [`on_game_over`](GameObserver::on_game_over) if the move checkmated,
+///    [`on_draw`](GameObserver::on_draw) if it drew the game, otherwise
+///    [`on_turn_change`](GameObserver::on_turn_change)
+pub trait GameObserver {
+    /// A piece moved from `source` to `dest`.
+    fn on_move(
+        &mut self,
+        _game: &Game,
+        _piece: &Piece,
+        _source: &LocationCoords,
+        _dest: &LocationCoords,
+    ) {
+    }
+
+    /// `captured` was taken by `attacker`. The captured piece has already been moved into its
+    /// owner's `dead_pieces`.
+    fn on_capture(&mut self, _game: &Game, _attacker: &Piece, _captured: &Piece) {}
+
+    /// `pawn` reached the last rank and was replaced on the board by `promoted`.
+    fn on_promotion(&mut self, _game: &Game, _pawn: &Piece, _promoted: &Piece) {}
+
+    /// `king` is in check after the last move.
+    fn on_check(&mut self, _game: &Game, _king: &Piece) {}
+
+    /// The turn passed to `player`.
+    fn on_turn_change(&mut self, _game: &Game, _player: &Player) {}
+
+    /// The game ended with `winner` putting the enemy king in checkmate. The loser is left to
+    /// move.
+    fn on_game_over(&mut self, _game: &Game, _winner: &Player) {}
+
+    /// The game ended in a draw the rules impose, e.g. stalemate.
+    fn on_draw(&mut self, _game: &Game, _reason: DrawReason) {}
+}
+
+impl fmt::Debug for dyn GameObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("GameObserver")
+    }
+}
@@ -0,0 +1,223 @@
+//! Reading and writing games in Portable Game Notation.
+//!
+//! Only the main line is kept: comments, variations, numeric annotation glyphs and move numbers
+//! are skipped. Moves stay SAN text until [`PgnGame::mainline`] replays them. Displaying a
+//! [`PgnGame`] writes it back out in export format.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::movegen::ChessMove;
+use crate::player::Color;
+use crate::position::{FenError, Position, STARTING_FEN};
+use crate::san::SanError;
+
+/// One game from a PGN file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in file order, e.g. `("White", "Carlsen, Magnus")`.
+    pub tags: Vec<(String, String)>,
+    /// Main line moves in SAN.
+    pub moves: Vec<String>,
+    /// Game termination marker: `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: Option<String>,
+}
+
+/// Reasons a PGN game could not be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// The `FEN` tag does not hold a valid position.
+    BadFen(FenError),
+    /// A move in the main line could not be played. `ply` counts from 0.
+    BadMove {
+        ply: usize,
+        san: String,
+        error: SanError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::BadFen(error) => write!(f, "bad FEN tag: {}", error),
+            PgnError::BadMove { ply, san, error } => {
+                write!(f, "move {} ({}): {}", ply / 2 + 1, san, error)
+            }
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl PgnGame {
+    /// Value of the first tag called `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from: the `FEN` tag if present, otherwise the standard
+    /// starting position.
+    pub fn start_position(&self) -> Result<Position, PgnError> {
+        Position::from_fen(self.tag("FEN").unwrap_or(STARTING_FEN)).map_err(PgnError::BadFen)
+    }
+
+    /// Replays the main line from the start position.
+    pub fn mainline(&self) -> Result<Vec<ChessMove>, PgnError> {
+        let mut position = self.start_position()?;
+        let mut moves: Vec<ChessMove> = Vec::with_capacity(self.moves.len());
+
+        for (ply, san) in self.moves.iter().enumerate() {
+            let chess_move = position.parse_san(san).map_err(|error| PgnError::BadMove {
+                ply,
+                san: san.clone(),
+                error,
+            })?;
+            position = position.make_move(&chess_move);
+            moves.push(chess_move);
+        }
+
+        Ok(moves)
+    }
+}
+
+// Export format keeps movetext lines under 80 characters.
+const LINE_WIDTH: usize = 79;
+
+/// Writes the tag pairs, a blank line and the movetext with move numbers, ending with the result
+/// (`*` when there is none).
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{} \"{}\"]", name, value)?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f)?;
+        }
+
+        // Move numbers follow the start position, which may have Black to move.
+        let (mut number, mut color) = match self.start_position() {
+            Ok(position) => (position.fullmove_number.max(1), position.side_to_move),
+            Err(_) => (1, Color::White),
+        };
+
+        let mut tokens: Vec<String> = vec![];
+        for (ply, san) in self.moves.iter().enumerate() {
+            match color {
+                Color::White => tokens.push(format!("{}. {}", number, san)),
+                Color::Black if ply == 0 => tokens.push(format!("{}... {}", number, san)),
+                Color::Black => tokens.push(san.clone()),
+            }
+
+            if color == Color::Black {
+                number += 1;
+            }
+            color = color.opponent();
+        }
+        tokens.push(self.result.clone().unwrap_or_else(|| "*".to_string()));
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_WIDTH {
+                writeln!(f)?;
+                line_len = 0;
+            }
+            if line_len > 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+            write!(f, "{}", token)?;
+            line_len += token.len();
+        }
+
+        writeln!(f)
+    }
+}
+
+/// Splits PGN text into games. Text that is not understood is skipped rather than rejected, so
+/// one damaged game does not lose the rest of a collection.
+pub fn parse_games(text: &str) -> Vec<PgnGame> {
+    let mut games: Vec<PgnGame> = vec![];
+    let mut game = PgnGame::default();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                // Tags after moves start the next game, even without a termination marker.
+                if !game.moves.is_empty() {
+                    games.push(std::mem::take(&mut game));
+                }
+
+                let tag: String = chars.by_ref().take_while(|c| *c != ']').collect();
+                if let Some((name, value)) = tag.trim().split_once(char::is_whitespace) {
+                    let value = value.trim().trim_matches('"').replace("\\\"", "\"");
+                    game.tags.push((name.to_string(), value));
+                }
+            }
+            '{' => {
+                chars.by_ref().take_while(|c| *c != '}').for_each(drop);
+            }
+            ';' | '%' => {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+            }
+            '(' => {
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => (),
+            _ => {
+                let mut token = String::from(c);
+                while let Some(next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '{' | '(' | ')' | ';' | '[') {
+                        break;
+                    }
+                    token.push(*next);
+                    chars.next();
+                }
+
+                if matches!(token.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    game.result = Some(token);
+                    games.push(std::mem::take(&mut game));
+                } else if let Some(san) = move_token(&token) {
+                    game.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    if !game.moves.is_empty() || !game.tags.is_empty() {
+        games.push(game);
+    }
+
+    games
+}
+
+// Strips a leading move number ("12." or "12...") and skips glyphs like "$1". Digits not
+// followed by a dot are kept, as in "0-0".
+fn move_token(token: &str) -> Option<&str> {
+    if token.starts_with('$') {
+        return None;
+    }
+
+    let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let san = if token[digits..].starts_with('.') {
+        token[digits..].trim_start_matches('.')
+    } else {
+        token
+    };
+
+    if san.is_empty() { None } else { Some(san) }
+}
@@ -1,13 +1,15 @@
 use std::cell::RefCell;
 use std::fmt;
 use std::rc::Rc;
+use tracing::trace;
 use uuid::Uuid;
 
 use crate::location::LocationCoords;
 use crate::player::Player;
 use crate::utils::vectors_same_direction;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -17,6 +19,19 @@ pub enum PieceType {
     King,
 }
 
+impl PieceType {
+    /// The usual material value in pawns: 1, 3, 3, 5 and 9, and 0 for the king.
+    pub fn points(self) -> u32 {
+        match self {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+        }
+    }
+}
+
 pub struct Piece {
     pub piece_type: PieceType,
     pub owner: Rc<Player>,
@@ -27,9 +42,15 @@ pub struct Piece {
 
 impl Piece {
     pub fn validate_attack(&self, attack_vec: &(i32, i32)) -> bool {
+        trace!(
+            piece = %self.id,
+            piece_type = ?self.piece_type,
+            vector = ?attack_vec,
+            "validating attack"
+        );
+
         match self.piece_type {
             PieceType::Pawn => {
-                println!("DO PAWN ATTACK");
                 let valid_attack: (i32, i32) = (1, 1);
 
                 // Validate vector matches attack vector in any direction.
@@ -37,13 +58,9 @@ impl Piece {
                     return false;
                 }
 
-                // Validate vector matches direction of owner's pawn direction.
-                if *attack_vec
-                    != (
-                        valid_attack.0 * self.owner.pawn_direction,
-                        valid_attack.1 * self.owner.pawn_direction,
-                    )
-                {
+                // Validate vector matches direction of owner's pawn direction. Pawns attack
+                // diagonally to either side.
+                if attack_vec.1 != valid_attack.1 * self.owner.pawn_direction {
                     return false;
                 }
 
@@ -55,11 +72,16 @@ impl Piece {
     }
 
     pub fn validate_move(&self, move_vec: &(i32, i32)) -> bool {
+        trace!(
+            piece = %self.id,
+            piece_type = ?self.piece_type,
+            vector = ?move_vec,
+            "validating move"
+        );
+
         // Check piece capabilities
         match self.piece_type {
             PieceType::Pawn => {
-                println!("DO PAWN MOVE");
-
                 if !*self.has_moved.borrow() {
                     if !(move_vec.1.abs() >= 1 && move_vec.1.abs() <= 2) {
                         return false;
@@ -84,8 +106,6 @@ impl Piece {
                 true
             }
             PieceType::Rook => {
-                println!("DO ROOK MOVE");
-
                 let valid_vecs: Vec<(i32, i32)> = vec![(0, 1), (1, 0)];
                 let mut valid_move: bool = false;
 
@@ -103,8 +123,6 @@ impl Piece {
                 true
             }
             PieceType::Knight => {
-                println!("DO KNIGHT MOVE");
-
                 let valid_vecs: Vec<(i32, i32)> = vec![(2, 1), (1, 2)];
 
                 let mut valid_move: bool = false;
@@ -123,8 +141,6 @@ impl Piece {
                 true
             }
             PieceType::Bishop => {
-                println!("DO BISHOP MOVE");
-
                 let valid_vecs: Vec<(i32, i32)> = vec![(1, 1)];
 
                 let mut valid_move: bool = false;
@@ -143,8 +159,6 @@ impl Piece {
                 true
             }
             PieceType::Queen => {
-                println!("DO QUEEN MOVE");
-
                 let valid_vecs: Vec<(i32, i32)> = vec![(1, 1), (1, 0), (0, 1)];
 
                 let mut valid_move: bool = false;
@@ -163,8 +177,6 @@ impl Piece {
                 true
             }
             PieceType::King => {
-                println!("DO KING MOVE");
-
                 let valid_vecs: Vec<(i32, i32)> = vec![(0, 1), (1, 0), (1, 1)];
 
                 let mut valid_move: bool = false;
@@ -0,0 +1,628 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::location::LocationCoords;
+use crate::piece::PieceType;
+use crate::player::Color;
+
+/// FEN of the standard starting position.
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Which castling moves are still available to each side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        CastlingRights::default()
+    }
+}
+
+/// A plain snapshot of a chess position.
+///
+/// Unlike [`Game`](crate::game::Game) this holds no players or shared piece objects, so it is
+/// cheap to copy and can be built from FEN, board editors or puzzles. `squares` is indexed
+/// `[y][x]` with the same orientation as `Game::board`: `y = 0` is Black's back rank.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub squares: [[Option<(Color, PieceType)>; 8]; 8],
+    pub side_to_move: Color,
+    pub castling: CastlingRights,
+    pub en_passant: Option<LocationCoords>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+}
+
+/// Reasons a FEN string could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// The FEN has fewer than four or more than six space separated fields.
+    FieldCount(usize),
+    /// The piece placement field does not describe exactly 8 ranks of 8 squares.
+    BadPlacement(String),
+    /// An unknown character was found in the piece placement field.
+    BadPiece(char),
+    /// The side to move is not `w` or `b`.
+    BadSideToMove(String),
+    /// The castling field contains something other than `KQkq` or `-`.
+    BadCastling(String),
+    /// The en passant field is not `-` or a square.
+    BadEnPassant(String),
+    /// The halfmove clock or fullmove number is not a number.
+    BadCounter(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::FieldCount(count) => write!(f, "expected 4 to 6 FEN fields, found {}", count),
+            FenError::BadPlacement(placement) => {
+                write!(f, "invalid piece placement '{}'", placement)
+            }
+            FenError::BadPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::BadSideToMove(side) => write!(f, "invalid side to move '{}'", side),
+            FenError::BadCastling(castling) => write!(f, "invalid castling rights '{}'", castling),
+            FenError::BadEnPassant(square) => write!(f, "invalid en passant square '{}'", square),
+            FenError::BadCounter(counter) => write!(f, "invalid move counter '{}'", counter),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// A reason a position could not arise in a legal game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionViolation {
+    /// A side does not have exactly one king.
+    KingCount { color: Color, count: usize },
+    /// A pawn stands on the first or last rank.
+    PawnOnBackRank { square: LocationCoords },
+    /// The side that just moved left its king in check.
+    OpponentInCheck { color: Color },
+    /// A side has more pawns than the 8 it started with.
+    TooManyPawns { color: Color, count: usize },
+    /// A side has more pieces than the 16 it started with.
+    TooManyPieces { color: Color, count: usize },
+    /// A side has more extra queens, rooks, bishops and knights than it could have promoted with
+    /// its missing pawns.
+    TooManyPromotions {
+        color: Color,
+        promoted: usize,
+        missing_pawns: usize,
+    },
+    /// A castling right is set but the king or rook is not on its home square.
+    CastlingRightsMismatch { color: Color, kingside: bool },
+    /// The en passant square does not sit behind a pawn that just made a double step.
+    BadEnPassant { square: LocationCoords },
+}
+
+impl fmt::Display for PositionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionViolation::KingCount { color, count } => {
+                write!(f, "{:?} has {} kings", color, count)
+            }
+            PositionViolation::PawnOnBackRank { square } => {
+                write!(f, "pawn on first or last rank at {}", square)
+            }
+            PositionViolation::OpponentInCheck { color } => {
+                write!(f, "{:?} is in check but it is not their turn", color)
+            }
+            PositionViolation::TooManyPawns { color, count } => {
+                write!(f, "{:?} has {} pawns", color, count)
+            }
+            PositionViolation::TooManyPieces { color, count } => {
+                write!(f, "{:?} has {} pieces", color, count)
+            }
+            PositionViolation::TooManyPromotions {
+                color,
+                promoted,
+                missing_pawns,
+            } => write!(
+                f,
+                "{:?} has {} promoted pieces but only {} missing pawns",
+                color, promoted, missing_pawns
+            ),
+            PositionViolation::CastlingRightsMismatch { color, kingside } => write!(
+                f,
+                "{:?} may castle {} but king or rook has left its square",
+                color,
+                if *kingside { "kingside" } else { "queenside" }
+            ),
+            PositionViolation::BadEnPassant { square } => {
+                write!(
+                    f,
+                    "en passant square {} does not follow a double pawn step",
+                    square
+                )
+            }
+        }
+    }
+}
+
+impl Error for PositionViolation {}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::from_fen(STARTING_FEN).expect("starting FEN is valid")
+    }
+}
+
+impl Position {
+    /// An empty board with White to move and no castling rights.
+    pub fn empty() -> Self {
+        Position {
+            squares: [[None; 8]; 8],
+            side_to_move: Color::White,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    /// Parses a position from Forsyth-Edwards Notation.
+    ///
+    /// The halfmove clock and fullmove number may be omitted, as in EPD.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 || fields.len() > 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let mut position = Position::empty();
+
+        // Piece placement, rank 8 first. That matches y = 0 on the board.
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::BadPlacement(fields[0].to_string()));
+        }
+
+        for (y, rank) in ranks.iter().enumerate() {
+            let mut x: usize = 0;
+
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    x += skip as usize;
+                    continue;
+                }
+
+                if x > 7 {
+                    return Err(FenError::BadPlacement(fields[0].to_string()));
+                }
+
+                position.squares[y][x] = Some(piece_from_char(c).ok_or(FenError::BadPiece(c))?);
+                x += 1;
+            }
+
+            if x != 8 {
+                return Err(FenError::BadPlacement(fields[0].to_string()));
+            }
+        }
+
+        position.side_to_move = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(FenError::BadSideToMove(other.to_string())),
+        };
+
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                match c {
+                    'K' => position.castling.white_kingside = true,
+                    'Q' => position.castling.white_queenside = true,
+                    'k' => position.castling.black_kingside = true,
+                    'q' => position.castling.black_queenside = true,
+                    _ => return Err(FenError::BadCastling(fields[2].to_string())),
+                }
+            }
+        }
+
+        if fields[3] != "-" {
+            let square = LocationCoords::from_algebraic(fields[3])
+                .ok_or_else(|| FenError::BadEnPassant(fields[3].to_string()))?;
+            position.en_passant = Some(square);
+        }
+
+        if let Some(halfmove) = fields.get(4) {
+            position.halfmove_clock = halfmove
+                .parse()
+                .map_err(|_| FenError::BadCounter(halfmove.to_string()))?;
+        }
+
+        if let Some(fullmove) = fields.get(5) {
+            position.fullmove_number = fullmove
+                .parse()
+                .map_err(|_| FenError::BadCounter(fullmove.to_string()))?;
+        }
+
+        Ok(position)
+    }
+
+    /// Formats the position as Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for (y, row) in self.squares.iter().enumerate() {
+            let mut empty: u32 = 0;
+
+            for square in row {
+                match square {
+                    Some((color, piece_type)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_to_char(*color, *piece_type));
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+
+            if y < 7 {
+                fen.push('/');
+            }
+        }
+
+        fen.push(' ');
+        fen.push(match self.side_to_move {
+            Color::White => 'w',
+            Color::Black => 'b',
+        });
+
+        fen.push(' ');
+        let castling_start = fen.len();
+        if self.castling.white_kingside {
+            fen.push('K');
+        }
+        if self.castling.white_queenside {
+            fen.push('Q');
+        }
+        if self.castling.black_kingside {
+            fen.push('k');
+        }
+        if self.castling.black_queenside {
+            fen.push('q');
+        }
+        if fen.len() == castling_start {
+            fen.push('-');
+        }
+
+        fen.push(' ');
+        match &self.en_passant {
+            Some(square) => fen.push_str(&square.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+
+        fen
+    }
+
+    pub fn piece_at(&self, square: &LocationCoords) -> Option<(Color, PieceType)> {
+        self.squares[square.y][square.x]
+    }
+
+    /// Finds the king of `color`, if there is one on the board.
+    pub fn king_square(&self, color: Color) -> Option<LocationCoords> {
+        for (y, row) in self.squares.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                if *square == Some((color, PieceType::King)) {
+                    return Some(LocationCoords { x, y });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Whether any piece of color `by` attacks `square`.
+    pub fn is_square_attacked(&self, square: &LocationCoords, by: Color) -> bool {
+        let x = square.x as i32;
+        let y = square.y as i32;
+
+        // Pawns attack diagonally towards the enemy, so look one rank back from the target.
+        let pawn_rank = match by {
+            Color::White => y + 1,
+            Color::Black => y - 1,
+        };
+        for dx in [-1, 1] {
+            if self.occupant(x + dx, pawn_rank) == Some((by, PieceType::Pawn)) {
+                return true;
+            }
+        }
+
+        for (dx, dy) in KNIGHT_VECS {
+            if self.occupant(x + dx, y + dy) == Some((by, PieceType::Knight)) {
+                return true;
+            }
+        }
+
+        for (dx, dy) in KING_VECS {
+            if self.occupant(x + dx, y + dy) == Some((by, PieceType::King)) {
+                return true;
+            }
+        }
+
+        for (dx, dy) in KING_VECS {
+            let diagonal = dx != 0 && dy != 0;
+            let mut curr_x = x + dx;
+            let mut curr_y = y + dy;
+
+            while (0..=7).contains(&curr_x) && (0..=7).contains(&curr_y) {
+                if let Some((color, piece_type)) = self.squares[curr_y as usize][curr_x as usize] {
+                    if color == by {
+                        let slides = match piece_type {
+                            PieceType::Queen => true,
+                            PieceType::Bishop => diagonal,
+                            PieceType::Rook => !diagonal,
+                            _ => false,
+                        };
+
+                        if slides {
+                            return true;
+                        }
+                    }
+                    break;
+                }
+
+                curr_x += dx;
+                curr_y += dy;
+            }
+        }
+
+        false
+    }
+
+    /// Whether the king of `color` is attacked.
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.king_square(color) {
+            Some(king) => self.is_square_attacked(&king, color.opponent()),
+            None => false,
+        }
+    }
+
+    /// Checks that the position could have arisen in a legal game.
+    ///
+    /// Every violation found is reported, not just the first one.
+    pub fn validate(&self) -> Result<(), Vec<PositionViolation>> {
+        let mut violations: Vec<PositionViolation> = vec![];
+
+        for color in [Color::White, Color::Black] {
+            self.check_material(color, &mut violations);
+            self.check_castling(color, &mut violations);
+        }
+
+        // Pawns can never stand on the first or last rank.
+        for y in [0, 7] {
+            for x in 0..8 {
+                if let Some((_, PieceType::Pawn)) = self.squares[y][x] {
+                    violations.push(PositionViolation::PawnOnBackRank {
+                        square: LocationCoords { x, y },
+                    });
+                }
+            }
+        }
+
+        // The side that just moved cannot have left its own king in check.
+        let waiting = self.side_to_move.opponent();
+        if self.is_in_check(waiting) {
+            violations.push(PositionViolation::OpponentInCheck { color: waiting });
+        }
+
+        if let Some(square) = &self.en_passant
+            && !self.en_passant_consistent(square)
+        {
+            violations.push(PositionViolation::BadEnPassant { square: *square });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn check_material(&self, color: Color, violations: &mut Vec<PositionViolation>) {
+        let mut counts = [0usize; 6];
+
+        for row in &self.squares {
+            for (piece_color, piece_type) in row.iter().flatten() {
+                if *piece_color == color {
+                    counts[piece_index(*piece_type)] += 1;
+                }
+            }
+        }
+
+        let kings = counts[piece_index(PieceType::King)];
+        let pawns = counts[piece_index(PieceType::Pawn)];
+        let total: usize = counts.iter().sum();
+
+        if kings != 1 {
+            violations.push(PositionViolation::KingCount {
+                color,
+                count: kings,
+            });
+        }
+
+        if pawns > 8 {
+            violations.push(PositionViolation::TooManyPawns {
+                color,
+                count: pawns,
+            });
+        }
+
+        if total > 16 {
+            violations.push(PositionViolation::TooManyPieces {
+                color,
+                count: total,
+            });
+        }
+
+        // Anything above the starting set must have come from a promoted pawn.
+        let promoted = counts[piece_index(PieceType::Queen)].saturating_sub(1)
+            + counts[piece_index(PieceType::Rook)].saturating_sub(2)
+            + counts[piece_index(PieceType::Bishop)].saturating_sub(2)
+            + counts[piece_index(PieceType::Knight)].saturating_sub(2);
+        let missing_pawns = 8usize.saturating_sub(pawns);
+
+        if promoted > missing_pawns {
+            violations.push(PositionViolation::TooManyPromotions {
+                color,
+                promoted,
+                missing_pawns,
+            });
+        }
+    }
+
+    fn check_castling(&self, color: Color, violations: &mut Vec<PositionViolation>) {
+        let (kingside, queenside, home_rank) = match color {
+            Color::White => (
+                self.castling.white_kingside,
+                self.castling.white_queenside,
+                7,
+            ),
+            Color::Black => (
+                self.castling.black_kingside,
+                self.castling.black_queenside,
+                0,
+            ),
+        };
+
+        let king_home = self.squares[home_rank][4] == Some((color, PieceType::King));
+
+        if kingside && !(king_home && self.squares[home_rank][7] == Some((color, PieceType::Rook)))
+        {
+            violations.push(PositionViolation::CastlingRightsMismatch {
+                color,
+                kingside: true,
+            });
+        }
+
+        if queenside && !(king_home && self.squares[home_rank][0] == Some((color, PieceType::Rook)))
+        {
+            violations.push(PositionViolation::CastlingRightsMismatch {
+                color,
+                kingside: false,
+            });
+        }
+    }
+
+    // The en passant square must be empty and sit directly behind an enemy pawn that just made a
+    // double step, with the square it came from now empty.
+    fn en_passant_consistent(&self, square: &LocationCoords) -> bool {
+        let mover = self.side_to_move.opponent();
+
+        // (en passant rank, pushed pawn rank, pawn origin rank) for the side that just moved.
+        let (ep_rank, pawn_rank, origin_rank) = match mover {
+            Color::White => (5, 4, 6),
+            Color::Black => (2, 3, 1),
+        };
+
+        square.y == ep_rank
+            && self.squares[ep_rank][square.x].is_none()
+            && self.squares[origin_rank][square.x].is_none()
+            && self.squares[pawn_rank][square.x] == Some((mover, PieceType::Pawn))
+    }
+
+    // Piece at signed coordinates, treating anything off the board as empty.
+    fn occupant(&self, x: i32, y: i32) -> Option<(Color, PieceType)> {
+        if (0..=7).contains(&x) && (0..=7).contains(&y) {
+            self.squares[y as usize][x as usize]
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) const KNIGHT_VECS: [(i32, i32); 8] = [
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+];
+
+pub(crate) const KING_VECS: [(i32, i32); 8] = [
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+    (0, 1),
+    (0, -1),
+    (1, 0),
+    (-1, 0),
+];
+
+pub(crate) fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+/// Maps a FEN piece letter to its color and type. Uppercase letters are White.
+pub fn piece_from_char(c: char) -> Option<(Color, PieceType)> {
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+
+    let piece_type = match c.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+
+    Some((color, piece_type))
+}
+
+/// Maps a color and piece type to its FEN letter. Uppercase letters are White.
+pub fn piece_to_char(color: Color, piece_type: PieceType) -> char {
+    let c = match piece_type {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+
+    match color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
@@ -0,0 +1,190 @@
+//! PNG images and animated GIFs of boards and games.
+//!
+//! Boards are drawn by an [`SvgRenderer`] and rasterized in-process, so its theme, pieces and
+//! overlays carry over. Coordinates and glyph pieces need a sans-serif system font; the built-in
+//! pieces need none.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use gif::{Encoder, Frame, Repeat};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
+
+use crate::game::Game;
+use crate::movegen::ChessMove;
+use crate::pgn::{PgnError, PgnGame};
+use crate::position::Position;
+use crate::svg::SvgRenderer;
+
+// Tried in order for the generic sans-serif family when the system default is missing.
+const SANS_SERIF_FAMILIES: [&str; 5] = [
+    "Arial",
+    "Helvetica",
+    "DejaVu Sans",
+    "Liberation Sans",
+    "Noto Sans",
+];
+
+// NeuQuant sampling speed from 1 (best) to 30 (fastest).
+const QUANTIZE_SPEED: i32 = 10;
+
+/// Reasons an image could not be made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RasterError {
+    /// The board is too large or too small to rasterize, in pixels.
+    Size(u32),
+    /// The drawn SVG could not be read back.
+    Svg(String),
+    /// Image encoding failed.
+    Encode(String),
+    /// The moves of a PGN game could not be replayed.
+    Pgn(PgnError),
+}
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RasterError::Size(size) => write!(f, "a {} pixel board cannot be rasterized", size),
+            RasterError::Svg(message) => write!(f, "bad board SVG: {}", message),
+            RasterError::Encode(message) => write!(f, "image encoding failed: {}", message),
+            RasterError::Pgn(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for RasterError {}
+
+/// Timing of the frames of an animated GIF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifOptions {
+    /// How long each position is shown before the next move.
+    pub delay: Duration,
+    /// How long the final position is shown before the animation repeats or stops.
+    pub final_delay: Duration,
+    /// Whether the animation loops forever.
+    pub repeat: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            delay: Duration::from_secs(1),
+            final_delay: Duration::from_secs(3),
+            repeat: true,
+        }
+    }
+}
+
+/// Draws `position` as a PNG, marking `last_move` if given.
+pub fn position_png(
+    renderer: &SvgRenderer,
+    position: &Position,
+    last_move: Option<&ChessMove>,
+) -> Result<Vec<u8>, RasterError> {
+    let options = svg_options();
+    let pixmap = rasterize(renderer, &options, position, last_move)?;
+    pixmap
+        .encode_png()
+        .map_err(|error| RasterError::Encode(error.to_string()))
+}
+
+/// Draws the board of `game` as a PNG, with its last move.
+pub fn game_png(renderer: &SvgRenderer, game: &Game) -> Result<Vec<u8>, RasterError> {
+    position_png(renderer, &game.to_position(), game.last_move().as_ref())
+}
+
+/// Animates `moves` played from `start` as a GIF, one frame per position. The moves must be
+/// legal.
+pub fn moves_gif(
+    renderer: &SvgRenderer,
+    start: &Position,
+    moves: &[ChessMove],
+    gif_options: &GifOptions,
+) -> Result<Vec<u8>, RasterError> {
+    let size = renderer.size();
+    let side = u16::try_from(size).map_err(|_| RasterError::Size(size))?;
+    let encode_error = |error: gif::EncodingError| RasterError::Encode(error.to_string());
+
+    let options = svg_options();
+    let mut bytes = Vec::new();
+    let mut encoder = Encoder::new(&mut bytes, side, side, &[]).map_err(encode_error)?;
+    if gif_options.repeat {
+        encoder.set_repeat(Repeat::Infinite).map_err(encode_error)?;
+    }
+
+    let mut position = start.clone();
+    for ply in 0..=moves.len() {
+        let last_move = ply.checked_sub(1).map(|index| &moves[index]);
+        let mut pixmap = rasterize(renderer, &options, &position, last_move)?;
+
+        let mut frame = Frame::from_rgba_speed(side, side, pixmap.data_mut(), QUANTIZE_SPEED);
+        frame.delay = centiseconds(if ply == moves.len() {
+            gif_options.final_delay
+        } else {
+            gif_options.delay
+        });
+        encoder.write_frame(&frame).map_err(encode_error)?;
+
+        if let Some(chess_move) = moves.get(ply) {
+            position = position.make_move(chess_move);
+        }
+    }
+
+    drop(encoder);
+    Ok(bytes)
+}
+
+/// Animates the main line of a PGN game as a GIF.
+pub fn pgn_gif(
+    renderer: &SvgRenderer,
+    game: &PgnGame,
+    gif_options: &GifOptions,
+) -> Result<Vec<u8>, RasterError> {
+    let start = game.start_position().map_err(RasterError::Pgn)?;
+    let moves = game.mainline().map_err(RasterError::Pgn)?;
+    moves_gif(renderer, &start, &moves, gif_options)
+}
+
+fn rasterize(
+    renderer: &SvgRenderer,
+    options: &Options,
+    position: &Position,
+    last_move: Option<&ChessMove>,
+) -> Result<Pixmap, RasterError> {
+    let size = renderer.size();
+    let svg = renderer.render_position(position, last_move);
+    let tree =
+        Tree::from_str(&svg, options).map_err(|error| RasterError::Svg(error.to_string()))?;
+
+    let mut pixmap = Pixmap::new(size, size).ok_or(RasterError::Size(size))?;
+    resvg::render(&tree, Transform::default(), &mut pixmap.as_mut());
+    Ok(pixmap)
+}
+
+// System fonts are loaded once per image or animation, as that is the slow part of the setup.
+fn svg_options() -> Options<'static> {
+    let mut options = Options::default();
+    let fontdb = options.fontdb_mut();
+    fontdb.load_system_fonts();
+
+    let installed = |family: &str| {
+        fontdb
+            .faces()
+            .any(|face| face.families.iter().any(|(name, _)| name == family))
+    };
+    if let Some(family) = SANS_SERIF_FAMILIES
+        .into_iter()
+        .find(|family| installed(family))
+    {
+        fontdb.set_sans_serif_family(family);
+    }
+
+    options
+}
+
+// GIF delays count hundredths of a second.
+fn centiseconds(delay: Duration) -> u16 {
+    (delay.as_millis() / 10).min(u16::MAX as u128) as u16
+}
@@ -0,0 +1,274 @@
+//! Text rendering of boards for terminals and logs.
+//!
+//! [`BoardRenderer`] draws a [`Position`] or [`Game`] with Unicode glyphs or ASCII letters, ANSI
+//! colored squares, rank and file labels, from either side, and with the last move and a king
+//! in check highlighted. [`BoardRenderer::plain`] sticks to ASCII without escape codes, for logs
+//! and files.
+
+use std::fmt::Write;
+
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::{Position, piece_to_char};
+
+// Background and foreground colors as 24-bit RGB.
+const LIGHT_SQUARE: (u8, u8, u8) = (240, 217, 181);
+const DARK_SQUARE: (u8, u8, u8) = (181, 136, 99);
+const LAST_MOVE_LIGHT: (u8, u8, u8) = (205, 210, 106);
+const LAST_MOVE_DARK: (u8, u8, u8) = (170, 162, 58);
+const CHECK: (u8, u8, u8) = (220, 70, 70);
+const WHITE_PIECE: (u8, u8, u8) = (255, 255, 255);
+const BLACK_PIECE: (u8, u8, u8) = (0, 0, 0);
+
+const RESET: &str = "\x1b[0m";
+
+/// How pieces are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceStyle {
+    /// FEN letters: uppercase for White, lowercase for Black.
+    Letters,
+    /// Unicode chess glyphs.
+    Glyphs,
+}
+
+/// Draws boards as text.
+///
+/// Every square is three columns wide. Without colors, the squares of the last move are drawn as
+/// `[x]` and a king in check as `(K)`, and empty squares as dots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardRenderer {
+    pieces: PieceStyle,
+    colors: bool,
+    labels: bool,
+    orientation: Color,
+    last_move: bool,
+    check: bool,
+}
+
+impl Default for BoardRenderer {
+    fn default() -> Self {
+        BoardRenderer::new()
+    }
+}
+
+impl BoardRenderer {
+    /// A renderer for terminals: glyphs on ANSI colored squares, labels, White at the bottom and
+    /// both highlights on.
+    pub fn new() -> Self {
+        BoardRenderer {
+            pieces: PieceStyle::Glyphs,
+            colors: true,
+            labels: true,
+            orientation: Color::White,
+            last_move: true,
+            check: true,
+        }
+    }
+
+    /// A renderer for logs: ASCII letters with labels, no escape codes and no highlights.
+    pub fn plain() -> Self {
+        BoardRenderer {
+            pieces: PieceStyle::Letters,
+            colors: false,
+            labels: true,
+            orientation: Color::White,
+            last_move: false,
+            check: false,
+        }
+    }
+
+    /// Sets how pieces are drawn.
+    pub fn pieces(mut self, pieces: PieceStyle) -> Self {
+        self.pieces = pieces;
+        self
+    }
+
+    /// Sets whether squares are colored with ANSI escape codes.
+    pub fn colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Sets whether ranks and files are labelled.
+    pub fn labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Sets the side drawn at the bottom of the board.
+    pub fn orientation(mut self, orientation: Color) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Turns the board around, putting the other side at the bottom.
+    pub fn flipped(mut self) -> Self {
+        self.orientation = self.orientation.opponent();
+        self
+    }
+
+    /// Sets whether the squares of the last move are highlighted.
+    pub fn highlight_last_move(mut self, highlight: bool) -> Self {
+        self.last_move = highlight;
+        self
+    }
+
+    /// Sets whether a king in check is highlighted.
+    pub fn highlight_check(mut self, highlight: bool) -> Self {
+        self.check = highlight;
+        self
+    }
+
+    /// Draws the board of `game` with its last move.
+    pub fn render(&self, game: &Game) -> String {
+        self.render_position(&game.to_position(), game.last_move().as_ref())
+    }
+
+    /// Draws `position`, highlighting `last_move` if given. Lines end in a newline.
+    pub fn render_position(&self, position: &Position, last_move: Option<&ChessMove>) -> String {
+        let last_move = last_move.filter(|_| self.last_move);
+        let checked = checked_king(position).filter(|_| self.check);
+
+        let mut text = String::new();
+
+        for row in 0..8 {
+            let y = board_index(self.orientation, row);
+            if self.labels {
+                let _ = write!(text, "{} ", 8 - y);
+            }
+
+            for column in 0..8 {
+                let square = LocationCoords {
+                    x: board_index(self.orientation, column),
+                    y,
+                };
+                let moved = last_move.is_some_and(|m| m.from == square || m.to == square);
+                let in_check = checked == Some(square);
+                self.push_square(&mut text, position, square, moved, in_check);
+            }
+
+            if self.colors {
+                text.push_str(RESET);
+            }
+            text.push('\n');
+        }
+
+        if self.labels {
+            text.push_str("  ");
+            for column in 0..8 {
+                let file = (b'a' + board_index(self.orientation, column) as u8) as char;
+                let _ = write!(text, " {} ", file);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    fn push_square(
+        &self,
+        text: &mut String,
+        position: &Position,
+        square: LocationCoords,
+        moved: bool,
+        in_check: bool,
+    ) {
+        let piece = position.squares[square.y][square.x];
+
+        if !self.colors {
+            let symbol = match piece {
+                Some((color, piece_type)) => self.symbol(color, piece_type),
+                None if self.pieces == PieceStyle::Glyphs => '·',
+                None => '.',
+            };
+            let (open, close) = if in_check {
+                ('(', ')')
+            } else if moved {
+                ('[', ']')
+            } else {
+                (' ', ' ')
+            };
+            text.push(open);
+            text.push(symbol);
+            text.push(close);
+            return;
+        }
+
+        let light = (square.x + square.y).is_multiple_of(2);
+        let background = match (in_check, moved, light) {
+            (true, _, _) => CHECK,
+            (false, true, true) => LAST_MOVE_LIGHT,
+            (false, true, false) => LAST_MOVE_DARK,
+            (false, false, true) => LIGHT_SQUARE,
+            (false, false, false) => DARK_SQUARE,
+        };
+        push_color(text, 48, background);
+
+        match piece {
+            Some((color, piece_type)) => {
+                let foreground = match color {
+                    Color::White => WHITE_PIECE,
+                    Color::Black => BLACK_PIECE,
+                };
+                push_color(text, 38, foreground);
+                text.push(' ');
+                text.push(self.symbol(color, piece_type));
+                text.push(' ');
+            }
+            None => text.push_str("   "),
+        }
+    }
+
+    // Glyphs on colored squares are all solid, with the piece color coming from the foreground.
+    fn symbol(&self, color: Color, piece_type: PieceType) -> char {
+        match self.pieces {
+            PieceStyle::Letters => piece_to_char(color, piece_type),
+            PieceStyle::Glyphs if self.colors => glyph(Color::Black, piece_type),
+            PieceStyle::Glyphs => glyph(color, piece_type),
+        }
+    }
+}
+
+// Board row (0 = rank 8) shown at `index` from the top when the board is seen from
+// `orientation`'s side, which is also the file shown at `index` from the left.
+pub(crate) fn board_index(orientation: Color, index: usize) -> usize {
+    match orientation {
+        Color::White => index,
+        Color::Black => 7 - index,
+    }
+}
+
+// The square of the king in check, which can only be the side to move's.
+pub(crate) fn checked_king(position: &Position) -> Option<LocationCoords> {
+    let color = position.side_to_move;
+    position
+        .is_in_check(color)
+        .then(|| position.king_square(color))
+        .flatten()
+}
+
+// Unicode chess symbol, outlined for White and solid for Black.
+pub(crate) fn glyph(color: Color, piece_type: PieceType) -> char {
+    match (color, piece_type) {
+        (Color::White, PieceType::King) => '♔',
+        (Color::White, PieceType::Queen) => '♕',
+        (Color::White, PieceType::Rook) => '♖',
+        (Color::White, PieceType::Bishop) => '♗',
+        (Color::White, PieceType::Knight) => '♘',
+        (Color::White, PieceType::Pawn) => '♙',
+        (Color::Black, PieceType::King) => '♚',
+        (Color::Black, PieceType::Queen) => '♛',
+        (Color::Black, PieceType::Rook) => '♜',
+        (Color::Black, PieceType::Bishop) => '♝',
+        (Color::Black, PieceType::Knight) => '♞',
+        (Color::Black, PieceType::Pawn) => '♟',
+    }
+}
+
+// `layer` is 38 for the foreground and 48 for the background.
+fn push_color(text: &mut String, layer: u8, (r, g, b): (u8, u8, u8)) {
+    let _ = write!(text, "\x1b[{};2;{};{};{}m", layer, r, g, b);
+}
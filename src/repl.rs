@@ -0,0 +1,424 @@
+//! Interactive command-line play.
+//!
+//! [`Repl`] reads one line at a time: a move in coordinate form (`e2e4`, `e7e8q`) or SAN
+//! (`Nf3`, `O-O`, `exd8=Q+`), or one of the commands listed by `help`. Both players sit at the
+//! same keyboard. [`run`] drives it from a reader, which is all the `rs_chess` binary does.
+
+use std::io::{self, BufRead, Write};
+
+use crate::game::{DrawReason, Game, GameStatus};
+use crate::movegen::ChessMove;
+use crate::pgn::PgnGame;
+use crate::piece::PieceType;
+use crate::player::{Color, Player};
+use crate::position::{Position, STARTING_FEN};
+use crate::render::BoardRenderer;
+use crate::san::SanError;
+
+const HELP: &str = "\
+Moves are written in coordinates (e2e4, e7e8q) or SAN (Nf3, O-O, exd8=Q).
+
+Commands:
+  board         show the board
+  flip          turn the board around
+  moves         list the legal moves
+  undo          take back the last move
+  fen           show the position in FEN
+  pgn           show the game so far in PGN
+  resign        resign the game for the side to move
+  draw          offer a draw, accept one, or claim one by repetition or the 50-move rule
+  new [<fen>]   start a new game, from the standard position or a FEN
+  help          show this text
+  quit          leave";
+
+// How a game ended: the PGN result and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Outcome {
+    pub(crate) result: &'static str,
+    pub(crate) reason: String,
+}
+
+/// State of a game played at the command line.
+#[derive(Debug)]
+pub struct Repl<W: Write> {
+    out: W,
+    game: Game,
+    // The side that offered a draw on its last turn, until the other side answers.
+    draw_offer: Option<Color>,
+    outcome: Option<Outcome>,
+    renderer: BoardRenderer,
+    flipped: bool,
+}
+
+impl<W: Write> Repl<W> {
+    pub fn new(out: W) -> Self {
+        let start = Position::from_fen(STARTING_FEN).expect("the starting FEN is valid");
+
+        Repl {
+            out,
+            game: new_game(&start),
+            draw_offer: None,
+            outcome: None,
+            renderer: BoardRenderer::plain(),
+            flipped: false,
+        }
+    }
+
+    /// Sets how the board is drawn. Boards are plain ASCII until this is called.
+    pub fn set_renderer(&mut self, renderer: BoardRenderer) {
+        self.renderer = renderer;
+    }
+
+    /// The game being played.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Writes the prompt for the next line, naming the side to move.
+    pub fn prompt(&mut self) -> io::Result<()> {
+        let mover = color_name(self.game.current_player.color);
+        match &self.outcome {
+            Some(_) => write!(self.out, "game over> ")?,
+            None => write!(self.out, "{} to move> ", mover)?,
+        }
+        self.out.flush()
+    }
+
+    /// Handles one line of input. Returns `Ok(false)` once the player wants to leave.
+    pub fn handle(&mut self, line: &str) -> io::Result<bool> {
+        let mut tokens = line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return Ok(true);
+        };
+
+        match command {
+            "board" => self.show_board()?,
+            "flip" => {
+                self.flipped = !self.flipped;
+                self.show_board()?;
+            }
+            "moves" => self.show_moves()?,
+            "undo" => self.undo()?,
+            "fen" => writeln!(self.out, "{}", self.game.to_position().to_fen())?,
+            "pgn" => write!(self.out, "{}", self.pgn())?,
+            "resign" => self.resign()?,
+            "draw" => self.draw()?,
+            "new" => {
+                let fen = tokens.collect::<Vec<_>>().join(" ");
+                self.new_game(&fen)?;
+            }
+            "help" | "?" => writeln!(self.out, "{}", HELP)?,
+            "quit" | "exit" => return Ok(false),
+            _ => self.user_move(command)?,
+        }
+
+        Ok(true)
+    }
+
+    fn user_move(&mut self, text: &str) -> io::Result<()> {
+        if let Some(outcome) = &self.outcome {
+            return writeln!(
+                self.out,
+                "The game is over ({} {}). Type `new` to play again or `undo` to go back.",
+                outcome.result, outcome.reason
+            );
+        }
+
+        let position = self.game.to_position();
+        let chess_move = match parse_move(&position, text) {
+            Ok(chess_move) => chess_move,
+            Err(SanError::Invalid(_)) => {
+                return writeln!(
+                    self.out,
+                    "Unknown command or move: {}. Type `help` for the commands.",
+                    text
+                );
+            }
+            Err(error) => {
+                return writeln!(self.out, "{}. Type `moves` to list the legal moves.", error);
+            }
+        };
+
+        self.game.make_move(&chess_move);
+
+        // Moving instead of answering declines a draw offer.
+        if self
+            .draw_offer
+            .is_some_and(|color| color != position.side_to_move)
+        {
+            self.draw_offer = None;
+            writeln!(self.out, "Draw offer declined.")?;
+        }
+
+        writeln!(
+            self.out,
+            "{}{} {}",
+            position.fullmove_number,
+            if position.side_to_move == Color::White {
+                "."
+            } else {
+                "..."
+            },
+            self.game
+                .san_moves()
+                .last()
+                .expect("the move was just played")
+        )?;
+
+        self.outcome = automatic_outcome(&self.game);
+        self.show_board()?;
+        self.announce()
+    }
+
+    fn announce(&mut self) -> io::Result<()> {
+        if let Some(outcome) = &self.outcome {
+            writeln!(
+                self.out,
+                "Game over: {} ({})",
+                outcome.result, outcome.reason
+            )?;
+        }
+        Ok(())
+    }
+
+    fn show_board(&mut self) -> io::Result<()> {
+        let position = self.game.to_position();
+        let board = if self.flipped {
+            self.renderer.clone().flipped().render(&self.game)
+        } else {
+            self.renderer.render(&self.game)
+        };
+
+        write!(self.out, "{}", board)?;
+
+        if position.is_in_check(position.side_to_move) && self.outcome.is_none() {
+            writeln!(
+                self.out,
+                "{} is in check.",
+                color_name(position.side_to_move)
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn show_moves(&mut self) -> io::Result<()> {
+        let position = self.game.to_position();
+        let mut moves: Vec<String> = position
+            .legal_moves()
+            .iter()
+            .map(|m| position.san(m))
+            .collect();
+        moves.sort();
+
+        if self.outcome.is_some() || moves.is_empty() {
+            return writeln!(self.out, "No legal moves.");
+        }
+        writeln!(self.out, "{}", moves.join(" "))
+    }
+
+    fn undo(&mut self) -> io::Result<()> {
+        let Some(san) = self.game.san_moves().last().cloned() else {
+            return writeln!(self.out, "Nothing to undo.");
+        };
+
+        self.game.take_back();
+        self.outcome = None;
+        self.draw_offer = None;
+
+        writeln!(self.out, "Took back {}.", san)
+    }
+
+    fn resign(&mut self) -> io::Result<()> {
+        if self.outcome.is_some() {
+            return writeln!(self.out, "The game is already over.");
+        }
+
+        let loser = self.game.current_player.color;
+        self.outcome = Some(Outcome {
+            result: match loser {
+                Color::White => "0-1",
+                Color::Black => "1-0",
+            },
+            reason: format!("{} resigns", color_name(loser)),
+        });
+        self.announce()
+    }
+
+    // Accepts a standing offer from the opponent, claims a draw the rules allow, or offers one.
+    fn draw(&mut self) -> io::Result<()> {
+        if self.outcome.is_some() {
+            return writeln!(self.out, "The game is already over.");
+        }
+
+        let mover = self.game.current_player.color;
+        let reason = if self.draw_offer == Some(mover.opponent()) {
+            "draw agreed"
+        } else if self.game.repetitions() >= 3 {
+            "threefold repetition"
+        } else if self.game.halfmove_clock >= 100 {
+            "50-move rule"
+        } else {
+            self.draw_offer = Some(mover);
+            return writeln!(
+                self.out,
+                "{} offers a draw. {} may type `draw` to accept or move to decline.",
+                color_name(mover),
+                color_name(mover.opponent())
+            );
+        };
+
+        self.draw_offer = None;
+        self.outcome = Some(Outcome {
+            result: "1/2-1/2",
+            reason: reason.to_string(),
+        });
+        self.announce()
+    }
+
+    fn new_game(&mut self, fen: &str) -> io::Result<()> {
+        let position = match Position::from_fen(if fen.is_empty() { STARTING_FEN } else { fen }) {
+            Ok(position) => position,
+            Err(error) => return writeln!(self.out, "Cannot read the FEN: {}.", error),
+        };
+        if let Err(violations) = position.validate() {
+            return writeln!(self.out, "Illegal position: {}.", violations[0]);
+        }
+
+        self.game = new_game(&position);
+        self.draw_offer = None;
+        self.outcome = automatic_outcome(&self.game);
+
+        self.show_board()?;
+        self.announce()
+    }
+
+    fn pgn(&self) -> PgnGame {
+        let result = self.outcome.as_ref().map_or("*", |outcome| outcome.result);
+        let mut tags: Vec<(String, String)> = [
+            ("Event", "Casual game"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "-"),
+            ("White", "White"),
+            ("Black", "Black"),
+            ("Result", result),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+        let start_fen = self.game.start_position().to_fen();
+        if start_fen != STARTING_FEN {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start_fen));
+        }
+        if let Some(outcome) = &self.outcome {
+            tags.push(("Termination".to_string(), outcome.reason.clone()));
+        }
+
+        PgnGame {
+            tags,
+            moves: self.game.san_moves().to_vec(),
+            result: Some(result.to_string()),
+        }
+    }
+}
+
+/// Plays games read from `input` until `quit` or end of input, writing to `output` and drawing
+/// boards with `renderer`. Prompts are only written when `interactive` is set.
+pub fn run<R: BufRead, W: Write>(
+    input: R,
+    output: W,
+    interactive: bool,
+    renderer: BoardRenderer,
+) -> io::Result<()> {
+    let mut repl = Repl::new(output);
+    repl.set_renderer(renderer);
+
+    if interactive {
+        writeln!(repl.out, "Type `help` for the commands.")?;
+        repl.show_board()?;
+        repl.prompt()?;
+    }
+
+    for line in input.lines() {
+        if !repl.handle(&line?)? {
+            return Ok(());
+        }
+        if interactive {
+            repl.prompt()?;
+        }
+    }
+
+    Ok(())
+}
+
+// The end of the game the rules imposed without a claim, if they ended it.
+pub(crate) fn automatic_outcome(game: &Game) -> Option<Outcome> {
+    status_outcome(game.status())
+}
+
+// The result and reason for a game that stands at `status`, unless it is still going on.
+pub(crate) fn status_outcome(status: GameStatus) -> Option<Outcome> {
+    let (result, reason) = match status {
+        GameStatus::Ongoing => return None,
+        GameStatus::Checkmate {
+            winner: Color::White,
+        } => ("1-0", "White mates"),
+        GameStatus::Checkmate {
+            winner: Color::Black,
+        } => ("0-1", "Black mates"),
+        GameStatus::Draw(reason) => (
+            "1/2-1/2",
+            match reason {
+                DrawReason::Stalemate => "stalemate",
+                DrawReason::InsufficientMaterial => "insufficient material",
+                DrawReason::FivefoldRepetition => "fivefold repetition",
+                DrawReason::SeventyFiveMoveRule => "75-move rule",
+            },
+        ),
+    };
+
+    Some(Outcome {
+        result,
+        reason: reason.to_string(),
+    })
+}
+
+// Coordinates first, as a coordinate move like "b1c3" is not valid SAN. A coordinate move to
+// the last rank without a piece letter promotes to a queen.
+pub(crate) fn parse_move(position: &Position, text: &str) -> Result<ChessMove, SanError> {
+    if let Some(chess_move) = ChessMove::from_uci(text) {
+        let legal = position.legal_moves();
+        let queening = ChessMove {
+            promotion: Some(PieceType::Queen),
+            ..chess_move
+        };
+
+        return if legal.contains(&chess_move) {
+            Ok(chess_move)
+        } else if chess_move.promotion.is_none() && legal.contains(&queening) {
+            Ok(queening)
+        } else {
+            Err(SanError::Illegal(text.to_string()))
+        };
+    }
+
+    position.parse_san(text)
+}
+
+// A game between players named after their colors, from a validated position.
+pub(crate) fn new_game(position: &Position) -> Game {
+    let white = Player::with_rc("White", Color::White);
+    let black = Player::with_rc("Black", Color::Black);
+
+    Game::from_position(white, black, position).expect("positions are validated before use")
+}
+
+pub(crate) fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
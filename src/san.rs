@@ -0,0 +1,219 @@
+//! Standard algebraic notation (SAN), e.g. "Nf3", "exd5", "O-O" or "e8=Q+".
+
+use std::error::Error;
+use std::fmt;
+
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::position::Position;
+
+/// Reasons a SAN move could not be read in a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not SAN at all.
+    Invalid(String),
+    /// The move is well formed but no legal move matches it.
+    Illegal(String),
+    /// More than one legal move matches; the move needs a disambiguating file or rank.
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "{} is not a valid move", san),
+            SanError::Illegal(san) => write!(f, "{} is not legal here", san),
+            SanError::Ambiguous(san) => {
+                write!(
+                    f,
+                    "{} is ambiguous, add the file or rank it moves from",
+                    san
+                )
+            }
+        }
+    }
+}
+
+impl Error for SanError {}
+
+impl Position {
+    /// Finds the legal move `san` describes. Check and annotation suffixes are ignored, castling
+    /// may be written with letter O or digit 0, and the `=` before a promotion is optional.
+    pub fn parse_san(&self, san: &str) -> Result<ChessMove, SanError> {
+        let text = san.trim_end_matches(['+', '#', '!', '?']);
+        let invalid = || SanError::Invalid(san.to_string());
+
+        if matches!(text, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let king = self
+                .king_square(self.side_to_move)
+                .ok_or_else(|| SanError::Illegal(san.to_string()))?;
+            let to_x = if text.len() == 3 { 6 } else { 2 };
+            let castle = ChessMove::new(king, LocationCoords { x: to_x, y: king.y });
+
+            return self
+                .legal_moves()
+                .into_iter()
+                .find(|m| *m == castle && self.is_castling(m))
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+
+        let piece_type = match chars.first() {
+            Some('N') => PieceType::Knight,
+            Some('B') => PieceType::Bishop,
+            Some('R') => PieceType::Rook,
+            Some('Q') => PieceType::Queen,
+            Some('K') => PieceType::King,
+            Some('a'..='h') => PieceType::Pawn,
+            _ => return Err(invalid()),
+        };
+        if piece_type != PieceType::Pawn {
+            chars.remove(0);
+        }
+
+        let promotion = match chars.last() {
+            Some(c @ ('N' | 'B' | 'R' | 'Q' | 'n' | 'b' | 'r' | 'q')) => {
+                let promoted = match c.to_ascii_uppercase() {
+                    'N' => PieceType::Knight,
+                    'B' => PieceType::Bishop,
+                    'R' => PieceType::Rook,
+                    _ => PieceType::Queen,
+                };
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(promoted)
+            }
+            _ => None,
+        };
+
+        // What is left is [from file][from rank][x]<to square>.
+        if chars.len() < 2 {
+            return Err(invalid());
+        }
+        let to_text: String = chars[chars.len() - 2..].iter().collect();
+        let to = LocationCoords::from_algebraic(&to_text).ok_or_else(invalid)?;
+        chars.truncate(chars.len() - 2);
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+
+        let mut from_file: Option<usize> = None;
+        let mut from_y: Option<usize> = None;
+        for c in chars {
+            match c {
+                'a'..='h' if from_file.is_none() && from_y.is_none() => {
+                    from_file = Some(c as usize - 'a' as usize)
+                }
+                '1'..='8' if from_y.is_none() => from_y = Some(8 - (c as usize - '0' as usize)),
+                _ => return Err(invalid()),
+            }
+        }
+
+        let candidates: Vec<ChessMove> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|m| {
+                m.to == to
+                    && m.promotion == promotion
+                    && matches!(self.piece_at(&m.from), Some((_, p)) if p == piece_type)
+                    && from_file.is_none_or(|x| m.from.x == x)
+                    && from_y.is_none_or(|y| m.from.y == y)
+                    && !(piece_type == PieceType::King && self.is_castling(m))
+            })
+            .collect();
+
+        match candidates.as_slice() {
+            [chess_move] => Ok(*chess_move),
+            [] => Err(SanError::Illegal(san.to_string())),
+            _ => Err(SanError::Ambiguous(san.to_string())),
+        }
+    }
+
+    /// Writes a legal move in SAN, with the shortest disambiguation and a check or mate
+    /// suffix.
+    pub fn san(&self, chess_move: &ChessMove) -> String {
+        let Some((_, piece_type)) = self.piece_at(&chess_move.from) else {
+            return chess_move.to_string();
+        };
+
+        let mut san = String::new();
+
+        if self.is_castling(chess_move) {
+            san.push_str(if chess_move.to.x == 6 { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = self.is_capture(chess_move);
+
+            if piece_type == PieceType::Pawn {
+                if capture {
+                    san.push(file_char(chess_move.from.x));
+                }
+            } else {
+                san.push(piece_letter(piece_type));
+
+                let rivals: Vec<ChessMove> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|m| {
+                        m.to == chess_move.to
+                            && m.from != chess_move.from
+                            && self.piece_at(&m.from) == self.piece_at(&chess_move.from)
+                    })
+                    .collect();
+
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|m| m.from.x != chess_move.from.x) {
+                        san.push(file_char(chess_move.from.x));
+                    } else if rivals.iter().all(|m| m.from.y != chess_move.from.y) {
+                        san.push(rank_char(chess_move.from.y));
+                    } else {
+                        san.push(file_char(chess_move.from.x));
+                        san.push(rank_char(chess_move.from.y));
+                    }
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&chess_move.to.to_string());
+
+            if let Some(promotion) = chess_move.promotion {
+                san.push('=');
+                san.push(piece_letter(promotion));
+            }
+        }
+
+        let next = self.make_move(chess_move);
+        if next.is_checkmate() {
+            san.push('#');
+        } else if next.is_in_check(next.side_to_move) {
+            san.push('+');
+        }
+
+        san
+    }
+}
+
+fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+fn file_char(x: usize) -> char {
+    (b'a' + x as u8) as char
+}
+
+// `y = 0` is rank 8.
+fn rank_char(y: usize) -> char {
+    (b'8' - y as u8) as char
+}
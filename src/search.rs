@@ -0,0 +1,1024 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use crate::eval::evaluate_position;
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::{Position, piece_index};
+use crate::syzygy::{Tablebases, Wdl};
+use crate::time::{Clock, TimeManager};
+use crate::tt::{Bound, TranspositionTable};
+
+/// Score of delivering checkmate on the spot. Mates further away score one less per ply.
+pub const MATE_SCORE: i32 = 30_000;
+
+/// Deepest ply the search will ever reach.
+pub const MAX_PLY: u32 = 128;
+
+/// Score of a tablebase win, below every mate score. Wins further away score one less per ply.
+pub const TB_WIN_SCORE: i32 = MATE_SCORE - 2 * MAX_PLY as i32;
+
+const INFINITY: i32 = 32_000;
+
+// How many nodes to search between checks of the clock and the stop flag.
+const CHECK_INTERVAL: u64 = 1024;
+
+// Move ordering bands. Captures and promotions sort by MVV-LVA inside their band, quiet moves by
+// their history score below the killers.
+const HASH_MOVE_SCORE: i32 = 3_000_000;
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+const HISTORY_MAX: i32 = 500_000;
+
+/// Limits on how long a search may run. Unset limits are unbounded; the search stops as soon as
+/// any one limit is reached or the [`StopFlag`] is raised.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Deepest iteration to complete, in plies.
+    pub depth: Option<u32>,
+    /// Total nodes to visit.
+    pub nodes: Option<u64>,
+    /// Wall-clock time to think.
+    pub movetime: Option<Duration>,
+    /// Clock of the side to move. A [`TimeManager`] budgets the search from it.
+    pub clock: Option<Clock>,
+    /// Stop once a mate in this many moves or fewer is found.
+    pub mate: Option<u32>,
+    /// Only consider these moves at the root. Empty, or with no legal move among them, means
+    /// every legal move.
+    pub search_moves: Vec<ChessMove>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> Self {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> Self {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..Default::default()
+        }
+    }
+
+    pub fn clock(clock: Clock) -> Self {
+        SearchLimits {
+            clock: Some(clock),
+            ..Default::default()
+        }
+    }
+}
+
+/// Which move ordering heuristics the search uses. All are on by default; turning them off is
+/// mostly useful to measure what each one is worth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveOrdering {
+    /// Search the best move stored in the transposition table first.
+    pub hash_move: bool,
+    /// Order captures by most valuable victim, then least valuable attacker. The quiescence
+    /// search always does, as its capture trees explode without it.
+    pub mvv_lva: bool,
+    /// Try quiet moves that caused a cutoff at the same ply before other quiet moves.
+    pub killers: bool,
+    /// Order the remaining quiet moves by how often they caused cutoffs anywhere.
+    pub history: bool,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        MoveOrdering {
+            hash_move: true,
+            mvv_lva: true,
+            killers: true,
+            history: true,
+        }
+    }
+}
+
+impl MoveOrdering {
+    /// Searches moves in generation order, apart from the previous iteration's principal
+    /// variation.
+    pub fn none() -> Self {
+        MoveOrdering {
+            hash_move: false,
+            mvv_lva: false,
+            killers: false,
+            history: false,
+        }
+    }
+}
+
+/// Shared flag that stops a running search. Clones refer to the same flag, so one can be handed
+/// to another thread while the search runs.
+#[derive(Debug, Clone, Default)]
+pub struct StopFlag(Arc<AtomicBool>);
+
+impl StopFlag {
+    pub fn new() -> Self {
+        StopFlag::default()
+    }
+
+    /// Asks the search to stop. It returns the best move of the deepest completed iteration.
+    pub fn stop(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the flag so it can be used for another search.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Outcome of a search, or of one iteration of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<ChessMove>,
+    /// Centipawns from the side to move's point of view, or a mate score.
+    pub score: i32,
+    /// Depth of the deepest completed iteration.
+    pub depth: u32,
+    pub nodes: u64,
+    /// Principal variation, starting with `best_move`.
+    pub pv: Vec<ChessMove>,
+    pub elapsed: Duration,
+    /// Positions answered by the endgame tablebases.
+    pub tb_hits: u64,
+}
+
+impl SearchResult {
+    /// Moves until mate if the score is a mate score. Positive when the side to move mates,
+    /// negative when it gets mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+
+    /// Nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        let millis = self.elapsed.as_millis() as u64;
+        if millis == 0 {
+            return self.nodes * 1000;
+        }
+
+        self.nodes * 1000 / millis
+    }
+}
+
+/// Converts a mate score into moves until mate. See [`SearchResult::mate_in`].
+pub fn mate_in(score: i32) -> Option<i32> {
+    let bound = MATE_SCORE - MAX_PLY as i32;
+
+    if score > bound {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else if score < -bound {
+        Some(-(MATE_SCORE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// Negamax alpha-beta search with iterative deepening, a transposition table and a quiescence
+/// search over captures and promotions at the horizon.
+///
+/// With more than one thread the search is a Lazy SMP: helper threads search their own copy of
+/// the position from the same root, sharing only the transposition table, while this searcher
+/// keeps the clock and reports. Node counts cover every thread.
+#[derive(Debug)]
+pub struct Searcher {
+    limits: SearchLimits,
+    stop: StopFlag,
+    table: Arc<TranspositionTable>,
+    ordering: MoveOrdering,
+    killers: Vec<[Option<ChessMove>; 2]>,
+    // Indexed by mover, from square and to square.
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    time: Option<TimeManager>,
+    tablebases: Option<Arc<Tablebases>>,
+    // Result and best moves of the root position when the tablebases cover it.
+    tb_root: Option<(Wdl, Vec<ChessMove>)>,
+    // Hashes of the game's positions up to the root, as set by `set_game_hashes`.
+    game_hashes: Vec<u64>,
+    // Hashes of the positions played before the one being searched, from the game and then
+    // down the current line, for finding repetitions.
+    path: Vec<u64>,
+    nodes: u64,
+    tb_hits: u64,
+    start: Instant,
+    aborted: bool,
+    threads: usize,
+    // Helpers add their nodes here every CHECK_INTERVAL nodes, for live reports of the total.
+    helper_nodes: Arc<AtomicU64>,
+    helper: bool,
+    // Iteration to start from. Half the helpers skip the first one, so threads spread over
+    // depths.
+    first_depth: u32,
+}
+
+impl Searcher {
+    pub fn new(limits: SearchLimits) -> Self {
+        Searcher::with_stop_flag(limits, StopFlag::new())
+    }
+
+    /// Creates a searcher that also stops when `stop` is raised.
+    pub fn with_stop_flag(limits: SearchLimits, stop: StopFlag) -> Self {
+        Searcher::with_table(limits, stop, Arc::new(TranspositionTable::default()))
+    }
+
+    /// Creates a searcher that stores results in `table`, which outlives the search and can be
+    /// shared with other searchers.
+    pub fn with_table(
+        limits: SearchLimits,
+        stop: StopFlag,
+        table: Arc<TranspositionTable>,
+    ) -> Self {
+        Searcher {
+            limits,
+            stop,
+            table,
+            ordering: MoveOrdering::default(),
+            killers: vec![[None; 2]; MAX_PLY as usize],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            time: None,
+            tablebases: None,
+            tb_root: None,
+            game_hashes: vec![],
+            path: vec![],
+            nodes: 0,
+            tb_hits: 0,
+            start: Instant::now(),
+            aborted: false,
+            threads: 1,
+            helper_nodes: Arc::new(AtomicU64::new(0)),
+            helper: false,
+            first_depth: 1,
+        }
+    }
+
+    /// Handle that stops this searcher from another thread.
+    pub fn stop_flag(&self) -> StopFlag {
+        self.stop.clone()
+    }
+
+    /// The transposition table this searcher reads and writes.
+    pub fn table(&self) -> &Arc<TranspositionTable> {
+        &self.table
+    }
+
+    /// Chooses the move ordering heuristics used by the following searches.
+    pub fn set_ordering(&mut self, ordering: MoveOrdering) {
+        self.ordering = ordering;
+    }
+
+    /// Probes `tablebases` in the following searches. At the root only the moves that keep the
+    /// best tablebase result are searched; elsewhere positions right after a capture or pawn
+    /// move are scored from the tables instead of being searched.
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+
+    /// Sets the hashes of the positions the game went through, oldest first, ending with the one
+    /// searched, as [`Game::position_hashes`] gives them. The search scores a position that
+    /// repeats one of them, or one earlier in its own line, as a draw.
+    pub fn set_game_hashes(&mut self, hashes: Vec<u64>) {
+        self.game_hashes = hashes;
+    }
+
+    /// Sets how many threads the following searches use, this one included. At least one.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Searches for the best move for the side to move.
+    pub fn search(&mut self, position: &Position) -> SearchResult {
+        self.search_with(position, |_| {})
+    }
+
+    /// Searches for the best move for the side to move, calling `on_iteration` with the result
+    /// of every completed iteration.
+    ///
+    /// With several threads the answer comes from the deepest iteration any thread completed.
+    pub fn search_with<F>(&mut self, position: &Position, on_iteration: F) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        self.table.new_search();
+        self.helper_nodes.store(0, Ordering::Relaxed);
+
+        if self.threads == 1 {
+            return self.iterate(position, on_iteration);
+        }
+
+        let helpers_stop = StopFlag::new();
+        let deepest: Mutex<Option<SearchResult>> = Mutex::new(None);
+
+        let (mut result, helpers) = thread::scope(|scope| {
+            let handles: Vec<_> = (1..self.threads)
+                .map(|index| {
+                    let mut helper = self.helper(index, helpers_stop.clone());
+                    let deepest = &deepest;
+
+                    scope.spawn(move || {
+                        helper.iterate(position, |iteration| {
+                            let mut deepest = deepest.lock().expect("no thread panics holding it");
+                            if deepest.as_ref().is_none_or(|d| iteration.depth > d.depth) {
+                                *deepest = Some(iteration.clone());
+                            }
+                        });
+                        (helper.nodes, helper.tb_hits)
+                    })
+                })
+                .collect();
+
+            let result = self.iterate(position, on_iteration);
+            helpers_stop.stop();
+
+            let helpers: Vec<(u64, u64)> = handles
+                .into_iter()
+                .map(|handle| handle.join().expect("helper search panicked"))
+                .collect();
+            (result, helpers)
+        });
+
+        if let Some(deeper) = deepest.into_inner().expect("helpers are done")
+            && deeper.depth > result.depth
+        {
+            result.best_move = deeper.best_move;
+            result.score = deeper.score;
+            result.depth = deeper.depth;
+            result.pv = deeper.pv;
+        }
+        result.nodes = self.nodes + helpers.iter().map(|(nodes, _)| nodes).sum::<u64>();
+        result.tb_hits = self.tb_hits + helpers.iter().map(|(_, hits)| hits).sum::<u64>();
+
+        result
+    }
+
+    // A searcher for one helper thread. It runs until this searcher's iterations are done and
+    // `stop` is raised, so it only keeps the depth and root restrictions of the limits.
+    fn helper(&self, index: usize, stop: StopFlag) -> Searcher {
+        let limits = SearchLimits {
+            depth: self.limits.depth,
+            mate: self.limits.mate,
+            search_moves: self.limits.search_moves.clone(),
+            ..Default::default()
+        };
+
+        let mut helper = Searcher::with_table(limits, stop, self.table.clone());
+        helper.ordering = self.ordering;
+        helper.tablebases = self.tablebases.clone();
+        helper.game_hashes = self.game_hashes.clone();
+        helper.helper_nodes = self.helper_nodes.clone();
+        helper.helper = true;
+        helper.first_depth = 1 + index as u32 % 2;
+        helper
+    }
+
+    // Iterative deepening on this thread.
+    fn iterate<F>(&mut self, position: &Position, mut on_iteration: F) -> SearchResult
+    where
+        F: FnMut(&SearchResult),
+    {
+        self.nodes = 0;
+        self.tb_hits = 0;
+        self.start = Instant::now();
+        self.aborted = false;
+        // Clear the previous root first, as probing looks at the root moves.
+        self.tb_root = None;
+        self.tb_root = self.probe_root(position);
+        self.time = self
+            .limits
+            .clock
+            .map(|clock| TimeManager::new(&clock, self.root_moves(position).len()));
+        self.killers.fill([None; 2]);
+        self.history
+            .iter_mut()
+            .flatten()
+            .for_each(|row| row.fill(0));
+
+        // A mate in n moves takes 2n - 1 plies to see.
+        let mate_depth = self.limits.mate.map(|moves| moves * 2);
+        let max_depth = [self.limits.depth, mate_depth]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(MAX_PLY)
+            .clamp(1, MAX_PLY);
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            pv: vec![],
+            elapsed: Duration::ZERO,
+            tb_hits: 0,
+        };
+
+        for depth in self.first_depth.min(max_depth)..=max_depth {
+            self.path.clone_from(&self.game_hashes);
+            if self.path.last() == Some(&position.hash()) {
+                self.path.pop();
+            }
+
+            let mut pv: Vec<ChessMove> = vec![];
+            let score = self.negamax(position, depth, 0, -INFINITY, INFINITY, &mut pv, &result.pv);
+
+            // An interrupted iteration is only trusted when nothing better is available.
+            if self.aborted {
+                if result.best_move.is_none() && !pv.is_empty() {
+                    result.best_move = pv.first().copied();
+                    result.pv = pv;
+                }
+                break;
+            }
+
+            result.best_move = pv.first().copied();
+            result.score = score;
+            result.depth = depth;
+            result.pv = self.extend_pv(position, pv, depth);
+            result.nodes = self.total_nodes();
+            result.tb_hits = self.tb_hits;
+            result.elapsed = self.start.elapsed();
+
+            // The tables know the outcome better than the search, short of a mate it can see.
+            if let Some((wdl, _)) = &self.tb_root
+                && result.mate_in().is_none()
+            {
+                result.score = tb_score(*wdl, 0);
+            }
+
+            debug!(
+                depth,
+                score,
+                nodes = self.nodes,
+                helper = self.helper,
+                best_move = ?result.best_move.map(|m| m.to_string()),
+                "iteration complete"
+            );
+            on_iteration(&result);
+
+            if let Some(time) = &mut self.time
+                && !time.iteration_complete(result.best_move)
+            {
+                break;
+            }
+
+            // A mate found within the searched depth cannot be improved on by going deeper.
+            if let Some(moves) = result.mate_in()
+                && (moves.unsigned_abs() * 2) <= depth
+            {
+                break;
+            }
+
+            if let (Some(moves), Some(limit)) = (result.mate_in(), self.limits.mate)
+                && moves > 0
+                && moves.unsigned_abs() <= limit
+            {
+                break;
+            }
+        }
+
+        // Always answer with a legal move when there is one, even if stopped immediately.
+        if result.best_move.is_none() {
+            result.best_move = self.root_moves(position).first().copied();
+        }
+
+        result.nodes = self.nodes;
+        result.tb_hits = self.tb_hits;
+        result.elapsed = self.start.elapsed();
+
+        result
+    }
+
+    // The root moves that keep the best tablebase result, with that result, if the position
+    // is in the tables.
+    fn probe_root(&self, position: &Position) -> Option<(Wdl, Vec<ChessMove>)> {
+        let tablebases = self.tablebases.as_ref()?;
+        let allowed = self.root_moves(position);
+        let moves: Vec<_> = match tablebases.root_moves(position) {
+            Ok(moves) => moves
+                .into_iter()
+                .filter(|m| allowed.contains(&m.chess_move))
+                .collect(),
+            Err(error) => {
+                debug!(%error, "root not in tablebases");
+                return None;
+            }
+        };
+
+        let best = moves.iter().map(|m| m.wdl).max()?;
+        let keep = moves
+            .iter()
+            .filter(|m| m.wdl == best)
+            .map(|m| m.chess_move)
+            .collect();
+
+        Some((best, keep))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        position: &Position,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        mut beta: i32,
+        pv: &mut Vec<ChessMove>,
+        pv_hint: &[ChessMove],
+    ) -> i32 {
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta);
+        }
+
+        self.nodes += 1;
+
+        if self.should_stop() {
+            self.aborted = true;
+            return 0;
+        }
+
+        let mover = position.side_to_move;
+
+        if ply > 0 && position.halfmove_clock >= 100 {
+            return 0;
+        }
+
+        if ply >= MAX_PLY {
+            return evaluate_position(position);
+        }
+
+        // Mate distance pruning: no line from here can beat a mate already found closer to the
+        // root.
+        alpha = alpha.max(-MATE_SCORE + ply as i32);
+        beta = beta.min(MATE_SCORE - ply as i32 - 1);
+        if alpha >= beta {
+            return alpha;
+        }
+
+        let key = position.hash();
+        if ply > 0 && self.is_repetition(position, key) {
+            return 0;
+        }
+
+        let entry = self.table.probe(key);
+
+        // The root always searches, so it always has a best move and a principal variation.
+        if ply > 0
+            && let Some(entry) = entry
+            && entry.depth >= depth
+        {
+            let score = score_from_table(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+
+            if cutoff {
+                return score;
+            }
+        }
+
+        // Right after a capture or pawn move the tables are exact. Once the root is in the
+        // tables, its move list already settles the result.
+        if ply > 0
+            && self.tb_root.is_none()
+            && position.halfmove_clock == 0
+            && let Some(tablebases) = &self.tablebases
+            && let Ok(wdl) = tablebases.probe_wdl(position)
+        {
+            self.tb_hits += 1;
+            let score = tb_score(wdl, ply);
+            self.table
+                .store(key, depth, Bound::Exact, score_to_table(score, ply), None);
+            return score;
+        }
+
+        let mut moves = if ply == 0 {
+            self.root_moves(position)
+        } else {
+            position.legal_moves()
+        };
+
+        if moves.is_empty() {
+            if position.is_in_check(mover) {
+                return -MATE_SCORE + ply as i32;
+            }
+            return 0;
+        }
+
+        // Search the previous iteration's principal variation first, then the ordering
+        // heuristics decide.
+        let hash_move = entry.and_then(|e| e.best_move);
+        self.order_moves(position, &mut moves, hash_move, ply);
+
+        let mut follow_hint = false;
+        if let Some(hint) = pv_hint.first()
+            && let Some(index) = moves.iter().position(|m| m == hint)
+        {
+            let hint = moves.remove(index);
+            moves.insert(0, hint);
+            follow_hint = true;
+        }
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move: Option<ChessMove> = None;
+
+        self.path.push(key);
+        for (index, chess_move) in moves.iter().enumerate() {
+            let child = position.make_move(chess_move);
+            let mut child_pv: Vec<ChessMove> = vec![];
+            let child_hint = if index == 0 && follow_hint {
+                &pv_hint[1..]
+            } else {
+                &[]
+            };
+
+            let score = -self.negamax(
+                &child,
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                &mut child_pv,
+                child_hint,
+            );
+
+            if self.aborted {
+                self.path.pop();
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(*chess_move);
+            }
+
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(*chess_move);
+                pv.extend(child_pv);
+            }
+
+            if alpha >= beta {
+                if !position.is_capture(chess_move) && chess_move.promotion.is_none() {
+                    self.record_cutoff(mover, chess_move, depth, ply);
+                }
+                break;
+            }
+        }
+        self.path.pop();
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table
+            .store(key, depth, bound, score_to_table(best, ply), best_move);
+
+        best
+    }
+
+    // Table cutoffs cut the principal variation short. Continue it with the stored best moves,
+    // as long as they are legal, up to the searched depth.
+    fn extend_pv(&self, root: &Position, mut pv: Vec<ChessMove>, depth: u32) -> Vec<ChessMove> {
+        let mut position = root.clone();
+        for chess_move in &pv {
+            position = position.make_move(chess_move);
+        }
+
+        while pv.len() < depth as usize {
+            let Some(chess_move) = self.table.probe(position.hash()).and_then(|e| e.best_move)
+            else {
+                break;
+            };
+            if !position.is_legal(&chess_move) {
+                break;
+            }
+
+            position = position.make_move(&chess_move);
+            pv.push(chess_move);
+        }
+
+        pv
+    }
+
+    // Legal moves at the root, restricted to `limits.search_moves` when any of those is legal,
+    // and to the moves keeping the tablebase result when the root is in the tables.
+    fn root_moves(&self, position: &Position) -> Vec<ChessMove> {
+        if let Some((_, moves)) = &self.tb_root {
+            return moves.clone();
+        }
+
+        let moves = position.legal_moves();
+        let restricted: Vec<ChessMove> = moves
+            .iter()
+            .filter(|m| self.limits.search_moves.contains(m))
+            .copied()
+            .collect();
+
+        if restricted.is_empty() {
+            moves
+        } else {
+            restricted
+        }
+    }
+
+    // Resolves captures and promotions until the position is quiet, so the static evaluation is
+    // never taken in the middle of an exchange. In check every evasion is searched instead.
+    fn quiescence(&mut self, position: &Position, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+
+        if self.should_stop() {
+            self.aborted = true;
+            return 0;
+        }
+
+        if position.halfmove_clock >= 100 {
+            return 0;
+        }
+
+        // No repetition is possible within three plies of a capture or pawn move, which spares
+        // hashing most positions here.
+        let key = (position.halfmove_clock >= 4).then(|| position.hash());
+        if let Some(key) = key
+            && self.is_repetition(position, key)
+        {
+            return 0;
+        }
+
+        if ply >= MAX_PLY {
+            return evaluate_position(position);
+        }
+
+        let in_check = position.is_in_check(position.side_to_move);
+        let mut best = -INFINITY;
+
+        if !in_check {
+            // Standing pat: the side to move need not capture at all.
+            best = evaluate_position(position);
+            if best >= beta {
+                return best;
+            }
+            alpha = alpha.max(best);
+        }
+
+        // Out of check only captures and promotions are tried, so legality is checked per move
+        // instead of generating every legal move.
+        let mut moves = if in_check {
+            let moves = position.legal_moves();
+            if moves.is_empty() {
+                return -MATE_SCORE + ply as i32;
+            }
+            moves
+        } else {
+            let mut moves = position.pseudo_legal_moves();
+            moves.retain(|m| position.is_capture(m) || m.promotion.is_some());
+            moves
+        };
+        moves.sort_by_cached_key(|m| -mvv_lva(position, m));
+
+        // Captures and promotions reset the halfmove clock, so only after evasions can a later
+        // position look back this far.
+        let hashed = in_check.then(|| key.unwrap_or_else(|| position.hash()));
+        if let Some(key) = hashed {
+            self.path.push(key);
+        }
+
+        for chess_move in &moves {
+            let child = position.make_move(chess_move);
+            if child.is_in_check(position.side_to_move) {
+                continue;
+            }
+
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+
+            if self.aborted {
+                break;
+            }
+
+            if score > best {
+                best = score;
+            }
+
+            if score > alpha {
+                alpha = score;
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if hashed.is_some() {
+            self.path.pop();
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        best
+    }
+
+    // Whether `position`, hashed to `key`, occurred before since the last capture or pawn move,
+    // in the game or earlier in the line searched.
+    fn is_repetition(&self, position: &Position, key: u64) -> bool {
+        self.path
+            .iter()
+            .rev()
+            .take(position.halfmove_clock as usize)
+            .any(|&hash| hash == key)
+    }
+
+    // Sorts `moves` best first: the hash move, captures and promotions, killers, then quiet
+    // moves by history. Heuristics switched off in `self.ordering` leave moves in their band
+    // unsorted.
+    fn order_moves(
+        &self,
+        position: &Position,
+        moves: &mut [ChessMove],
+        hash_move: Option<ChessMove>,
+        ply: u32,
+    ) {
+        let mover = position.side_to_move;
+        let killers = self.killers.get(ply as usize).copied().unwrap_or([None; 2]);
+
+        moves.sort_by_cached_key(|chess_move| {
+            let score = if self.ordering.hash_move && hash_move == Some(*chess_move) {
+                HASH_MOVE_SCORE
+            } else if position.is_capture(chess_move) || chess_move.promotion.is_some() {
+                if self.ordering.mvv_lva {
+                    CAPTURE_SCORE + mvv_lva(position, chess_move)
+                } else {
+                    CAPTURE_SCORE
+                }
+            } else if self.ordering.killers && killers[0] == Some(*chess_move) {
+                KILLER_SCORE + 1
+            } else if self.ordering.killers && killers[1] == Some(*chess_move) {
+                KILLER_SCORE
+            } else if self.ordering.history {
+                self.history[mover.index()][square_index(chess_move.from)]
+                    [square_index(chess_move.to)]
+            } else {
+                0
+            };
+
+            -score
+        });
+    }
+
+    // A quiet move refuted the opponent's last move: remember it as a killer for this ply and
+    // credit its history.
+    fn record_cutoff(&mut self, mover: Color, chess_move: &ChessMove, depth: u32, ply: u32) {
+        if let Some(killers) = self.killers.get_mut(ply as usize)
+            && killers[0] != Some(*chess_move)
+        {
+            killers[1] = killers[0];
+            killers[0] = Some(*chess_move);
+        }
+
+        let history = &mut self.history[mover.index()];
+        let entry = &mut history[square_index(chess_move.from)][square_index(chess_move.to)];
+        *entry += (depth * depth) as i32;
+
+        // Keep history scores below the killer band by halving them all once one grows too big.
+        if *entry > HISTORY_MAX {
+            history.iter_mut().flatten().for_each(|score| *score /= 2);
+        }
+    }
+
+    // Nodes searched so far by this searcher and its helpers.
+    fn total_nodes(&self) -> u64 {
+        if self.helper {
+            return self.nodes;
+        }
+
+        self.nodes + self.helper_nodes.load(Ordering::Relaxed)
+    }
+
+    fn should_stop(&self) -> bool {
+        if let Some(max_nodes) = self.limits.nodes
+            && self.total_nodes() >= max_nodes
+        {
+            return true;
+        }
+
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return false;
+        }
+
+        if self.helper {
+            self.helper_nodes
+                .fetch_add(CHECK_INTERVAL, Ordering::Relaxed);
+        }
+
+        if self.stop.is_stopped() {
+            return true;
+        }
+
+        if let Some(time) = &self.time
+            && time.out_of_time()
+        {
+            return true;
+        }
+
+        match self.limits.movetime {
+            Some(movetime) => self.start.elapsed() >= movetime,
+            None => false,
+        }
+    }
+}
+
+// Most valuable victim first, least valuable attacker second. Promotions count the promoted
+// piece as a victim so queening sorts with the best captures.
+fn mvv_lva(position: &Position, chess_move: &ChessMove) -> i32 {
+    let victim = match position.piece_at(&chess_move.to) {
+        Some((_, piece_type)) => piece_index(piece_type) as i32,
+        None if position.is_en_passant(chess_move) => piece_index(PieceType::Pawn) as i32,
+        None => -1,
+    };
+    let promotion = chess_move
+        .promotion
+        .map_or(0, |piece_type| piece_index(piece_type) as i32);
+    let attacker = position
+        .piece_at(&chess_move.from)
+        .map_or(0, |(_, piece_type)| piece_index(piece_type) as i32);
+
+    (victim + 1) * 64 + promotion * 8 - attacker
+}
+
+fn square_index(square: LocationCoords) -> usize {
+    square.y * 8 + square.x
+}
+
+// Search score of a tablebase result `ply` plies from the root. Wins and losses the 50-move
+// rule spoils are barely different from a draw.
+fn tb_score(wdl: Wdl, ply: u32) -> i32 {
+    match wdl {
+        Wdl::Win => TB_WIN_SCORE - ply as i32,
+        Wdl::CursedWin => 1,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -1,
+        Wdl::Loss => -TB_WIN_SCORE + ply as i32,
+    }
+}
+
+// Mate and tablebase scores count plies from the root, but a table entry can be reached at any
+// ply, so they are stored relative to the node itself.
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    let bound = TB_WIN_SCORE - MAX_PLY as i32;
+
+    if score > bound {
+        score + ply as i32
+    } else if score < -bound {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    let bound = TB_WIN_SCORE - MAX_PLY as i32;
+
+    if score > bound {
+        score - ply as i32
+    } else if score < -bound {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+impl Game {
+    /// Searches for the best move for the current player.
+    pub fn best_move(&self, limits: SearchLimits) -> SearchResult {
+        let mut searcher = Searcher::new(limits);
+        searcher.set_game_hashes(self.position_hashes().to_vec());
+        searcher.search(&self.to_position())
+    }
+}
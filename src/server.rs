@@ -0,0 +1,563 @@
+//! HTTP/JSON API hosting many games at once.
+//!
+//! [`GameServer`] keeps every game in memory, addressed by its [`Game::id`]. [`serve`] answers
+//! these requests on one thread:
+//!
+//! * `POST /games` with `{"white": "Ann", "black": "Bob"}` and an optional `"fen"` creates a
+//!   game and answers `201` with its [`GameState`].
+//! * `GET /games` lists [`GameSummary`]s in the order the games were created.
+//! * `GET /games/{id}` answers the [`GameState`].
+//! * `POST /games/{id}/moves` with `{"move": "e2e4"}` plays a move in coordinates or SAN and
+//!   answers the new [`GameState`].
+//!
+//! Failures answer `{"error": kind, "message": text}` with the status and kind of the
+//! [`ApiError`]. Every response allows any origin, so a front-end served from elsewhere on the
+//! machine can call the API.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::game::Game;
+use crate::movegen::ChessMove;
+use crate::player::{Color, Player};
+use crate::position::{Position, STARTING_FEN, piece_to_char};
+use crate::repl::{Outcome, automatic_outcome, parse_move};
+use crate::san::SanError;
+
+/// Body of `POST /games`. Missing names default to "White" and "Black".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewGame {
+    #[serde(default)]
+    pub white: String,
+    #[serde(default)]
+    pub black: String,
+    /// Starting position, the standard one if missing.
+    #[serde(default)]
+    pub fen: Option<String>,
+}
+
+/// Body of `POST /games/{id}/moves`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveRequest {
+    /// Coordinates such as `e7e8q` or SAN such as `Nf3`.
+    #[serde(rename = "move")]
+    pub notation: String,
+}
+
+/// Where a game stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+    InProgress,
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+/// Everything a client needs to show a game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameState {
+    pub id: Uuid,
+    pub white: String,
+    pub black: String,
+    pub fen: String,
+    /// Ranks 8 to 1, each from the a to the h file, holding FEN letters or null when empty.
+    pub board: Vec<Vec<Option<char>>>,
+    /// "white" or "black".
+    pub turn: String,
+    pub check: bool,
+    /// Legal moves in coordinates, none once the game is over.
+    pub legal_moves: Vec<String>,
+    /// Moves played so far in SAN.
+    pub moves: Vec<String>,
+    pub status: GameStatus,
+    /// Why the game ended, e.g. "stalemate".
+    pub reason: Option<String>,
+}
+
+/// One entry of `GET /games`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub id: Uuid,
+    pub white: String,
+    pub black: String,
+    pub turn: String,
+    pub moves: usize,
+    pub status: GameStatus,
+}
+
+/// Reasons a request failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// The body is not the JSON the route expects.
+    BadRequest(String),
+    /// No route matches the method and path.
+    NotFound(String),
+    /// No game has this id.
+    UnknownGame(Uuid),
+    /// The `fen` of a new game is not a legal position.
+    InvalidFen(String),
+    /// The move is not written in coordinates or SAN.
+    InvalidMove(String),
+    /// The move is well formed but not legal in the position.
+    IllegalMove(String),
+    /// The SAN move matches more than one legal move.
+    AmbiguousMove(String),
+    /// The game has already ended.
+    GameOver(Uuid),
+}
+
+impl ApiError {
+    /// HTTP status code of the response.
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::BadRequest(_) => 400,
+            ApiError::NotFound(_) | ApiError::UnknownGame(_) => 404,
+            ApiError::GameOver(_) => 409,
+            ApiError::InvalidFen(_)
+            | ApiError::InvalidMove(_)
+            | ApiError::IllegalMove(_)
+            | ApiError::AmbiguousMove(_) => 422,
+        }
+    }
+
+    /// Machine-readable name of the error, sent as `error` in the response.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::UnknownGame(_) => "unknown_game",
+            ApiError::InvalidFen(_) => "invalid_fen",
+            ApiError::InvalidMove(_) => "invalid_move",
+            ApiError::IllegalMove(_) => "illegal_move",
+            ApiError::AmbiguousMove(_) => "ambiguous_move",
+            ApiError::GameOver(_) => "game_over",
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(message) => write!(f, "bad request: {}", message),
+            ApiError::NotFound(route) => write!(f, "no route for {}", route),
+            ApiError::UnknownGame(id) => write!(f, "no game with id {}", id),
+            ApiError::InvalidFen(message) => write!(f, "invalid FEN: {}", message),
+            ApiError::InvalidMove(text) => write!(f, "{} is not a valid move", text),
+            ApiError::IllegalMove(text) => write!(f, "{} is not legal here", text),
+            ApiError::AmbiguousMove(text) => {
+                write!(
+                    f,
+                    "{} is ambiguous, add the file or rank it moves from",
+                    text
+                )
+            }
+            ApiError::GameOver(id) => write!(f, "game {} is over", id),
+        }
+    }
+}
+
+impl Error for ApiError {}
+
+impl From<SanError> for ApiError {
+    fn from(error: SanError) -> Self {
+        match error {
+            SanError::Invalid(text) => ApiError::InvalidMove(text),
+            SanError::Illegal(text) => ApiError::IllegalMove(text),
+            SanError::Ambiguous(text) => ApiError::AmbiguousMove(text),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: String,
+}
+
+// A game with the state the API reports that `Game` does not keep.
+#[derive(Debug)]
+pub(crate) struct HostedGame {
+    pub(crate) game: Game,
+    pub(crate) outcome: Option<Outcome>,
+}
+
+impl HostedGame {
+    pub(crate) fn new(request: NewGame) -> Result<Self, ApiError> {
+        let fen = request.fen.as_deref().unwrap_or(STARTING_FEN);
+        let position =
+            Position::from_fen(fen).map_err(|error| ApiError::InvalidFen(error.to_string()))?;
+        if let Err(violations) = position.validate() {
+            return Err(ApiError::InvalidFen(violations[0].to_string()));
+        }
+
+        let white = Player::with_rc(&player_name(&request.white, Color::White), Color::White);
+        let black = Player::with_rc(&player_name(&request.black, Color::Black), Color::Black);
+        let game = Game::from_position(white, black, &position)
+            .map_err(|violations| ApiError::InvalidFen(violations[0].to_string()))?;
+
+        Ok(HostedGame {
+            outcome: automatic_outcome(&game),
+            game,
+        })
+    }
+
+    // Plays a move in coordinates or SAN for the side to move.
+    pub(crate) fn play(&mut self, notation: &str) -> Result<ChessMove, ApiError> {
+        if self.outcome.is_some() {
+            return Err(ApiError::GameOver(self.game.id));
+        }
+
+        let chess_move = parse_move(&self.game.to_position(), notation.trim())?;
+
+        self.game.make_move(&chess_move);
+        self.outcome = automatic_outcome(&self.game);
+
+        Ok(chess_move)
+    }
+
+    // Ends the game with a PGN result, for resignations and other ends the board cannot show.
+    #[cfg(feature = "websocket")]
+    pub(crate) fn end(&mut self, result: &'static str, reason: &str) {
+        self.outcome = Some(Outcome {
+            result,
+            reason: reason.to_string(),
+        });
+    }
+
+    pub(crate) fn state(&self) -> GameState {
+        let position = self.game.to_position();
+        let over = self.outcome.is_some();
+
+        GameState {
+            id: self.game.id,
+            white: self.game.player1.name.clone(),
+            black: self.game.player2.name.clone(),
+            fen: position.to_fen(),
+            board: position
+                .squares
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|square| square.map(|(color, piece)| piece_to_char(color, piece)))
+                        .collect()
+                })
+                .collect(),
+            turn: color_name(position.side_to_move).to_string(),
+            check: position.is_in_check(position.side_to_move),
+            legal_moves: if over {
+                vec![]
+            } else {
+                position
+                    .legal_moves()
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect()
+            },
+            moves: self.game.san_moves().to_vec(),
+            status: self.status(),
+            reason: self.outcome.as_ref().map(|outcome| outcome.reason.clone()),
+        }
+    }
+
+    pub(crate) fn summary(&self) -> GameSummary {
+        GameSummary {
+            id: self.game.id,
+            white: self.game.player1.name.clone(),
+            black: self.game.player2.name.clone(),
+            turn: color_name(self.game.current_player.color).to_string(),
+            moves: self.game.moves().len(),
+            status: self.status(),
+        }
+    }
+
+    fn status(&self) -> GameStatus {
+        match self.outcome.as_ref().map(|outcome| outcome.result) {
+            None => GameStatus::InProgress,
+            Some("1-0") => GameStatus::WhiteWins,
+            Some("0-1") => GameStatus::BlackWins,
+            Some(_) => GameStatus::Draw,
+        }
+    }
+}
+
+/// Games hosted by the API. Requests can be answered without a socket through [`handle`].
+///
+/// [`handle`]: GameServer::handle
+#[derive(Debug, Default)]
+pub struct GameServer {
+    games: HashMap<Uuid, HostedGame>,
+    // Ids in the order the games were created, for listing.
+    order: Vec<Uuid>,
+}
+
+impl GameServer {
+    pub fn new() -> Self {
+        GameServer::default()
+    }
+
+    /// Starts a game and returns its state.
+    pub fn create_game(&mut self, request: NewGame) -> Result<GameState, ApiError> {
+        let hosted = HostedGame::new(request)?;
+        let id = hosted.game.id;
+        let state = hosted.state();
+
+        info!(%id, white = %state.white, black = %state.black, "game created");
+        self.games.insert(id, hosted);
+        self.order.push(id);
+        Ok(state)
+    }
+
+    /// The state of a game.
+    pub fn game_state(&self, id: Uuid) -> Result<GameState, ApiError> {
+        self.games
+            .get(&id)
+            .map(HostedGame::state)
+            .ok_or(ApiError::UnknownGame(id))
+    }
+
+    /// Plays a move in coordinates or SAN for the side to move and returns the new state.
+    pub fn play_move(&mut self, id: Uuid, notation: &str) -> Result<GameState, ApiError> {
+        let hosted = self.games.get_mut(&id).ok_or(ApiError::UnknownGame(id))?;
+        let chess_move = hosted.play(notation)?;
+
+        info!(%id, chess_move = %chess_move, "move played");
+        Ok(hosted.state())
+    }
+
+    /// Every game, oldest first.
+    pub fn list_games(&self) -> Vec<GameSummary> {
+        self.order
+            .iter()
+            .filter_map(|id| self.games.get(id))
+            .map(HostedGame::summary)
+            .collect()
+    }
+
+    /// Answers one request with its status code and JSON body. `path` may carry a query string,
+    /// which is ignored.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> (u16, String) {
+        match self.route(method, path, body) {
+            Ok(answer) => answer,
+            Err(error) => (error.status(), error_json(&error)),
+        }
+    }
+
+    fn route(&mut self, method: &str, path: &str, body: &str) -> Result<(u16, String), ApiError> {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let not_found = || ApiError::NotFound(format!("{} {}", method, path));
+
+        match (method, segments.as_slice()) {
+            ("GET", ["games"]) => Ok((200, to_json(&self.list_games()))),
+            ("POST", ["games"]) => {
+                // An empty body starts a default game.
+                let request = match body.trim() {
+                    "" => NewGame::default(),
+                    body => parse_body(body)?,
+                };
+                Ok((201, to_json(&self.create_game(request)?)))
+            }
+            ("GET", ["games", id]) => {
+                let id = parse_id(id).ok_or_else(not_found)?;
+                Ok((200, to_json(&self.game_state(id)?)))
+            }
+            ("POST", ["games", id, "moves"]) => {
+                let id = parse_id(id).ok_or_else(not_found)?;
+                let request: MoveRequest = parse_body(body)?;
+                Ok((200, to_json(&self.play_move(id, &request.notation)?)))
+            }
+            _ => Err(not_found()),
+        }
+    }
+}
+
+/// Serves the API on `address`, e.g. `127.0.0.1:8080`, until the process ends.
+pub fn serve(address: &str) -> io::Result<()> {
+    let server = Server::http(address).map_err(io::Error::other)?;
+    let mut games = GameServer::new();
+    info!(address, "serving games");
+
+    for request in server.incoming_requests() {
+        if let Err(error) = answer(&mut games, request) {
+            warn!(%error, "could not answer a request");
+        }
+    }
+
+    Ok(())
+}
+
+fn answer(games: &mut GameServer, mut request: Request) -> io::Result<()> {
+    // Browsers check cross-origin POSTs with a preflight request first.
+    if *request.method() == Method::Options {
+        let response = Response::empty(204)
+            .with_header(header("Access-Control-Allow-Origin", "*"))
+            .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "Content-Type"));
+        return request.respond(response);
+    }
+
+    let mut body = String::new();
+    let (status, json) = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => {
+            let method = request.method().to_string();
+            games.handle(&method, request.url(), &body)
+        }
+        Err(error) => {
+            let error = ApiError::BadRequest(error.to_string());
+            (error.status(), error_json(&error))
+        }
+    };
+
+    let response = Response::from_string(json)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+        .with_header(header("Access-Control-Allow-Origin", "*"));
+    request.respond(response)
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("header names and values are ASCII")
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|error| ApiError::BadRequest(error.to_string()))
+}
+
+fn parse_id(text: &str) -> Option<Uuid> {
+    Uuid::parse_str(text).ok()
+}
+
+fn error_json(error: &ApiError) -> String {
+    to_json(&ErrorBody {
+        error: error.kind(),
+        message: error.to_string(),
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("API types serialize to JSON")
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+// A player's name as given, or the name of their color when it is blank.
+pub(crate) fn player_name(name: &str, color: Color) -> String {
+    match (name.trim(), color) {
+        ("", Color::White) => "White".to_string(),
+        ("", Color::Black) => "Black".to_string(),
+        (name, _) => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn request(server: &mut GameServer, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let (status, json) = server.handle(method, path, body);
+        (status, serde_json::from_str(&json).unwrap())
+    }
+
+    // Starts a game from `body` and returns its path.
+    fn create(server: &mut GameServer, body: &str) -> String {
+        let (status, state) = request(server, "POST", "/games", body);
+        assert_eq!(status, 201);
+        format!("/games/{}", state["id"].as_str().unwrap())
+    }
+
+    fn play(server: &mut GameServer, game: &str, notation: &str) -> (u16, Value) {
+        let body = format!(r#"{{"move":"{}"}}"#, notation);
+        request(server, "POST", &format!("{}/moves", game), &body)
+    }
+
+    fn assert_error(answer: (u16, Value), status: u16, kind: &str) {
+        assert_eq!(answer.0, status);
+        assert_eq!(answer.1["error"], kind);
+    }
+
+    #[test]
+    fn creates_lists_gets_and_moves() {
+        let mut server = GameServer::new();
+        let game = create(&mut server, r#"{"white":"Ann"}"#);
+
+        let (status, games) = request(&mut server, "GET", "/games", "");
+        assert_eq!(status, 200);
+        assert_eq!(games[0]["white"], "Ann");
+        assert_eq!(games[0]["black"], "Black");
+        assert_eq!(games[0]["status"], "in_progress");
+
+        let (status, state) = request(&mut server, "GET", &format!("{}?watch=1", game), "");
+        assert_eq!(status, 200);
+        assert_eq!(state["fen"], STARTING_FEN);
+        assert_eq!(state["legal_moves"].as_array().unwrap().len(), 20);
+
+        assert_eq!(play(&mut server, &game, "e4").0, 200);
+        let (status, state) = play(&mut server, &game, "e7e5");
+        assert_eq!(status, 200);
+        assert_eq!(state["moves"], serde_json::json!(["e4", "e5"]));
+        assert_eq!(state["turn"], "white");
+    }
+
+    #[test]
+    fn refuses_moves_once_the_game_is_over() {
+        let mut server = GameServer::new();
+        let game = create(&mut server, "");
+
+        for notation in ["f3", "e5", "g4", "Qh4#"] {
+            assert_eq!(play(&mut server, &game, notation).0, 200);
+        }
+        let (_, state) = request(&mut server, "GET", &game, "");
+        assert_eq!(state["status"], "black_wins");
+        assert!(state["legal_moves"].as_array().unwrap().is_empty());
+
+        assert_error(play(&mut server, &game, "a3"), 409, "game_over");
+    }
+
+    #[test]
+    fn reports_each_error() {
+        let mut server = GameServer::new();
+        let game = create(&mut server, "");
+        // Knights on b1 and f1 can both reach d2.
+        let knights = create(&mut server, r#"{"fen":"4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1"}"#);
+
+        assert_error(
+            request(&mut server, "POST", "/games", r#"{"fen":"8/8/8 w"}"#),
+            422,
+            "invalid_fen",
+        );
+        assert_error(
+            request(&mut server, "POST", "/games", "{"),
+            400,
+            "bad_request",
+        );
+        assert_error(play(&mut server, &game, "e5"), 422, "illegal_move");
+        assert_error(play(&mut server, &game, "hello"), 422, "invalid_move");
+        assert_error(play(&mut server, &knights, "Nd2"), 422, "ambiguous_move");
+        assert_error(
+            request(
+                &mut server,
+                "GET",
+                &format!("/games/{}", Uuid::new_v4()),
+                "",
+            ),
+            404,
+            "unknown_game",
+        );
+        assert_error(request(&mut server, "GET", "/boards", ""), 404, "not_found");
+        assert_error(request(&mut server, "DELETE", &game, ""), 404, "not_found");
+    }
+}
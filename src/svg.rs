@@ -0,0 +1,535 @@
+//! SVG board diagrams.
+//!
+//! [`SvgRenderer`] draws a [`Position`] or [`Game`] as a standalone SVG document, for docs, bug
+//! reports and web pages. Square colors come from a [`Theme`] and pieces from a [`PieceSet`].
+//! Coordinates and orientation are optional, and highlighted squares, arrows and the last-move
+//! and check markers are drawn over the board.
+
+use std::fmt::Write;
+
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::Position;
+use crate::render::{board_index, checked_king, glyph};
+
+// Built-in pieces are drawn in a 45 by 45 box and scaled to the square size.
+const PIECE_BOX: f64 = 45.0;
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/// Colors used to draw a board, as SVG color values such as `#f0d9b5`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub light_square: String,
+    pub dark_square: String,
+    /// Laid over both squares of the last move.
+    pub last_move: String,
+    /// Glow under a king in check.
+    pub check: String,
+    /// Laid over squares passed to [`SvgRenderer::highlight`].
+    pub highlight: String,
+    pub arrow: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::brown()
+    }
+}
+
+impl Theme {
+    /// Brown and beige wood.
+    pub fn brown() -> Self {
+        Theme::new("#f0d9b5", "#b58863")
+    }
+
+    /// Pale and steel blue.
+    pub fn blue() -> Self {
+        Theme::new("#dee3e6", "#8ca2ad")
+    }
+
+    /// Cream and green, as in tournament vinyl boards.
+    pub fn green() -> Self {
+        Theme::new("#eeeed2", "#769656")
+    }
+
+    fn new(light_square: &str, dark_square: &str) -> Self {
+        Theme {
+            light_square: light_square.to_string(),
+            dark_square: dark_square.to_string(),
+            last_move: "#cdd26a".to_string(),
+            check: "#ff0000".to_string(),
+            highlight: "#6a874d".to_string(),
+            arrow: "#15781b".to_string(),
+        }
+    }
+}
+
+/// How pieces are drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PieceSet {
+    /// Built-in vector drawings, which need no fonts or files.
+    Shapes,
+    /// Unicode chess glyphs, drawn with whatever font the viewer picks.
+    Glyphs,
+    /// External images at the given prefix followed by the color and piece letter, e.g.
+    /// `pieces/` gives `pieces/wK.svg` and `pieces/bN.svg`.
+    Images(String),
+}
+
+/// Draws boards as SVG documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgRenderer {
+    theme: Theme,
+    pieces: PieceSet,
+    square_size: u32,
+    coordinates: bool,
+    orientation: Color,
+    last_move: bool,
+    check: bool,
+    highlights: Vec<LocationCoords>,
+    arrows: Vec<(LocationCoords, LocationCoords)>,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        SvgRenderer::new()
+    }
+}
+
+impl SvgRenderer {
+    /// A renderer with the brown theme, built-in pieces on 45 pixel squares, coordinates, White
+    /// at the bottom and both markers on.
+    pub fn new() -> Self {
+        SvgRenderer {
+            theme: Theme::default(),
+            pieces: PieceSet::Shapes,
+            square_size: 45,
+            coordinates: true,
+            orientation: Color::White,
+            last_move: true,
+            check: true,
+            highlights: vec![],
+            arrows: vec![],
+        }
+    }
+
+    /// Sets the square colors.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Sets how pieces are drawn.
+    pub fn pieces(mut self, pieces: PieceSet) -> Self {
+        self.pieces = pieces;
+        self
+    }
+
+    /// Sets the width of a square in pixels. The board is eight times as wide.
+    pub fn square_size(mut self, square_size: u32) -> Self {
+        self.square_size = square_size.max(1);
+        self
+    }
+
+    /// Sets whether file letters and rank numbers are drawn on the edge squares.
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// Sets the side drawn at the bottom of the board.
+    pub fn orientation(mut self, orientation: Color) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Sets whether the squares of the last move are marked.
+    pub fn last_move(mut self, marked: bool) -> Self {
+        self.last_move = marked;
+        self
+    }
+
+    /// Sets whether a king in check is marked.
+    pub fn check(mut self, marked: bool) -> Self {
+        self.check = marked;
+        self
+    }
+
+    /// Adds a highlighted square. Squares off the board are ignored.
+    pub fn highlight(mut self, square: LocationCoords) -> Self {
+        if on_board(square) {
+            self.highlights.push(square);
+        }
+        self
+    }
+
+    /// Adds an arrow from the center of one square to another. Arrows with an end off the board
+    /// are ignored.
+    pub fn arrow(mut self, from: LocationCoords, to: LocationCoords) -> Self {
+        if on_board(from) && on_board(to) {
+            self.arrows.push((from, to));
+        }
+        self
+    }
+
+    /// Width and height of the drawn board in pixels.
+    pub fn size(&self) -> u32 {
+        self.square_size * 8
+    }
+
+    /// Draws the board of `game` with its last move.
+    pub fn render(&self, game: &Game) -> String {
+        self.render_position(&game.to_position(), game.last_move().as_ref())
+    }
+
+    /// Draws `position`, marking `last_move` if given.
+    pub fn render_position(&self, position: &Position, last_move: Option<&ChessMove>) -> String {
+        let size = self.size();
+        let mut svg = String::new();
+
+        let _ = writeln!(
+            svg,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" "#,
+                r#"viewBox="0 0 {size} {size}">"#,
+            ),
+            size = size,
+        );
+        self.write_defs(&mut svg);
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let (left, top) = self.corner(LocationCoords { x, y });
+                let _ = writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}"/>"#,
+                    left,
+                    top,
+                    escape(self.square_color(x, y)),
+                    s = self.square_size,
+                );
+            }
+        }
+
+        if let Some(last_move) = last_move.filter(|_| self.last_move) {
+            for square in [last_move.from, last_move.to] {
+                self.write_overlay(&mut svg, square, &self.theme.last_move);
+            }
+        }
+        for square in &self.highlights {
+            self.write_overlay(&mut svg, *square, &self.theme.highlight);
+        }
+
+        if self.check
+            && let Some(king) = checked_king(position)
+        {
+            let (left, top) = self.corner(king);
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="url(#check)"/>"#,
+                left,
+                top,
+                s = self.square_size,
+            );
+        }
+
+        if self.coordinates {
+            self.write_coordinates(&mut svg);
+        }
+
+        for (y, row) in position.squares.iter().enumerate() {
+            for (x, square) in row.iter().enumerate() {
+                if let Some((color, piece_type)) = square {
+                    self.write_piece(&mut svg, LocationCoords { x, y }, *color, *piece_type);
+                }
+            }
+        }
+
+        for (from, to) in &self.arrows {
+            self.write_arrow(&mut svg, *from, *to);
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn write_defs(&self, svg: &mut String) {
+        svg.push_str("<defs>\n");
+
+        let _ = writeln!(
+            svg,
+            concat!(
+                r#"<radialGradient id="check"><stop offset="0%" stop-color="{c}"/>"#,
+                r#"<stop offset="25%" stop-color="{c}"/>"#,
+                r#"<stop offset="100%" stop-color="{c}" stop-opacity="0"/></radialGradient>"#,
+            ),
+            c = escape(&self.theme.check),
+        );
+
+        if self.pieces == PieceSet::Shapes {
+            for color in [Color::White, Color::Black] {
+                for piece_type in PIECE_TYPES {
+                    let (fill, detail) = match color {
+                        Color::White => ("#ffffff", "#000000"),
+                        Color::Black => ("#000000", "#ffffff"),
+                    };
+                    let _ = writeln!(
+                        svg,
+                        concat!(
+                            r##"<g id="{}" fill="{}" stroke="#000000" stroke-width="1.5" "##,
+                            r#"stroke-linejoin="round">{}</g>"#,
+                        ),
+                        piece_name(color, piece_type),
+                        fill,
+                        shape(piece_type).replace("DETAIL", detail),
+                    );
+                }
+            }
+        }
+
+        svg.push_str("</defs>\n");
+    }
+
+    fn write_overlay(&self, svg: &mut String, square: LocationCoords, color: &str) {
+        let (left, top) = self.corner(square);
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}" fill-opacity="0.6"/>"#,
+            left,
+            top,
+            escape(color),
+            s = self.square_size,
+        );
+    }
+
+    fn write_coordinates(&self, svg: &mut String) {
+        let s = self.square_size as f64;
+        let font_size = s * 0.25;
+
+        for index in 0..8 {
+            // Files along the bottom row, ranks down the left column, each in the color of the
+            // other kind of square.
+            let file = board_index(self.orientation, index);
+            let bottom = LocationCoords {
+                x: file,
+                y: board_index(self.orientation, 7),
+            };
+            let (left, top) = self.corner(bottom);
+            let _ = writeln!(
+                svg,
+                concat!(
+                    r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{:.1}" "#,
+                    r#"text-anchor="end" fill="{}">{}</text>"#,
+                ),
+                left as f64 + s - font_size * 0.2,
+                top as f64 + s - font_size * 0.25,
+                font_size,
+                escape(self.square_color(bottom.x + 1, bottom.y)),
+                (b'a' + file as u8) as char,
+            );
+
+            let rank = board_index(self.orientation, index);
+            let side = LocationCoords {
+                x: board_index(self.orientation, 0),
+                y: rank,
+            };
+            let (left, top) = self.corner(side);
+            let _ = writeln!(
+                svg,
+                concat!(
+                    r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{:.1}" "#,
+                    r#"fill="{}">{}</text>"#,
+                ),
+                left as f64 + font_size * 0.2,
+                top as f64 + font_size,
+                font_size,
+                escape(self.square_color(side.x + 1, side.y)),
+                8 - rank,
+            );
+        }
+    }
+
+    fn write_piece(
+        &self,
+        svg: &mut String,
+        square: LocationCoords,
+        color: Color,
+        piece_type: PieceType,
+    ) {
+        let (left, top) = self.corner(square);
+        let s = self.square_size as f64;
+
+        let _ = match &self.pieces {
+            PieceSet::Shapes => writeln!(
+                svg,
+                r##"<use href="#{}" transform="translate({} {}) scale({})"/>"##,
+                piece_name(color, piece_type),
+                left,
+                top,
+                s / PIECE_BOX,
+            ),
+            PieceSet::Glyphs => writeln!(
+                svg,
+                concat!(
+                    r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="{:.1}" "#,
+                    r##"text-anchor="middle" fill="#000000">{}</text>"##,
+                ),
+                left as f64 + s / 2.0,
+                top as f64 + s * 0.8,
+                s * 0.8,
+                glyph(color, piece_type),
+            ),
+            PieceSet::Images(prefix) => writeln!(
+                svg,
+                r#"<image href="{}{}.svg" x="{}" y="{}" width="{s}" height="{s}"/>"#,
+                escape(prefix),
+                piece_name(color, piece_type),
+                left,
+                top,
+                s = self.square_size,
+            ),
+        };
+    }
+
+    fn write_arrow(&self, svg: &mut String, from: LocationCoords, to: LocationCoords) {
+        if from == to {
+            return;
+        }
+
+        let s = self.square_size as f64;
+        let center = |square| {
+            let (left, top) = self.corner(square);
+            (left as f64 + s / 2.0, top as f64 + s / 2.0)
+        };
+        let (x1, y1) = center(from);
+        let (x2, y2) = center(to);
+
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        let (ux, uy) = ((x2 - x1) / length, (y2 - y1) / length);
+        let (nx, ny) = (-uy, ux);
+
+        let shaft = s * 0.1;
+        let head = s * 0.25;
+        let head_length = (s * 0.45).min(length);
+        let (bx, by) = (x2 - ux * head_length, y2 - uy * head_length);
+
+        let points = [
+            (x1 + nx * shaft, y1 + ny * shaft),
+            (bx + nx * shaft, by + ny * shaft),
+            (bx + nx * head, by + ny * head),
+            (x2, y2),
+            (bx - nx * head, by - ny * head),
+            (bx - nx * shaft, by - ny * shaft),
+            (x1 - nx * shaft, y1 - ny * shaft),
+        ];
+        let points: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect();
+
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.8"/>"#,
+            points.join(" "),
+            escape(&self.theme.arrow),
+        );
+    }
+
+    // Top left corner of `square` in pixels.
+    fn corner(&self, square: LocationCoords) -> (u32, u32) {
+        let column = board_index(self.orientation, square.x) as u32;
+        let row = board_index(self.orientation, square.y) as u32;
+        (column * self.square_size, row * self.square_size)
+    }
+
+    fn square_color(&self, x: usize, y: usize) -> &str {
+        if (x + y).is_multiple_of(2) {
+            &self.theme.light_square
+        } else {
+            &self.theme.dark_square
+        }
+    }
+}
+
+fn on_board(square: LocationCoords) -> bool {
+    square.x < 8 && square.y < 8
+}
+
+// Color letter and uppercase piece letter, e.g. "wK".
+fn piece_name(color: Color, piece_type: PieceType) -> String {
+    let color = match color {
+        Color::White => 'w',
+        Color::Black => 'b',
+    };
+    let piece = match piece_type {
+        PieceType::King => 'K',
+        PieceType::Queen => 'Q',
+        PieceType::Rook => 'R',
+        PieceType::Bishop => 'B',
+        PieceType::Knight => 'N',
+        PieceType::Pawn => 'P',
+    };
+    format!("{}{}", color, piece)
+}
+
+// Outline of each built-in piece in the 45 by 45 box. `DETAIL` is replaced by the color of inner
+// lines, which must stand out against the piece's fill.
+fn shape(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        PieceType::King => concat!(
+            r#"<path d="M22.5 6V14M19 9.5H26" fill="none" stroke-width="2"/>"#,
+            r#"<path d="M12 34Q6 24 14 20Q19 18 22.5 23Q26 18 31 20Q39 24 33 34Z"/>"#,
+            r#"<path d="M22.5 14Q25 18 22.5 23Q20 18 22.5 14Z"/>"#,
+            r#"<path d="M11 38H34L33 34H12Z"/>"#,
+            r#"<path d="M14 30H31" fill="none" stroke="DETAIL"/>"#,
+        ),
+        PieceType::Queen => concat!(
+            r#"<path d="M12 34L9 14L15 25L16 10L20 24L22.5 9L25 24L29 10L30 25L36 14L33 34Z"/>"#,
+            r#"<circle cx="9" cy="12.5" r="2"/><circle cx="16" cy="8.5" r="2"/>"#,
+            r#"<circle cx="22.5" cy="7.5" r="2"/><circle cx="29" cy="8.5" r="2"/>"#,
+            r#"<circle cx="36" cy="12.5" r="2"/>"#,
+            r#"<path d="M11 38H34L33 34H12Z"/>"#,
+            r#"<path d="M13 30H32" fill="none" stroke="DETAIL"/>"#,
+        ),
+        PieceType::Rook => concat!(
+            r#"<path d="M11 39H34V36H11Z"/>"#,
+            r#"<path d="M14 36L15 17H30L31 36Z"/>"#,
+            r#"<path d="M12 17V9H16V12H20.5V9H24.5V12H29V9H33V17Z"/>"#,
+            r#"<path d="M15 20H30M14.5 33H30.5" fill="none" stroke="DETAIL"/>"#,
+        ),
+        PieceType::Bishop => concat!(
+            r#"<path d="M12 38H33V35H12Z"/>"#,
+            r#"<path d="M16 35Q13 26 22.5 13Q32 26 29 35Z"/>"#,
+            r#"<circle cx="22.5" cy="10" r="2.5"/>"#,
+            r#"<path d="M22.5 20V28M18.5 24H26.5" fill="none" stroke="DETAIL"/>"#,
+        ),
+        PieceType::Knight => concat!(
+            r#"<path d="M13 38H33L32 30Q32 18 26 12L24 8L21 11Q15 13 11 21L10 26L13 28L18 24"#,
+            r#"L20 26Q14 31 13 38Z"/>"#,
+            r#"<circle cx="17" cy="17" r="1.3" fill="DETAIL" stroke="none"/>"#,
+        ),
+        PieceType::Pawn => concat!(
+            r#"<circle cx="22.5" cy="13" r="5"/>"#,
+            r#"<path d="M15 37H30L27 31Q26 24 24.5 19H20.5Q19 24 18 31Z"/>"#,
+            r#"<path d="M12 39H33V35.5H12Z"/>"#,
+        ),
+    }
+}
+
+// Escapes text placed in attribute values.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
@@ -0,0 +1,1561 @@
+//! Syzygy endgame tablebases.
+//!
+//! [`Tablebases`] probes the `.rtbw` (win/draw/loss) and `.rtbz` (distance to zeroing) files
+//! found in local directories. Probes answer for the side to move under perfect play and know
+//! about the 50-move rule. A table is read into memory the first time a position needs it.
+//!
+//! The tables do not cover castling rights, so positions where castling is still possible are
+//! never probed. En passant is handled by searching captures before probing, as the format
+//! requires.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use tracing::debug;
+
+use crate::game::Game;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::player::Color;
+use crate::position::Position;
+
+/// Most pieces, kings included, that a Syzygy table can hold.
+pub const MAX_PIECES: usize = 7;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// Flags stored with each compressed block set.
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// Rank of a root move that wins, or loses, within the 50-move rule.
+const MAX_DTZ: i32 = 1 << 18;
+
+// Piece letters in the order table names list them, with the index used by `Counts`.
+const NAME_ORDER: [(char, usize); 6] = [('K', 5), ('Q', 4), ('R', 3), ('B', 2), ('N', 1), ('P', 0)];
+
+// Pieces of one side, indexed pawn, knight, bishop, rook, queen, king.
+type Counts = [u8; 6];
+
+/// Result of a position under perfect play, from the side to move's point of view.
+///
+/// Cursed wins and blessed losses are wins and losses that the 50-move rule turns into draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        match self {
+            Wdl::Loss | Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin | Wdl::Win => 1,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+impl fmt::Display for Wdl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Wdl::Loss => "loss",
+            Wdl::BlessedLoss => "blessed loss",
+            Wdl::Draw => "draw",
+            Wdl::CursedWin => "cursed win",
+            Wdl::Win => "win",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Reasons a position could not be probed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeError {
+    /// The position has more pieces than the largest installed table.
+    TooManyPieces(usize),
+    /// Castling is still possible, which the tables do not cover.
+    Castling,
+    /// A table the probe needs is not installed, e.g. `KRPvKR.rtbz`.
+    MissingTable(String),
+    /// A table file could not be read or is corrupt.
+    BadTable(String),
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeError::TooManyPieces(count) => {
+                write!(f, "{} pieces is more than the tablebases hold", count)
+            }
+            ProbeError::Castling => write!(f, "tablebases do not cover castling rights"),
+            ProbeError::MissingTable(name) => write!(f, "table {} is not installed", name),
+            ProbeError::BadTable(name) => write!(f, "table {} is corrupt or unreadable", name),
+        }
+    }
+}
+
+impl Error for ProbeError {}
+
+/// A legal move ranked by the tablebases. See [`Tablebases::root_moves`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TablebaseMove {
+    pub chess_move: ChessMove,
+    /// Result of playing the move. Wins and losses that the halfmove clock of the position does
+    /// not leave room for count as cursed wins and blessed losses.
+    pub wdl: Wdl,
+    /// Plies from the position before the move until the next capture or pawn move under
+    /// perfect play. Positive when the move wins, negative when it loses, 0 for a draw.
+    pub dtz: i32,
+}
+
+/// A set of Syzygy tables found on disk.
+#[derive(Default)]
+pub struct Tablebases {
+    tables: HashMap<String, TableFiles>,
+    max_pieces: usize,
+}
+
+impl fmt::Debug for Tablebases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tablebases")
+            .field("tables", &self.tables.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+impl Tablebases {
+    /// An empty set. Positions other than bare kings fail to probe until tables are added.
+    pub fn new() -> Self {
+        Tablebases::default()
+    }
+
+    /// Opens the tables in `dir`.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Tablebases> {
+        let mut tablebases = Tablebases::new();
+        tablebases.add_directory(dir)?;
+        Ok(tablebases)
+    }
+
+    /// Adds the tables in `dir`. WDL and DTZ files may sit in different directories; a table
+    /// is usable once its WDL file has been found. Returns how many files were added.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<usize> {
+        let mut added = 0;
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let kind = match path.extension().and_then(|e| e.to_str()) {
+                Some("rtbw") => Kind::Wdl,
+                Some("rtbz") => Kind::Dtz,
+                _ => continue,
+            };
+            let Some((white, black)) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(parse_name)
+            else {
+                debug!(path = %path.display(), "not a table name");
+                continue;
+            };
+
+            let name = table_name(&white, &black);
+            let files = self
+                .tables
+                .entry(name.clone())
+                .or_insert_with(|| TableFiles {
+                    name,
+                    material: Material::new(&white, &black),
+                    paths: [None, None],
+                    loaded: [OnceLock::new(), OnceLock::new()],
+                });
+
+            // The first directory holding a file wins.
+            if files.paths[kind as usize].is_some() {
+                continue;
+            }
+            files.paths[kind as usize] = Some(path);
+            files.loaded[kind as usize] = OnceLock::new();
+            added += 1;
+
+            if kind == Kind::Wdl {
+                self.max_pieces = self.max_pieces.max(files.material.piece_count);
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Pieces, kings included, of the largest table with a WDL file. 0 when there is none.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Win, draw or loss for the side to move.
+    ///
+    /// Only the halfmove clock being 0 makes the answer exact: the 50-move rule may turn a win
+    /// into a draw sooner than the tables assume otherwise. See [`Tablebases::root_moves`] for
+    /// results that account for the clock.
+    pub fn probe_wdl(&self, position: &Position) -> Result<Wdl, ProbeError> {
+        self.check(position)?;
+        Ok(self.search_wdl(position, false)?.0)
+    }
+
+    /// Distance to zeroing for the side to move: plies until the next capture or pawn move
+    /// under perfect play. Positive when the side to move wins, negative when it loses, 0 for a
+    /// draw. Cursed wins and blessed losses are 100 plies further from 0 than their distance.
+    ///
+    /// Some tables count whole moves, so the answer can be one ply longer than the truth.
+    pub fn probe_dtz(&self, position: &Position) -> Result<i32, ProbeError> {
+        self.check(position)?;
+        self.dtz(position)
+    }
+
+    /// Every legal move, ranked best first by its result and then by distance to zeroing:
+    /// quicker wins and slower losses are better. The halfmove clock of `position` is counted
+    /// in, so a win the 50-move rule would cut short ranks as a cursed win.
+    pub fn root_moves(&self, position: &Position) -> Result<Vec<TablebaseMove>, ProbeError> {
+        self.check(position)?;
+
+        let halfmove_clock = position.halfmove_clock as i32;
+        let mut ranked: Vec<(i32, TablebaseMove)> = vec![];
+
+        for chess_move in position.legal_moves() {
+            let next = position.make_move(&chess_move);
+
+            let mut dtz = if next.halfmove_clock == 0 {
+                dtz_before_zeroing(-self.search_wdl(&next, false)?.0)
+            } else if next.halfmove_clock >= 100 && !next.is_checkmate() {
+                0
+            } else {
+                let dtz = -self.dtz(&next)?;
+                dtz + dtz.signum()
+            };
+
+            // A mating move ends the game, it does not merely lead to a zeroing move.
+            if dtz == 2 && next.is_checkmate() {
+                dtz = 1;
+            }
+
+            let rank = match dtz.cmp(&0) {
+                Ordering::Greater if dtz + halfmove_clock <= 99 => MAX_DTZ,
+                Ordering::Greater => MAX_DTZ - (dtz + halfmove_clock),
+                Ordering::Less if -dtz * 2 + halfmove_clock < 100 => -MAX_DTZ,
+                Ordering::Less => -MAX_DTZ + (-dtz + halfmove_clock),
+                Ordering::Equal => 0,
+            };
+            let wdl = if rank == MAX_DTZ {
+                Wdl::Win
+            } else if rank > 0 {
+                Wdl::CursedWin
+            } else if rank == -MAX_DTZ {
+                Wdl::Loss
+            } else if rank < 0 {
+                Wdl::BlessedLoss
+            } else {
+                Wdl::Draw
+            };
+
+            ranked.push((
+                rank,
+                TablebaseMove {
+                    chess_move,
+                    wdl,
+                    dtz,
+                },
+            ));
+        }
+
+        ranked.sort_by_key(|(rank, tablebase_move)| (Reverse(*rank), tablebase_move.dtz));
+
+        Ok(ranked.into_iter().map(|(_, m)| m).collect())
+    }
+
+    /// The best move by [`Tablebases::root_moves`], or `None` when there is no legal move.
+    pub fn best_move(&self, position: &Position) -> Result<Option<ChessMove>, ProbeError> {
+        Ok(self.root_moves(position)?.first().map(|m| m.chess_move))
+    }
+
+    fn check(&self, position: &Position) -> Result<(), ProbeError> {
+        let pieces = position.squares.iter().flatten().flatten().count();
+        if pieces > self.max_pieces.max(2) {
+            return Err(ProbeError::TooManyPieces(pieces));
+        }
+
+        let castling = position.castling;
+        if castling.white_kingside
+            || castling.white_queenside
+            || castling.black_kingside
+            || castling.black_queenside
+        {
+            return Err(ProbeError::Castling);
+        }
+
+        Ok(())
+    }
+
+    // Tables may store anything for positions where the side to move has a winning capture,
+    // and a loss where a capture draws, as that compresses better. So captures, and for DTZ
+    // pawn moves too, are searched first and the best of them and the table wins. The flag
+    // says whether a capture or pawn move is the best move, where DTZ tables are not to be
+    // trusted.
+    fn search_wdl(
+        &self,
+        position: &Position,
+        zeroing_moves: bool,
+    ) -> Result<(Wdl, bool), ProbeError> {
+        let moves = position.legal_moves();
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+
+        for chess_move in &moves {
+            let pawn_move = matches!(
+                position.piece_at(&chess_move.from),
+                Some((_, PieceType::Pawn))
+            );
+            if !(position.is_capture(chess_move) || zeroing_moves && pawn_move) {
+                continue;
+            }
+
+            searched += 1;
+            let value = -self.search_wdl(&position.make_move(chess_move), false)?.0;
+
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Ok((value, true));
+                }
+            }
+        }
+
+        // With every legal move searched there is nothing left to look up. The tables know
+        // nothing of en passant, so this also covers positions where it is the only move.
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched {
+            best
+        } else {
+            self.probe_table(position, Kind::Wdl, Wdl::Draw)?
+                .map_or(Wdl::Draw, |value| Wdl::from_value(value - 2))
+        };
+
+        if best >= value {
+            return Ok((best, best > Wdl::Draw || all_searched));
+        }
+
+        Ok((value, false))
+    }
+
+    fn dtz(&self, position: &Position) -> Result<i32, ProbeError> {
+        let (wdl, zeroing_best) = self.search_wdl(position, true)?;
+
+        if wdl == Wdl::Draw {
+            return Ok(0);
+        }
+
+        if zeroing_best {
+            return Ok(dtz_before_zeroing(wdl));
+        }
+
+        if let Some(dtz) = self.probe_table(position, Kind::Dtz, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Ok((dtz + if cursed { 100 } else { 0 }) * wdl.signum());
+        }
+
+        // The DTZ table only stores the other side to move, so look one ply ahead and take the
+        // best distance among the moves that keep the result.
+        let mut best = i32::MAX;
+
+        for chess_move in position.legal_moves() {
+            let zeroing = position.is_capture(&chess_move)
+                || matches!(
+                    position.piece_at(&chess_move.from),
+                    Some((_, PieceType::Pawn))
+                );
+            let next = position.make_move(&chess_move);
+
+            // A zeroing move's distance is that of the move itself, not of the next one.
+            let mut dtz = if zeroing {
+                -dtz_before_zeroing(self.search_wdl(&next, false)?.0)
+            } else {
+                -self.dtz(&next)?
+            };
+
+            if dtz == 1 && next.is_checkmate() {
+                best = 1;
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < best && dtz.signum() == wdl.signum() {
+                best = dtz;
+            }
+        }
+
+        // No legal move at all: the side to move is mated.
+        Ok(if best == i32::MAX { -1 } else { best })
+    }
+
+    // Looks the position up in its table. `None` means a DTZ table that only stores the other
+    // side to move; bare kings are not looked up at all and come back as a draw.
+    fn probe_table(
+        &self,
+        position: &Position,
+        kind: Kind,
+        wdl: Wdl,
+    ) -> Result<Option<i32>, ProbeError> {
+        let mut counts: [Counts; 2] = [[0; 6]; 2];
+        for (color, piece_type) in position.squares.iter().flatten().flatten() {
+            counts[color.index()][piece_type_index(*piece_type)] += 1;
+        }
+
+        if counts.iter().flatten().sum::<u8>() == 2 {
+            return Ok(Some(match kind {
+                Kind::Wdl => 2,
+                Kind::Dtz => 0,
+            }));
+        }
+
+        // Tables list the stronger side first and call it white. With the colors the other way
+        // round on the board, the board is mirrored and the colors swapped.
+        let (files, black_stronger) = match self.tables.get(&table_name(&counts[0], &counts[1])) {
+            Some(files) => (files, false),
+            None => match self.tables.get(&table_name(&counts[1], &counts[0])) {
+                Some(files) => (files, true),
+                None => {
+                    let (strong, weak) = if strength(&counts[0]) >= strength(&counts[1]) {
+                        (&counts[0], &counts[1])
+                    } else {
+                        (&counts[1], &counts[0])
+                    };
+                    return Err(ProbeError::MissingTable(format!(
+                        "{}.{}",
+                        table_name(strong, weak),
+                        kind.extension()
+                    )));
+                }
+            },
+        };
+
+        // Tables with the same pieces on both sides only store White to move.
+        let flip =
+            black_stronger || (files.material.symmetric && position.side_to_move == Color::Black);
+
+        let table = files.load(kind)?;
+        table
+            .probe(position, flip, wdl)
+            .ok_or_else(|| ProbeError::BadTable(format!("{}.{}", files.name, kind.extension())))
+    }
+}
+
+impl Game {
+    /// Win, draw or loss for the current player. See [`Tablebases::probe_wdl`].
+    pub fn probe_wdl(&self, tablebases: &Tablebases) -> Result<Wdl, ProbeError> {
+        tablebases.probe_wdl(&self.to_position())
+    }
+
+    /// Distance to zeroing for the current player. See [`Tablebases::probe_dtz`].
+    pub fn probe_dtz(&self, tablebases: &Tablebases) -> Result<i32, ProbeError> {
+        tablebases.probe_dtz(&self.to_position())
+    }
+
+    /// The current player's legal moves ranked by the tablebases, best first.
+    pub fn tablebase_moves(
+        &self,
+        tablebases: &Tablebases,
+    ) -> Result<Vec<TablebaseMove>, ProbeError> {
+        tablebases.root_moves(&self.to_position())
+    }
+}
+
+// Distance to zeroing of a position whose best move is a capture or pawn move with result
+// `wdl`.
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+fn piece_type_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+// Piece codes as the table files store them: pawn 1 to king 6, plus 8 for black.
+fn piece_code(color: Color, piece_type: PieceType) -> u8 {
+    piece_type_index(piece_type) as u8 + 1 + color.index() as u8 * 8
+}
+
+fn table_name(white: &Counts, black: &Counts) -> String {
+    let side = |counts: &Counts| -> String {
+        NAME_ORDER
+            .iter()
+            .flat_map(|&(letter, index)| std::iter::repeat_n(letter, counts[index] as usize))
+            .collect()
+    };
+
+    format!("{}v{}", side(white), side(black))
+}
+
+// "KRPvKR" into the pieces of each side.
+fn parse_name(name: &str) -> Option<(Counts, Counts)> {
+    let (white, black) = name.split_once('v')?;
+    let side = |text: &str| -> Option<Counts> {
+        let mut counts: Counts = [0; 6];
+        for letter in text.chars() {
+            let (_, index) = NAME_ORDER.iter().find(|(l, _)| *l == letter)?;
+            counts[*index] += 1;
+        }
+        (counts[5] == 1).then_some(counts)
+    };
+
+    let (white, black) = (side(white)?, side(black)?);
+    let pieces: u8 = white.iter().chain(&black).sum();
+
+    (pieces as usize <= MAX_PIECES).then_some((white, black))
+}
+
+// Orders sides the way table names do: more pieces first, then stronger pieces first.
+fn strength(counts: &Counts) -> (u8, [u8; 5]) {
+    let total = counts.iter().sum();
+    (
+        total,
+        [counts[4], counts[3], counts[2], counts[1], counts[0]],
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Wdl,
+    Dtz,
+}
+
+impl Kind {
+    fn extension(self) -> &'static str {
+        match self {
+            Kind::Wdl => "rtbw",
+            Kind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            Kind::Wdl => WDL_MAGIC,
+            Kind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+// What a table holds, worked out from its name. The first side of the name plays White.
+#[derive(Debug, Clone, Copy)]
+struct Material {
+    piece_count: usize,
+    has_pawns: bool,
+    // Whether some piece other than a king is alone of its kind and color. Three such pieces
+    // are then encoded together instead of just the kings.
+    has_unique_pieces: bool,
+    // Pawns of the leading color, then of the other.
+    pawn_count: [usize; 2],
+    symmetric: bool,
+}
+
+impl Material {
+    fn new(white: &Counts, black: &Counts) -> Self {
+        // The side with fewer pawns leads, as that compresses better, White when tied.
+        let white_leads = black[0] == 0 || (white[0] > 0 && black[0] >= white[0]);
+        let (lead, other) = if white_leads {
+            (white[0], black[0])
+        } else {
+            (black[0], white[0])
+        };
+
+        Material {
+            piece_count: white.iter().chain(black).map(|&n| n as usize).sum(),
+            has_pawns: white[0] + black[0] > 0,
+            has_unique_pieces: white[..5].contains(&1) || black[..5].contains(&1),
+            pawn_count: [lead as usize, other as usize],
+            symmetric: white == black,
+        }
+    }
+}
+
+// The WDL and DTZ files of one table, loaded on first use.
+struct TableFiles {
+    name: String,
+    material: Material,
+    // Indexed by `Kind`.
+    paths: [Option<PathBuf>; 2],
+    loaded: [OnceLock<Option<Table>>; 2],
+}
+
+impl TableFiles {
+    fn load(&self, kind: Kind) -> Result<&Table, ProbeError> {
+        let file_name = format!("{}.{}", self.name, kind.extension());
+        let Some(path) = &self.paths[kind as usize] else {
+            return Err(ProbeError::MissingTable(file_name));
+        };
+
+        self.loaded[kind as usize]
+            .get_or_init(|| match fs::read(path) {
+                Ok(data) => {
+                    let table = Table::parse(kind, data, self.material);
+                    if table.is_none() {
+                        debug!(path = %path.display(), "malformed table");
+                    }
+                    table
+                }
+                Err(error) => {
+                    debug!(path = %path.display(), %error, "cannot read table");
+                    None
+                }
+            })
+            .as_ref()
+            .ok_or(ProbeError::BadTable(file_name))
+    }
+}
+
+// One compressed value stream: a table has one per side to move stored and, with pawns, per
+// file of the leading pawn. Offsets point into the table's data.
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    // Pieces per encoding group, 0 terminated, and the factor each group's index is scaled by.
+    // The entry after the last group holds the number of positions.
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    span: u64,
+    sparse_index_size: usize,
+    num_blocks: usize,
+    block_length_size: usize,
+    // For single valued tables, the value.
+    min_sym_len: u8,
+    lowest_sym: usize,
+    // Smallest code of each Huffman code length, left aligned.
+    base64: Vec<u64>,
+    // How many values less one each symbol expands to.
+    symlen: Vec<u8>,
+    btree: usize,
+    sparse_index: usize,
+    block_lengths: usize,
+    blocks: usize,
+    // DTZ tables: where the value maps start, by result.
+    map_idx: [usize; 4],
+}
+
+struct Table {
+    kind: Kind,
+    data: Vec<u8>,
+    material: Material,
+    // Indexed by file of the leading pawn, then side to move.
+    pairs: Vec<PairsData>,
+    sides: usize,
+    map: usize,
+}
+
+impl Table {
+    // Reads the headers of a table file. `None` when it is not a valid table for `material`.
+    fn parse(kind: Kind, data: Vec<u8>, material: Material) -> Option<Table> {
+        if data.get(..4)? != kind.magic() {
+            return None;
+        }
+
+        let sides = if kind == Kind::Wdl && !material.symmetric {
+            2
+        } else {
+            1
+        };
+        let files = if material.has_pawns { 4 } else { 1 };
+        let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+
+        let mut table = Table {
+            kind,
+            data,
+            material,
+            pairs: vec![PairsData::default(); files * sides],
+            sides,
+            map: 0,
+        };
+
+        // The first byte holds flags saying whether the table is split by side to move and has
+        // pawns; both follow from the material already.
+        let mut offset = 5;
+
+        for file in 0..files {
+            let first = *table.data.get(offset)?;
+            let second = if both_pawns {
+                *table.data.get(offset + 1)?
+            } else {
+                0xff
+            };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            offset += 1 + both_pawns as usize;
+
+            for k in 0..material.piece_count {
+                let byte = *table.data.get(offset)?;
+                for side in 0..sides {
+                    table.pairs[file * sides + side].pieces[k] =
+                        if side == 1 { byte >> 4 } else { byte & 0xf };
+                }
+                offset += 1;
+            }
+
+            for (side, order) in order.iter().enumerate().take(sides) {
+                set_groups(
+                    &mut table.pairs[file * sides + side],
+                    &material,
+                    *order,
+                    file,
+                )?;
+            }
+        }
+
+        offset += offset & 1;
+
+        for d in &mut table.pairs {
+            offset = set_sizes(d, &table.data, offset)?;
+        }
+
+        if kind == Kind::Dtz {
+            table.map = offset;
+
+            for file in 0..files {
+                let d = &mut table.pairs[file * sides];
+                if d.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+
+                if d.flags & FLAG_WIDE != 0 {
+                    offset += offset & 1;
+                    for index in &mut d.map_idx {
+                        *index = (offset - table.map) / 2 + 1;
+                        offset += 2 * u16_le(&table.data, offset)? as usize + 2;
+                    }
+                } else {
+                    for index in &mut d.map_idx {
+                        *index = offset - table.map + 1;
+                        offset += *table.data.get(offset)? as usize + 1;
+                    }
+                }
+            }
+
+            offset += offset & 1;
+        }
+
+        for d in &mut table.pairs {
+            d.sparse_index = offset;
+            offset += d.sparse_index_size * 6;
+        }
+
+        for d in &mut table.pairs {
+            d.block_lengths = offset;
+            offset += d.block_length_size * 2;
+        }
+
+        for d in &mut table.pairs {
+            offset = (offset + 0x3f) & !0x3f;
+            d.blocks = offset;
+            offset += d.num_blocks * d.block_size;
+        }
+
+        (offset <= table.data.len()).then_some(table)
+    }
+
+    fn pairs(&self, side: usize, file: usize) -> &PairsData {
+        &self.pairs[file * self.sides + side % self.sides]
+    }
+
+    // Looks up the raw table value of `position`. With `flip` the board is mirrored top to
+    // bottom and the colors swapped first. `None` for DTZ means the position's side to move is
+    // not stored; a corrupt table gives `None` too, so the caller tells them apart.
+    fn probe(&self, position: &Position, flip: bool, wdl: Wdl) -> Option<Option<i32>> {
+        let (side, file, index) = self.encode(position, flip)?;
+        let d = self.pairs(side, file);
+
+        if self.kind == Kind::Dtz {
+            let stored = (d.flags & FLAG_STM) as usize == side
+                || (self.material.symmetric && !self.material.has_pawns);
+            if !stored {
+                return Some(None);
+            }
+        }
+
+        let value = self.decompress(d, index)?;
+
+        Some(Some(match self.kind {
+            Kind::Wdl => value,
+            Kind::Dtz => self.map_dtz(file, value, wdl)?,
+        }))
+    }
+
+    // Where `position` is stored: the side to move as the table sees it, the file of the
+    // leading pawn and the index into that value stream.
+    fn encode(&self, position: &Position, flip: bool) -> Option<(usize, usize, u64)> {
+        let indexes = indexes();
+        let material = &self.material;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side = flip as usize ^ position.side_to_move.index();
+
+        // Squares count a1 = 0 to h8 = 63, while `y = 0` is rank 8 here.
+        let mut board: Vec<(usize, u8)> = Vec::with_capacity(MAX_PIECES);
+        for rank in 0..8 {
+            for file in 0..8 {
+                if let Some((color, piece_type)) = position.squares[7 - rank][file] {
+                    board.push((rank * 8 + file, piece_code(color, piece_type)));
+                }
+            }
+        }
+        if board.len() != material.piece_count {
+            return None;
+        }
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut tb_file = 0;
+
+        // Pawn tables are split by the file of the leading pawn, the one nearest the edge and
+        // then the lowest, of the leading color.
+        let lead = material
+            .has_pawns
+            .then(|| self.pairs(0, 0).pieces[0] ^ flip_color);
+        if let Some(lead) = lead {
+            for &(square, code) in &board {
+                if code == lead {
+                    squares[size] = square ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns = size;
+
+            let leading = (0..lead_pawns).max_by_key(|&i| indexes.map_pawns[squares[i]])?;
+            squares.swap(0, leading);
+            tb_file = (squares[0] % 8).min(7 - squares[0] % 8);
+        }
+
+        for &(square, code) in &board {
+            if lead == Some(code) {
+                continue;
+            }
+            squares[size] = square ^ flip_squares;
+            pieces[size] = code ^ flip_color;
+            size += 1;
+        }
+
+        let d = self.pairs(side, tb_file);
+
+        // Put the pieces in the order the table encodes them.
+        for i in lead_pawns..size - 1 {
+            if let Some(j) = (i..size).find(|&j| pieces[j] == d.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // Mirror so the leading piece is on files a to d.
+        if squares[0] % 8 > 3 {
+            squares[..size].iter_mut().for_each(|s| *s ^= 7);
+        }
+
+        let mut index: u64;
+
+        if material.has_pawns {
+            index = indexes.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|&s| indexes.map_pawns[s]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns).skip(1) {
+                index += indexes.binomial[i][indexes.map_pawns[square]];
+            }
+        } else {
+            // Without pawns, mirror further so the leading piece is on ranks 1 to 4, and then
+            // in the a1-d1-d4 triangle.
+            if squares[0] / 8 > 3 {
+                squares[..size].iter_mut().for_each(|s| *s ^= 56);
+            }
+
+            for i in 0..d.group_len[0] {
+                let off = off_diagonal(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for s in &mut squares[i..size] {
+                        *s = ((*s >> 3) | (*s << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            index = if material.has_unique_pieces {
+                encode_unique(indexes, &squares)
+            } else {
+                indexes.map_kk[indexes.map_a1d1d4[squares[0]]][squares[1]]
+            };
+        }
+
+        // The remaining groups are combinations of the squares the earlier groups left free.
+        index *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut group = 1;
+
+        while d.group_len[group] != 0 {
+            let len = d.group_len[group];
+            squares[start..start + len].sort_unstable();
+
+            let mut n: u64 = 0;
+            for i in 0..len {
+                let square = squares[start + i];
+                let below = squares[..start].iter().filter(|&&s| square > s).count();
+                let free =
+                    square
+                        .checked_sub(below)?
+                        .checked_sub(if remaining_pawns { 8 } else { 0 })?;
+                n += indexes.binomial.get(i + 1)?.get(free)?;
+            }
+
+            remaining_pawns = false;
+            index += n * d.group_idx[group];
+            start += len;
+            group += 1;
+        }
+
+        Some((side, tb_file, index))
+    }
+
+    // Turns a stored DTZ value into plies.
+    fn map_dtz(&self, file: usize, value: i32, wdl: Wdl) -> Option<i32> {
+        let d = self.pairs(0, file);
+        let mut value = value;
+
+        if d.flags & FLAG_MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Loss => d.map_idx[1],
+                Wdl::BlessedLoss => d.map_idx[3],
+                Wdl::Draw => d.map_idx[0],
+                Wdl::CursedWin => d.map_idx[2],
+                Wdl::Win => d.map_idx[0],
+            } + value as usize;
+
+            value = if d.flags & FLAG_WIDE != 0 {
+                u16_le(&self.data, self.map + 2 * map)? as i32
+            } else {
+                *self.data.get(self.map + map)? as i32
+            };
+        }
+
+        let in_moves = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+
+    // Finds the value at `index`. Values are Huffman coded symbols in fixed size blocks; a
+    // symbol may stand for a pair of symbols, recursively, and so for a run of values. The
+    // sparse index points close to the block that holds each `span`-th value.
+    fn decompress(&self, d: &PairsData, index: u64) -> Option<i32> {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(d.min_sym_len as i32);
+        }
+
+        let data = &self.data;
+        let entry = d.sparse_index + (index / d.span) as usize * 6;
+        if index / d.span >= d.sparse_index_size as u64 {
+            return None;
+        }
+        let mut block = u32_le(data, entry)? as usize;
+        let mut offset = u16_le(data, entry + 4)? as i64;
+        offset += (index % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            (block < d.block_length_size)
+                .then(|| u16_le(data, d.block_lengths + block * 2).map(i64::from))?
+        };
+
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut at = d.blocks + block * d.block_size;
+        let mut buffer = read_be(data, at, 8);
+        at += 8;
+        let mut buffered: i32 = 64;
+        let min_len = d.min_sym_len as u32;
+
+        let mut symbol = loop {
+            let mut len = 0;
+            while buffer < *d.base64.get(len)? {
+                len += 1;
+            }
+
+            let shift = 64 - len as u32 - min_len;
+            let symbol = (buffer - d.base64[len]).checked_shr(shift).unwrap_or(0) as usize
+                + u16_le(data, d.lowest_sym + 2 * len)? as usize;
+            let run = *d.symlen.get(symbol)? as i64 + 1;
+
+            if offset < run {
+                break symbol;
+            }
+
+            offset -= run;
+            let consumed = len as u32 + min_len;
+            buffer = buffer.checked_shl(consumed).unwrap_or(0);
+            buffered -= consumed as i32;
+
+            if buffered <= 32 {
+                if buffered < 0 {
+                    return None;
+                }
+                buffered += 32;
+                buffer |= read_be(data, at, 4) << (64 - buffered);
+                at += 4;
+            }
+        };
+
+        // Expand the symbol down to the single value at `offset`.
+        while d.symlen[symbol] != 0 {
+            let (left, right) = pair(data, d.btree, symbol)?;
+            let left_run = *d.symlen.get(left)? as i64 + 1;
+
+            if offset < left_run {
+                symbol = left;
+            } else {
+                offset -= left_run;
+                symbol = right;
+            }
+        }
+
+        Some(pair(data, d.btree, symbol)?.0 as i32)
+    }
+}
+
+// Splits the pieces into encoding groups and works out each group's index factor. A group is
+// the leading pawns, or the first two or three pieces without pawns, or a run of identical
+// pieces. `order` says where the leading group, and the other side's pawns, come in the
+// encoding; the other groups fill the remaining places in turn.
+fn set_groups(d: &mut PairsData, material: &Material, order: [u8; 2], file: usize) -> Option<()> {
+    let indexes = indexes();
+    let mut n = 0;
+    let mut first_len: i32 = if material.has_pawns {
+        0
+    } else if material.has_unique_pieces {
+        3
+    } else {
+        2
+    };
+    d.group_len[0] = 1;
+
+    for i in 1..material.piece_count {
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+            d.group_len[n] += 1;
+        } else {
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    let both_pawns = material.has_pawns && material.pawn_count[1] > 0;
+    let mut next = if both_pawns { 2 } else { 1 };
+    let mut free = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+    let mut index: u64 = 1;
+    let mut k = 0;
+
+    while next < n || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            d.group_idx[0] = index;
+            index *= if material.has_pawns {
+                *indexes.lead_pawns_size.get(d.group_len[0])?.get(file)?
+            } else if material.has_unique_pieces {
+                31_332
+            } else {
+                462
+            };
+        } else if k == order[1] as usize {
+            d.group_idx[1] = index;
+            index *= indexes.binomial.get(d.group_len[1])?[48 - d.group_len[0]];
+        } else {
+            d.group_idx[next] = index;
+            index *= indexes.binomial.get(d.group_len[next])?[free];
+            free -= d.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+
+    d.group_idx[n] = index;
+    Some(())
+}
+
+// Reads the Huffman and block layout of one value stream. Returns the offset after it.
+fn set_sizes(d: &mut PairsData, data: &[u8], mut offset: usize) -> Option<usize> {
+    d.flags = *data.get(offset)?;
+    offset += 1;
+
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        d.min_sym_len = *data.get(offset)?;
+        return Some(offset + 1);
+    }
+
+    let groups = d.group_len.iter().position(|&len| len == 0)?;
+    let size = d.group_idx[groups];
+
+    d.block_size = 1usize.checked_shl(*data.get(offset)? as u32)?;
+    d.span = 1u64.checked_shl(*data.get(offset + 1)? as u32)?;
+    d.sparse_index_size = size.div_ceil(d.span) as usize;
+    let padding = *data.get(offset + 2)? as usize;
+    d.num_blocks = u32_le(data, offset + 3)? as usize;
+    d.block_length_size = d.num_blocks + padding;
+    let max_len = *data.get(offset + 7)?;
+    d.min_sym_len = *data.get(offset + 8)?;
+    offset += 9;
+
+    if d.min_sym_len == 0 || max_len < d.min_sym_len {
+        return None;
+    }
+
+    // Canonical Huffman codes: longer codes are smaller numbers, and the codes of one length
+    // are consecutive. `lowest_sym` holds the first symbol of each length, longest last.
+    d.lowest_sym = offset;
+    let lengths = (max_len - d.min_sym_len) as usize + 1;
+    let lowest = |i: usize| u16_le(data, offset + 2 * i).map(u64::from);
+    d.base64 = vec![0; lengths];
+    for i in (0..lengths - 1).rev() {
+        d.base64[i] = (d.base64[i + 1] + lowest(i)?).checked_sub(lowest(i + 1)?)? / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        *base = base
+            .checked_shl(64 - i as u32 - d.min_sym_len as u32)
+            .unwrap_or(0);
+    }
+    offset += lengths * 2;
+
+    let symbols = u16_le(data, offset)? as usize;
+    offset += 2;
+    d.btree = offset;
+    data.get(offset..offset + symbols * 3)?;
+
+    d.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            d.symlen[symbol] = set_symlen(d, data, symbol, &mut visited)?;
+        }
+    }
+
+    Some(offset + symbols * 3 + (symbols & 1))
+}
+
+// How many values less one `symbol` stands for. Leaves have 0xfff on the right.
+fn set_symlen(d: &mut PairsData, data: &[u8], symbol: usize, visited: &mut [bool]) -> Option<u8> {
+    visited[symbol] = true;
+
+    let (left, right) = pair(data, d.btree, symbol)?;
+    if right == 0xfff {
+        return Some(0);
+    }
+
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            d.symlen[child] = set_symlen(d, data, child, visited)?;
+        }
+    }
+
+    Some(d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1))
+}
+
+// The two 12-bit halves of a pair entry.
+fn pair(data: &[u8], btree: usize, symbol: usize) -> Option<(usize, usize)> {
+    let bytes = data.get(btree + symbol * 3..btree + symbol * 3 + 3)?;
+    let left = ((bytes[1] as usize & 0xf) << 8) | bytes[0] as usize;
+    let right = ((bytes[2] as usize) << 4) | (bytes[1] as usize >> 4);
+
+    Some((left, right))
+}
+
+// Index of the first three pieces of a pawnless table with unique pieces, the first of them
+// in the a1-d1-d4 triangle.
+fn encode_unique(indexes: &Indexes, squares: &[usize; MAX_PIECES]) -> u64 {
+    let [s0, s1, s2] = [squares[0], squares[1], squares[2]];
+    let adjust1 = (s1 > s0) as u64;
+    let adjust2 = (s2 > s0) as u64 + (s2 > s1) as u64;
+    let rank = |square: usize| (square / 8) as u64;
+
+    if off_diagonal(s0) != 0 {
+        (indexes.map_a1d1d4[s0] as u64 * 63 + (s1 as u64 - adjust1)) * 62 + s2 as u64 - adjust2
+    } else if off_diagonal(s1) != 0 {
+        (6 * 63 + rank(s0) * 28 + indexes.map_b1h1h7[s1]) * 62 + s2 as u64 - adjust2
+    } else if off_diagonal(s2) != 0 {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + rank(s0) * 7 * 28
+            + (rank(s1) - adjust1) * 28
+            + indexes.map_b1h1h7[s2]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + rank(s0) * 7 * 6
+            + (rank(s1) - adjust1) * 6
+            + (rank(s2) - adjust2)
+    }
+}
+
+// Rank minus file: 0 on the a1-h8 diagonal, negative below it.
+fn off_diagonal(square: usize) -> i32 {
+    (square / 8) as i32 - (square % 8) as i32
+}
+
+// Lookup tables for turning piece squares into table indexes.
+struct Indexes {
+    // Ways to choose k of n squares.
+    binomial: [[u64; 64]; MAX_PIECES],
+    // Squares below the a1-h8 diagonal, 0 to 27.
+    map_b1h1h7: [u64; 64],
+    // Squares of the a1-d1-d4 triangle: 0 to 5 below the diagonal, 6 to 9 on it.
+    map_a1d1d4: [usize; 64],
+    // The 462 placements of two kings with the first in the a1-d1-d4 triangle.
+    map_kk: [[u64; 64]; 10],
+    // Pawn squares a2 to h7, 47 down to 0: the highest is the leading pawn.
+    map_pawns: [usize; 64],
+    // Index of the leading pawn square for each count of leading pawns.
+    lead_pawn_idx: [[u64; 64]; 6],
+    // Number of leading pawn placements for each count and file.
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+fn indexes() -> &'static Indexes {
+    static INDEXES: OnceLock<Indexes> = OnceLock::new();
+    INDEXES.get_or_init(Indexes::new)
+}
+
+impl Indexes {
+    #[allow(clippy::needless_range_loop)]
+    fn new() -> Self {
+        let mut map_b1h1h7 = [0; 64];
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        let mut map_a1d1d4 = [0; 64];
+        let mut diagonal: Vec<usize> = vec![];
+        let mut code = 0;
+        for square in (0..=27).filter(|square| square % 8 <= 3) {
+            match off_diagonal(square).cmp(&0) {
+                Ordering::Less => {
+                    map_a1d1d4[square] = code;
+                    code += 1;
+                }
+                Ordering::Equal => diagonal.push(square),
+                Ordering::Greater => (),
+            }
+        }
+        for square in diagonal {
+            map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // Placements with both kings on the diagonal come last.
+        let mut map_kk = [[0; 64]; 10];
+        let mut both_on_diagonal: Vec<(usize, usize)> = vec![];
+        let mut code = 0;
+        for idx in 0..10 {
+            for first in 0..=27 {
+                if map_a1d1d4[first] != idx || (idx == 0 && first != 1) {
+                    continue;
+                }
+
+                for second in 0..64 {
+                    let touching = (first % 8).abs_diff(second % 8) <= 1
+                        && (first / 8).abs_diff(second / 8) <= 1;
+                    if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    }
+
+                    if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((idx, second));
+                    } else {
+                        map_kk[idx][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, second) in both_on_diagonal {
+            map_kk[idx][second] = code;
+            code += 1;
+        }
+
+        let mut binomial = [[0; 64]; MAX_PIECES];
+        binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                binomial[k][n] = if k > 0 { binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        let mut map_pawns = [0; 64];
+        let mut lead_pawn_idx = [[0; 64]; 6];
+        let mut lead_pawns_size = [[0; 4]; 6];
+        let mut available: usize = 47;
+        for lead_pawns in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+                    if lead_pawns == 1 {
+                        map_pawns[square] = available;
+                        map_pawns[square ^ 7] = available - 1;
+                        available = available.saturating_sub(2);
+                    }
+                    lead_pawn_idx[lead_pawns][square] = idx;
+                    idx += binomial[lead_pawns - 1][map_pawns[square]];
+                }
+                lead_pawns_size[lead_pawns][file] = idx;
+            }
+        }
+
+        Indexes {
+            binomial,
+            map_b1h1h7,
+            map_a1d1d4,
+            map_kk,
+            map_pawns,
+            lead_pawn_idx,
+            lead_pawns_size,
+        }
+    }
+}
+
+fn u16_le(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_le(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+// Big-endian bits of a compressed block. The decoder reads a little ahead of the symbols it
+// needs, which may run past the end of the file after the last block.
+fn read_be(data: &[u8], offset: usize, bytes: usize) -> u64 {
+    (0..bytes).fold(0, |value, i| {
+        value << 8 | data.get(offset + i).copied().unwrap_or(0) as u64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The three bytes of a `pair` entry. Leaves hold their value on the left.
+    fn pair_bytes(left: usize, right: usize) -> [u8; 3] {
+        [
+            left as u8,
+            (left >> 8) as u8 | (right as u8 & 0xf) << 4,
+            (right >> 4) as u8,
+        ]
+    }
+
+    // A KRvK WDL table, pieces in the order white king, black king, white rook. White to move
+    // holds raw values 0 and 4 in turn for the first 256 indexes and 2 after that, with 8-bit
+    // codes that are the symbol numbers. Black to move is a single value table of 2.
+    fn krk_table() -> Table {
+        let white_king = piece_code(Color::White, PieceType::King);
+        let black_king = piece_code(Color::Black, PieceType::King);
+        let white_rook = piece_code(Color::White, PieceType::Rook);
+
+        let mut data = WDL_MAGIC.to_vec();
+        data.push(0);
+        // Leading group order, then the pieces of both sides to move.
+        data.push(0);
+        data.extend([white_king, black_king, white_rook].map(|code| code | code << 4));
+        data.push(0);
+
+        // Leaves 0, 4 and 2, then runs of two up to 256 of alternating 0 and 4 (symbol 10) and
+        // of 2 (symbol 18).
+        let mut tree = vec![(0, 0xfff), (4, 0xfff), (2, 0xfff), (0, 1)];
+        tree.extend((3..10).map(|symbol| (symbol, symbol)));
+        tree.push((2, 2));
+        tree.extend((11..18).map(|symbol| (symbol, symbol)));
+
+        data.push(0);
+        data.extend([7, 15, 0]);
+        data.extend(1u32.to_le_bytes());
+        data.extend([8, 8]);
+        data.extend(0u16.to_le_bytes());
+        data.extend((tree.len() as u16).to_le_bytes());
+        for &(left, right) in &tree {
+            data.extend(pair_bytes(left, right));
+        }
+        data.push(0);
+
+        data.extend([FLAG_SINGLE_VALUE, 2]);
+
+        // Sparse index: block 0, its offset taking back the half span the decoder subtracts.
+        data.extend(0u32.to_le_bytes());
+        data.extend((1u16 << 14).to_le_bytes());
+        data.extend(31_331u16.to_le_bytes());
+
+        data.resize(data.len().next_multiple_of(64), 0);
+        data.push(10);
+        data.extend([18; 122]);
+        data.resize(data.len().next_multiple_of(128), 0);
+
+        let (white, black) = parse_name("KRvK").unwrap();
+        Table::parse(Kind::Wdl, data, Material::new(&white, &black)).unwrap()
+    }
+
+    fn raw_value(table: &Table, fen: &str) -> Option<i32> {
+        let position = Position::from_fen(fen).unwrap();
+        table.probe(&position, false, Wdl::Draw).unwrap()
+    }
+
+    #[test]
+    fn indexes_count_placements() {
+        let indexes = indexes();
+
+        let kings = indexes.map_kk.iter().flatten().max().unwrap();
+        assert_eq!(kings + 1, 462);
+        assert_eq!(indexes.binomial[2][5], 10);
+        assert_eq!(indexes.lead_pawns_size[1].iter().sum::<u64>(), 24);
+    }
+
+    #[test]
+    fn encodes_unique_pieces() {
+        let table = krk_table();
+        let index = |fen: &str| {
+            let position = Position::from_fen(fen).unwrap();
+            table.encode(&position, false).unwrap()
+        };
+
+        assert_eq!(table.pairs[0].group_idx[1], 31_332);
+        // King b1, king d1 and rook a1: (0 * 63 + 3 - 1) * 62 + 0.
+        assert_eq!(index("8/8/8/8/8/8/8/RK1k4 w - - 0 1"), (0, 0, 124));
+        // The same mirrored to the h-file.
+        assert_eq!(index("8/8/8/8/8/8/8/4k1KR w - - 0 1"), (0, 0, 124));
+        // King b1, king h8 and rook a8: (0 * 63 + 63 - 1) * 62 + 56 - 1.
+        assert_eq!(index("R6k/8/8/8/8/8/8/1K6 b - - 0 1"), (1, 0, 3899));
+    }
+
+    #[test]
+    fn decompresses_pairs() {
+        let table = krk_table();
+
+        assert_eq!(raw_value(&table, "8/8/8/8/8/8/8/RK1k4 w - - 0 1"), Some(0));
+        // Rook b2 instead: index 131.
+        assert_eq!(
+            raw_value(&table, "8/8/8/8/8/8/1R6/1K1k4 w - - 0 1"),
+            Some(4)
+        );
+        assert_eq!(raw_value(&table, "R6k/8/8/8/8/8/8/1K6 w - - 0 1"), Some(2));
+        assert_eq!(raw_value(&table, "R6k/8/8/8/8/8/8/1K6 b - - 0 1"), Some(2));
+    }
+
+    // Known results from real tables, found in the directories listed in `SYZYGY_PATH`: the
+    // 3-piece WDL and DTZ files and KQQvKR.rtbw. Skipped when the variable is not set.
+    #[test]
+    fn probes_real_tables() {
+        let Some(paths) = std::env::var_os("SYZYGY_PATH") else {
+            eprintln!("SYZYGY_PATH is not set, skipping the real tables");
+            return;
+        };
+        let mut tablebases = Tablebases::new();
+        for dir in std::env::split_paths(&paths) {
+            tablebases.add_directory(dir).unwrap();
+        }
+        let wdl = |fen: &str| tablebases.probe_wdl(&Position::from_fen(fen).unwrap());
+        let dtz = |fen: &str| tablebases.probe_dtz(&Position::from_fen(fen).unwrap());
+
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/KQ6 w - - 0 1"), Ok(Wdl::Win));
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/KQ6 b - - 0 1"), Ok(Wdl::Loss));
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/KR6 w - - 0 1"), Ok(Wdl::Win));
+        assert_eq!(wdl("8/8/8/4k3/8/8/8/KN6 w - - 0 1"), Ok(Wdl::Draw));
+        // The pawn promotes out of the black king's reach.
+        assert_eq!(wdl("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), Ok(Wdl::Win));
+        // The black king takes the pawn, which its own king is too far away to defend.
+        assert_eq!(wdl("8/8/8/8/8/4k3/4P3/K7 w - - 0 1"), Ok(Wdl::Draw));
+        assert_eq!(wdl("4k3/8/8/8/8/8/7r/KQQ5 w - - 0 1"), Ok(Wdl::Win));
+
+        // Mate with Qh8 and promotion on e8 both zero the count at once.
+        assert_eq!(dtz("k7/8/1K6/8/8/8/8/7Q w - - 0 1"), Ok(1));
+        assert_eq!(dtz("8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), Ok(1));
+        assert_eq!(dtz("8/4P3/8/8/8/8/k7/4K3 b - - 0 1"), Ok(-2));
+        assert_eq!(dtz("8/8/8/4k3/8/8/8/KN6 w - - 0 1"), Ok(0));
+        assert!(dtz("8/8/8/4k3/8/8/8/KR6 w - - 0 1").unwrap() > 1);
+    }
+}
@@ -0,0 +1,115 @@
+//! Time management for searches played under a clock.
+//!
+//! A [`TimeManager`] turns the side to move's [`Clock`] into two budgets. The soft budget is what
+//! the search aims to spend: it is checked between iterations and stretched or shrunk depending
+//! on how stable the best move is. The hard budget is checked with the other search limits and
+//! is never exceeded.
+
+use std::time::{Duration, Instant};
+
+use crate::movegen::ChessMove;
+
+// Moves the remaining time is spread over when the time control does not say.
+const DEFAULT_MOVES_TO_GO: u32 = 25;
+// Most moves to budget for at once, so long controls still think a reasonable time per move.
+const MAX_MOVES_TO_GO: u32 = 50;
+// Time kept back for communication and move overhead.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(20);
+// Soft budget multipliers indexed by how many iterations in a row kept the same best move.
+const STABILITY_SCALE: [f64; 5] = [2.0, 1.5, 1.2, 1.0, 0.8];
+// An iteration is only started when this fraction of the soft budget is still left, since the
+// next one usually takes longer than all earlier iterations together.
+const NEXT_ITERATION_FRACTION: f64 = 0.6;
+
+/// The clock of the side to move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Clock {
+    /// Time left on the clock.
+    pub remaining: Duration,
+    /// Time added after every move.
+    pub increment: Duration,
+    /// Moves to play before the next time control, if the control has one.
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long one search may think. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    forced: bool,
+    best_move: Option<ChessMove>,
+    stable_iterations: usize,
+}
+
+impl TimeManager {
+    /// Budgets a search starting now. `legal_moves` is the number of legal moves at the root;
+    /// with only one the search stops after its first iteration.
+    pub fn new(clock: &Clock, legal_moves: usize) -> Self {
+        let moves_to_go = clock
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, MAX_MOVES_TO_GO);
+        let available = clock.remaining.saturating_sub(MOVE_OVERHEAD);
+
+        let hard = (available / moves_to_go * 4 + clock.increment).min(available * 4 / 5);
+        let soft = (available / moves_to_go + clock.increment * 3 / 4).min(hard);
+
+        TimeManager {
+            start: Instant::now(),
+            soft,
+            hard,
+            forced: legal_moves <= 1,
+            best_move: None,
+            stable_iterations: 0,
+        }
+    }
+
+    /// Time the search aims to spend with a stable best move.
+    pub fn soft_limit(&self) -> Duration {
+        self.soft
+    }
+
+    /// Time the search may never exceed.
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Whether the hard budget is used up. The search checks this together with its other
+    /// limits.
+    pub fn out_of_time(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+
+    /// Records the best move of a completed iteration and decides whether another one should
+    /// start.
+    ///
+    /// The soft budget grows when the best move just changed and shrinks once it has stayed the
+    /// same for a few iterations. A forced move never needs a second iteration.
+    pub fn iteration_complete(&mut self, best_move: Option<ChessMove>) -> bool {
+        if self.forced {
+            return false;
+        }
+
+        if best_move == self.best_move {
+            self.stable_iterations += 1;
+        } else {
+            self.best_move = best_move;
+            self.stable_iterations = 0;
+        }
+
+        self.elapsed().as_secs_f64() < self.scaled_soft_limit() * NEXT_ITERATION_FRACTION
+    }
+
+    // Soft budget adjusted for best move stability, in seconds, never beyond the hard budget.
+    fn scaled_soft_limit(&self) -> f64 {
+        let scale = STABILITY_SCALE[self.stable_iterations.min(STABILITY_SCALE.len() - 1)];
+
+        (self.soft.as_secs_f64() * scale).min(self.hard.as_secs_f64())
+    }
+}
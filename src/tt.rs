@@ -0,0 +1,260 @@
+//! Transposition table shared by searches.
+//!
+//! The table is a fixed block of buckets sized in megabytes and indexed by
+//! [`Position::hash`](crate::position::Position::hash). Every entry is two atomic words, the key
+//! XORed with the data and the data itself, so searches on several threads can read and write it
+//! without locks: a torn write simply fails the key check and reads as a miss.
+
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+
+/// Table size used when none is configured, in megabytes.
+pub const DEFAULT_SIZE_MB: usize = 16;
+
+const ENTRY_BYTES: usize = 16;
+const BUCKET_SIZE: usize = 4;
+const GENERATIONS: u8 = 64;
+
+/// How the stored score relates to the true score of the position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high; the true score is at least the stored one.
+    Lower,
+    /// The search failed low; the true score is at most the stored one.
+    Upper,
+}
+
+/// What a search stored about a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<ChessMove>,
+    /// Score as stored. Mate scores are relative to the stored node, not to the root.
+    pub score: i32,
+    /// Remaining depth the position was searched to.
+    pub depth: u32,
+    pub bound: Bound,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// Fixed-size, lock-free transposition table. Share it between searches with an `Arc`.
+///
+/// Each bucket holds four entries. A new entry replaces one for the same position, otherwise the
+/// entry that is shallowest once entries from earlier searches are aged down.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    generation: AtomicU8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_SIZE_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Creates a table using about `megabytes` of memory. At least one bucket is always
+    /// allocated.
+    pub fn new(megabytes: usize) -> Self {
+        let buckets = (megabytes * 1024 * 1024 / (ENTRY_BYTES * BUCKET_SIZE)).max(1);
+        let mut slots = Vec::with_capacity(buckets * BUCKET_SIZE);
+        slots.resize_with(buckets * BUCKET_SIZE, Slot::default);
+
+        TranspositionTable {
+            slots,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    /// Size of the table in megabytes.
+    pub fn size_mb(&self) -> usize {
+        self.slots.len() * ENTRY_BYTES / (1024 * 1024)
+    }
+
+    /// Number of entries the table can hold.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Empties the table. Call between games so old results do not leak into the next one.
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
+    }
+
+    /// Marks the start of a new search, so entries from earlier searches are replaced first.
+    pub fn new_search(&self) {
+        let next = (self.generation.load(Ordering::Relaxed) + 1) % GENERATIONS;
+        self.generation.store(next, Ordering::Relaxed);
+    }
+
+    /// Looks up the entry for a position hash.
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        self.bucket(key).iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data == 0 || slot.key.load(Ordering::Relaxed) ^ data != key {
+                return None;
+            }
+
+            unpack(data)
+        })
+    }
+
+    /// Stores a search result for a position hash. A missing `best_move` keeps the move already
+    /// stored for the same position.
+    pub fn store(
+        &self,
+        key: u64,
+        depth: u32,
+        bound: Bound,
+        score: i32,
+        best_move: Option<ChessMove>,
+    ) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = self.bucket(key);
+
+        let mut victim = &bucket[0];
+        let mut victim_worth = i32::MAX;
+        let mut existing: Option<TtEntry> = None;
+
+        for slot in bucket {
+            let data = slot.data.load(Ordering::Relaxed);
+            if data != 0 && slot.key.load(Ordering::Relaxed) ^ data == key {
+                victim = slot;
+                existing = unpack(data);
+                break;
+            }
+
+            let worth = match unpack(data) {
+                None => i32::MIN,
+                Some(entry) => {
+                    let age = generation.wrapping_sub(data_generation(data)) % GENERATIONS;
+                    entry.depth as i32 - 8 * age as i32
+                }
+            };
+            if worth < victim_worth {
+                victim = slot;
+                victim_worth = worth;
+            }
+        }
+
+        let best_move = best_move.or(existing.and_then(|entry| entry.best_move));
+        let data = pack(best_move, score, depth, bound, generation);
+        victim.key.store(key ^ data, Ordering::Relaxed);
+        victim.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Permille of sampled entries written during the current search, as UCI `hashfull` expects.
+    pub fn hashfull(&self) -> u32 {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| {
+                let data = slot.data.load(Ordering::Relaxed);
+                data != 0 && data_generation(data) == generation
+            })
+            .count();
+
+        (used * 1000 / sample) as u32
+    }
+
+    fn bucket(&self, key: u64) -> &[Slot] {
+        let buckets = (self.slots.len() / BUCKET_SIZE) as u128;
+        let index = ((key as u128 * buckets) >> 64) as usize;
+
+        &self.slots[index * BUCKET_SIZE..(index + 1) * BUCKET_SIZE]
+    }
+}
+
+// Data word layout, low bits first: move (16), score (16), depth (8), bound (2),
+// generation (6). A zero word is an empty slot, which is why bounds start at 1.
+fn pack(best_move: Option<ChessMove>, score: i32, depth: u32, bound: Bound, generation: u8) -> u64 {
+    let bound = match bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+
+    encode_move(best_move) as u64
+        | (score as i16 as u16 as u64) << 16
+        | (depth.min(u8::MAX as u32) as u64) << 32
+        | bound << 40
+        | (generation as u64) << 42
+}
+
+fn unpack(data: u64) -> Option<TtEntry> {
+    let bound = match (data >> 40) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+
+    Some(TtEntry {
+        best_move: decode_move(data as u16),
+        score: (data >> 16) as u16 as i16 as i32,
+        depth: ((data >> 32) & 0xff) as u32,
+        bound,
+    })
+}
+
+fn data_generation(data: u64) -> u8 {
+    (data >> 42) as u8 & (GENERATIONS - 1)
+}
+
+// From square (6 bits), to square (6 bits) and promotion (3 bits). A move never starts and ends
+// on the same square, so zero stands for no move.
+fn encode_move(chess_move: Option<ChessMove>) -> u16 {
+    let Some(chess_move) = chess_move else {
+        return 0;
+    };
+
+    let square = |c: LocationCoords| (c.y * 8 + c.x) as u16;
+    let promotion = match chess_move.promotion {
+        None => 0,
+        Some(PieceType::Knight) => 1,
+        Some(PieceType::Bishop) => 2,
+        Some(PieceType::Rook) => 3,
+        Some(_) => 4,
+    };
+
+    square(chess_move.from) | square(chess_move.to) << 6 | promotion << 12
+}
+
+fn decode_move(bits: u16) -> Option<ChessMove> {
+    if bits == 0 {
+        return None;
+    }
+
+    let square = |s: u16| LocationCoords {
+        x: (s % 8) as usize,
+        y: (s / 8) as usize,
+    };
+    let promotion = match (bits >> 12) & 0b111 {
+        1 => Some(PieceType::Knight),
+        2 => Some(PieceType::Bishop),
+        3 => Some(PieceType::Rook),
+        4 => Some(PieceType::Queen),
+        _ => None,
+    };
+
+    Some(ChessMove {
+        from: square(bits & 0x3f),
+        to: square((bits >> 6) & 0x3f),
+        promotion,
+    })
+}
@@ -0,0 +1,801 @@
+//! Full-screen terminal interface for playing and reviewing games.
+//!
+//! [`run`] takes over the terminal and draws the board next to the clocks, captured pieces and
+//! the move list. A move is made by selecting a piece, with the cursor keys and Enter or with
+//! the mouse, and then one of its highlighted destinations. Two players can share the keyboard,
+//! or one plays the built-in engine. Earlier positions can be stepped through at any time
+//! without leaving the game. The `rs_chess-tui` binary only reads its options and calls [`run`].
+
+use std::io;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color as TermColor, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+
+use crate::game::Game;
+use crate::location::LocationCoords;
+use crate::movegen::ChessMove;
+use crate::piece::PieceType;
+use crate::player::{Color, Player};
+use crate::position::{Position, STARTING_FEN};
+use crate::render::{checked_king, glyph};
+use crate::repl::{Outcome, automatic_outcome, color_name, new_game};
+use crate::search::{SearchLimits, SearchResult, Searcher, StopFlag};
+use crate::time::Clock;
+use crate::tt::{DEFAULT_SIZE_MB, TranspositionTable};
+
+// How long to wait for input before redrawing the clocks.
+const TICK: Duration = Duration::from_millis(50);
+
+const LIGHT_SQUARE: TermColor = TermColor::Rgb(240, 217, 181);
+const DARK_SQUARE: TermColor = TermColor::Rgb(181, 136, 99);
+const LAST_MOVE_LIGHT: TermColor = TermColor::Rgb(205, 210, 106);
+const LAST_MOVE_DARK: TermColor = TermColor::Rgb(170, 162, 58);
+const SELECTED: TermColor = TermColor::Rgb(106, 135, 77);
+const CURSOR: TermColor = TermColor::Rgb(95, 140, 200);
+const CAPTURE_TARGET: TermColor = TermColor::Rgb(214, 120, 95);
+const CHECK: TermColor = TermColor::Rgb(220, 70, 70);
+const DESTINATION_DOT: TermColor = TermColor::Rgb(70, 70, 70);
+const WHITE_PIECE: TermColor = TermColor::Rgb(255, 255, 255);
+const BLACK_PIECE: TermColor = TermColor::Rgb(0, 0, 0);
+
+const HELP: &str = "arrows enter/click move  esc cancel  u undo  [ ] home end review  f flip  \
+                    n new  e vs engine  q quit";
+
+/// How a TUI session starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TuiOptions {
+    /// Color the engine plays, or `None` for two players at one keyboard.
+    pub engine: Option<Color>,
+    /// Starting time on each clock.
+    pub time: Duration,
+    /// Time added after every move.
+    pub increment: Duration,
+    /// Search threads for the engine.
+    pub threads: usize,
+}
+
+impl Default for TuiOptions {
+    fn default() -> Self {
+        TuiOptions {
+            engine: None,
+            time: Duration::from_secs(5 * 60),
+            increment: Duration::ZERO,
+            threads: 1,
+        }
+    }
+}
+
+// A search for the engine's move, running on its own thread.
+#[derive(Debug)]
+struct EngineSearch {
+    handle: JoinHandle<SearchResult>,
+    stop: StopFlag,
+}
+
+// Everything the screen shows.
+#[derive(Debug)]
+struct App {
+    options: TuiOptions,
+    game: Game,
+    // Ply being reviewed, or `None` to follow the game.
+    view: Option<usize>,
+    cursor: LocationCoords,
+    selected: Option<LocationCoords>,
+    // A pawn move to the last rank waiting for the piece to promote to.
+    promotion: Option<ChessMove>,
+    flipped: bool,
+    // Indexed by color, White first.
+    clocks: [Duration; 2],
+    last_tick: Instant,
+    outcome: Option<Outcome>,
+    table: Arc<TranspositionTable>,
+    engine: Option<EngineSearch>,
+    message: String,
+    // Where the squares were last drawn and how big each one is, for mouse clicks.
+    board_area: Rect,
+    square_size: (u16, u16),
+    quit: bool,
+}
+
+/// Runs the terminal interface until the user quits.
+pub fn run(options: TuiOptions) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    execute!(io::stdout(), EnableMouseCapture)?;
+
+    let result = App::new(options).run(&mut terminal);
+
+    execute!(io::stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn new(options: TuiOptions) -> Self {
+        let start = Position::from_fen(STARTING_FEN).expect("the starting FEN is valid");
+
+        let mut app = App {
+            game: new_game(&start),
+            view: None,
+            cursor: LocationCoords { x: 4, y: 6 },
+            selected: None,
+            promotion: None,
+            flipped: false,
+            clocks: [options.time; 2],
+            last_tick: Instant::now(),
+            outcome: None,
+            table: Arc::new(TranspositionTable::new(DEFAULT_SIZE_MB)),
+            engine: None,
+            message: String::new(),
+            board_area: Rect::default(),
+            square_size: (1, 1),
+            quit: false,
+            options,
+        };
+        app.new_game(app.options.engine);
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(TICK)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key(key),
+                    Event::Mouse(mouse) => self.on_mouse(mouse),
+                    _ => (),
+                }
+            }
+
+            self.tick();
+        }
+
+        self.cancel_engine();
+        Ok(())
+    }
+
+    // The position after the first `ply` moves of the game.
+    fn position_at(&self, ply: usize) -> Position {
+        self.game.moves()[..ply].iter().fold(
+            self.game.start_position().clone(),
+            |position, chess_move| position.make_move(chess_move),
+        )
+    }
+
+    // The position on screen: the reviewed one, or the live one.
+    fn shown_ply(&self) -> usize {
+        self.view.unwrap_or(self.game.moves().len())
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.options.engine == Some(self.game.current_player.color)
+    }
+
+    fn new_game(&mut self, engine: Option<Color>) {
+        self.cancel_engine();
+
+        let start = Position::from_fen(STARTING_FEN).expect("the starting FEN is valid");
+        self.game = new_game(&start);
+        self.view = None;
+        self.selected = None;
+        self.promotion = None;
+        self.clocks = [self.options.time; 2];
+        self.outcome = None;
+        self.table.clear();
+        self.options.engine = engine;
+        self.flipped = engine == Some(Color::White);
+        self.cursor = match self.flipped {
+            false => LocationCoords { x: 4, y: 6 },
+            true => LocationCoords { x: 4, y: 1 },
+        };
+        self.message = match engine {
+            Some(color) => format!("The engine plays {}.", color_name(color)),
+            None => "New game, two players.".to_string(),
+        };
+    }
+
+    // Clocks run from the first move on. The engine's move is picked up once its search ends,
+    // and a new search starts whenever it is the engine's turn.
+    fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last_tick;
+        self.last_tick = now;
+
+        if self.outcome.is_none() && !self.game.moves().is_empty() {
+            let mover = self.game.current_player.color;
+            let clock = &mut self.clocks[mover.index()];
+            *clock = clock.saturating_sub(elapsed);
+
+            if clock.is_zero() {
+                self.flag_fall(mover);
+            }
+        }
+
+        if self
+            .engine
+            .as_ref()
+            .is_some_and(|search| search.handle.is_finished())
+        {
+            let search = self.engine.take().expect("checked above");
+            match search.handle.join() {
+                Ok(result) => match result.best_move {
+                    Some(chess_move) if self.outcome.is_none() => self.play(chess_move),
+                    _ => (),
+                },
+                Err(_) => self.message = "The engine failed.".to_string(),
+            }
+        }
+
+        if self.engine.is_none() && self.outcome.is_none() && self.engine_to_move() {
+            self.start_engine();
+        }
+    }
+
+    fn start_engine(&mut self) {
+        let mover = self.game.current_player.color;
+        let clock = Clock {
+            remaining: self.clocks[mover.index()],
+            increment: self.options.increment,
+            moves_to_go: None,
+        };
+
+        let stop = StopFlag::new();
+        let mut searcher =
+            Searcher::with_table(SearchLimits::clock(clock), stop.clone(), self.table.clone());
+        searcher.set_threads(self.options.threads);
+        let position = self.game.to_position();
+
+        self.engine = Some(EngineSearch {
+            handle: thread::spawn(move || searcher.search(&position)),
+            stop,
+        });
+        self.message = "The engine is thinking.".to_string();
+    }
+
+    fn cancel_engine(&mut self) {
+        if let Some(search) = self.engine.take() {
+            search.stop.stop();
+            let _ = search.handle.join();
+        }
+    }
+
+    fn flag_fall(&mut self, loser: Color) {
+        let winner = loser.opponent();
+        self.outcome = Some(if self.game.to_position().has_mating_material(winner) {
+            Outcome {
+                result: win_for(winner),
+                reason: format!("{} lost on time", color_name(loser)),
+            }
+        } else {
+            Outcome {
+                result: "1/2-1/2",
+                reason: "time out, no mating material".to_string(),
+            }
+        });
+        self.message.clear();
+        self.cancel_engine();
+    }
+
+    fn play(&mut self, chess_move: ChessMove) {
+        let mover = self.game.current_player.color;
+        self.game.make_move(&chess_move);
+        self.selected = None;
+        self.promotion = None;
+
+        let clock = &mut self.clocks[mover.index()];
+        *clock += self.options.increment;
+
+        self.outcome = automatic_outcome(&self.game);
+        self.message.clear();
+    }
+
+    // Takes back the last move, and against the engine also its reply, so it is the human's
+    // turn again. A game lost on time stays lost, as the clocks cannot be wound back.
+    fn undo(&mut self) {
+        if self.clocks.iter().any(|clock| clock.is_zero()) {
+            self.message = "Time ran out: n or e for a new game.".to_string();
+            return;
+        }
+
+        self.cancel_engine();
+
+        let played = self.game.moves().len();
+        if played == 0 {
+            self.message = "Nothing to undo.".to_string();
+            return;
+        }
+
+        // The side that made the move at `ply`.
+        let start = self.game.start_position().side_to_move;
+        let mover = |ply: usize| {
+            if ply.is_multiple_of(2) {
+                start
+            } else {
+                start.opponent()
+            }
+        };
+
+        let mut plies = 1;
+        if let Some(engine) = self.options.engine {
+            while plies < played && mover(played - plies) == engine {
+                plies += 1;
+            }
+        }
+
+        let taken_back = self.game.san_moves()[played - plies..].join(" ");
+        for _ in 0..plies {
+            self.game.take_back();
+        }
+        self.outcome = None;
+        self.selected = None;
+        self.promotion = None;
+        self.view = None;
+        self.message = format!("Took back {}.", taken_back);
+    }
+
+    fn on_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+
+        if let Some(pending) = self.promotion {
+            let piece_type = match key.code {
+                KeyCode::Char('q') | KeyCode::Enter => Some(PieceType::Queen),
+                KeyCode::Char('r') => Some(PieceType::Rook),
+                KeyCode::Char('b') => Some(PieceType::Bishop),
+                KeyCode::Char('n') => Some(PieceType::Knight),
+                KeyCode::Esc => {
+                    self.promotion = None;
+                    self.message.clear();
+                    return;
+                }
+                _ => None,
+            };
+            if let Some(piece_type) = piece_type {
+                self.play(ChessMove {
+                    promotion: Some(piece_type),
+                    ..pending
+                });
+            }
+            return;
+        }
+
+        // Screen directions, whichever way up the board is.
+        let (up, right): (i32, i32) = if self.flipped { (1, -1) } else { (-1, 1) };
+
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, up),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, -up),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-right, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(right, 0),
+            KeyCode::Enter | KeyCode::Char(' ') => self.activate(self.cursor),
+            KeyCode::Esc => {
+                self.selected = None;
+                self.view = None;
+            }
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('[') | KeyCode::PageUp => self.step(-1),
+            KeyCode::Char(']') | KeyCode::PageDown => self.step(1),
+            KeyCode::Home => self.view = Some(0),
+            KeyCode::End => self.view = None,
+            KeyCode::Char('f') => self.flipped = !self.flipped,
+            KeyCode::Char('n') => self.new_game(None),
+            KeyCode::Char('e') => {
+                // Against the engine, the human keeps the side at the bottom of the board.
+                let engine = if self.flipped {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+                self.new_game(Some(engine));
+            }
+            _ => (),
+        }
+    }
+
+    fn on_mouse(&mut self, mouse: MouseEvent) {
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(square) = self.square_at(mouse.column, mouse.row) {
+                    self.cursor = square;
+                    self.activate(square);
+                }
+            }
+            MouseEventKind::ScrollUp => self.step(-1),
+            MouseEventKind::ScrollDown => self.step(1),
+            _ => (),
+        }
+    }
+
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let x = (self.cursor.x as i32 + dx).clamp(0, 7);
+        let y = (self.cursor.y as i32 + dy).clamp(0, 7);
+        self.cursor = LocationCoords {
+            x: x as usize,
+            y: y as usize,
+        };
+    }
+
+    // Moves through the game's history; stepping past the last move follows the game again.
+    fn step(&mut self, delta: i32) {
+        let last = self.game.moves().len();
+        let ply = (self.shown_ply() as i32 + delta).clamp(0, last as i32) as usize;
+        self.view = (ply < last).then_some(ply);
+        self.selected = None;
+    }
+
+    // Selects a piece of the side to move, or plays the selected piece to `square`.
+    fn activate(&mut self, square: LocationCoords) {
+        if self.view.is_some() {
+            self.message = "Press End to play on.".to_string();
+            return;
+        }
+        if self.outcome.is_some() {
+            self.message = "Game over: n or e for a new one.".to_string();
+            return;
+        }
+        if self.engine_to_move() {
+            self.message = "The engine is thinking.".to_string();
+            return;
+        }
+
+        let position = &self.game.to_position();
+        let own_piece = matches!(
+            position.piece_at(&square),
+            Some((color, _)) if color == position.side_to_move
+        );
+
+        if let Some(from) = self.selected {
+            let candidates: Vec<ChessMove> = position
+                .legal_moves()
+                .into_iter()
+                .filter(|m| m.from == from && m.to == square)
+                .collect();
+
+            match candidates.as_slice() {
+                [] if square == from => self.selected = None,
+                [] if own_piece => self.selected = Some(square),
+                [] => {
+                    self.selected = None;
+                    self.message = "Not a legal move.".to_string();
+                }
+                [chess_move] => self.play(*chess_move),
+                [first, ..] => {
+                    self.promotion = Some(*first);
+                    self.message = "Promote to q, r, b or n?".to_string();
+                }
+            }
+        } else if own_piece {
+            self.selected = Some(square);
+            self.message.clear();
+        }
+    }
+
+    fn square_at(&self, column: u16, row: u16) -> Option<LocationCoords> {
+        let area = self.board_area;
+        if !area.contains((column, row).into()) {
+            return None;
+        }
+
+        let (width, height) = self.square_size;
+        let col = ((column - area.x) / width) as usize;
+        let row = ((row - area.y) / height) as usize;
+        Some(self.square_on_screen(col, row))
+    }
+
+    // Board coordinates of the square drawn in screen column `col` and row `row`.
+    fn square_on_screen(&self, col: usize, row: usize) -> LocationCoords {
+        match self.flipped {
+            false => LocationCoords { x: col, y: row },
+            true => LocationCoords {
+                x: 7 - col,
+                y: 7 - row,
+            },
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        let [main, help] =
+            Layout::vertical([Constraint::Min(10), Constraint::Length(1)]).areas(area);
+
+        // Squares are about twice as wide as tall, so they look square in most fonts.
+        let height = ((main.height.saturating_sub(1)) / 8)
+            .min(main.width.saturating_sub(32) / 8 / 2)
+            .max(1);
+        let width = height * 2 + 1;
+        let board_width = 2 + width * 8 + 1;
+
+        let [board, panel] =
+            Layout::horizontal([Constraint::Length(board_width), Constraint::Min(28)]).areas(main);
+
+        self.square_size = (width, height);
+        self.board_area = Rect {
+            x: board.x + 2,
+            y: board.y,
+            width: width * 8,
+            height: height * 8,
+        }
+        .intersection(board);
+
+        self.draw_board(frame.buffer_mut());
+        self.draw_panel(frame, panel);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+            help,
+        );
+    }
+
+    fn draw_board(&self, buf: &mut Buffer) {
+        let ply = self.shown_ply();
+        let position = &self.position_at(ply);
+        let last_move = ply.checked_sub(1).map(|i| self.game.moves()[i]);
+        let live = self.view.is_none();
+        let (width, height) = self.square_size;
+        let area = self.board_area;
+
+        let destinations: Vec<ChessMove> = match self.selected {
+            Some(from) if live => position
+                .legal_moves()
+                .into_iter()
+                .filter(|m| m.from == from)
+                .collect(),
+            _ => vec![],
+        };
+        let checked_king = checked_king(position);
+
+        for row in 0..8 {
+            for col in 0..8 {
+                let square = self.square_on_screen(col, row);
+                let light = (square.x + square.y).is_multiple_of(2);
+                let piece = position.piece_at(&square);
+                let is_destination = destinations.iter().any(|m| m.to == square);
+
+                let mut background = if light { LIGHT_SQUARE } else { DARK_SQUARE };
+                if last_move.is_some_and(|m| m.from == square || m.to == square) {
+                    background = if light {
+                        LAST_MOVE_LIGHT
+                    } else {
+                        LAST_MOVE_DARK
+                    };
+                }
+                if checked_king == Some(square) {
+                    background = CHECK;
+                }
+                if is_destination && piece.is_some() {
+                    background = CAPTURE_TARGET;
+                }
+                if self.selected == Some(square) && live {
+                    background = SELECTED;
+                }
+                if self.cursor == square && live {
+                    background = CURSOR;
+                }
+
+                let rect = Rect {
+                    x: area.x + col as u16 * width,
+                    y: area.y + row as u16 * height,
+                    width,
+                    height,
+                }
+                .intersection(area);
+                buf.set_style(rect, Style::default().bg(background));
+
+                let (symbol, foreground) = match piece {
+                    Some((color, piece_type)) => (
+                        // Solid glyphs for both sides; color tells them apart.
+                        glyph(Color::Black, piece_type),
+                        if color == Color::White {
+                            WHITE_PIECE
+                        } else {
+                            BLACK_PIECE
+                        },
+                    ),
+                    None if is_destination => ('•', DESTINATION_DOT),
+                    None => continue,
+                };
+                let (x, y) = (rect.x + width / 2, rect.y + height / 2);
+                if rect.contains((x, y).into()) {
+                    buf.set_string(
+                        x,
+                        y,
+                        symbol.to_string(),
+                        Style::default()
+                            .fg(foreground)
+                            .bg(background)
+                            .add_modifier(Modifier::BOLD),
+                    );
+                }
+            }
+        }
+
+        // Rank labels on the left, file labels underneath.
+        for row in 0..8u16 {
+            let rank = self.square_on_screen(0, row as usize).y;
+            let y = area.y + row * height + height / 2;
+            if y < area.bottom() {
+                buf.set_string(area.x - 2, y, rank_label(rank), Style::default());
+            }
+        }
+        let files_y = area.bottom();
+        if files_y < buf.area.bottom() {
+            for col in 0..8u16 {
+                let file = self.square_on_screen(col as usize, 0).x;
+                let x = area.x + col * width + width / 2;
+                if x < area.right() {
+                    buf.set_string(x, files_y, file_label(file), Style::default());
+                }
+            }
+        }
+    }
+
+    fn draw_panel(&self, frame: &mut Frame, area: Rect) {
+        let [clocks, status, captured, moves] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(4),
+            Constraint::Length(4),
+            Constraint::Min(3),
+        ])
+        .areas(area);
+
+        let mover = self.game.current_player.color;
+        let clock_line = |color: Color| {
+            let running = self.outcome.is_none() && mover == color && !self.game.moves().is_empty();
+            let mut style = Style::default();
+            if running {
+                style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+            }
+            let who = match self.options.engine {
+                Some(engine) if engine == color => format!("{} (engine)", color_name(color)),
+                _ => color_name(color).to_string(),
+            };
+            Line::from(vec![
+                Span::raw(format!("{:<16}", who)),
+                Span::styled(format_clock(self.clocks[color.index()]), style),
+            ])
+        };
+        frame.render_widget(
+            Paragraph::new(vec![clock_line(Color::White), clock_line(Color::Black)])
+                .block(Block::bordered().title(" Clocks ")),
+            clocks,
+        );
+
+        let mut status_lines = vec![Line::from(match (&self.outcome, self.view) {
+            (_, Some(ply)) => format!("Reviewing ply {} of {}", ply, self.game.moves().len()),
+            (Some(outcome), None) => format!("Game over: {}", outcome.result),
+            (None, None) => format!("{} to move", color_name(mover)),
+        })];
+        status_lines.push(Line::from(match &self.outcome {
+            Some(outcome) if self.message.is_empty() => outcome.reason.clone(),
+            _ => self.message.clone(),
+        }));
+        frame.render_widget(
+            Paragraph::new(status_lines).block(Block::bordered().title(" Status ")),
+            status,
+        );
+
+        // Each side's captures are the other side's dead pieces.
+        let taken_by = |player: &Player| -> String {
+            let mut pieces: Vec<PieceType> = player
+                .dead_pieces
+                .borrow()
+                .iter()
+                .map(|p| p.piece_type)
+                .collect();
+            pieces.sort_by_key(|p| std::cmp::Reverse(p.points()));
+            pieces
+                .into_iter()
+                .map(|piece_type| glyph(Color::Black, piece_type))
+                .collect::<String>()
+        };
+        let (white, black) = match self.game.player1.color {
+            Color::White => (&self.game.player1, &self.game.player2),
+            Color::Black => (&self.game.player2, &self.game.player1),
+        };
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from(format!("White: {}", taken_by(black))),
+                Line::from(format!("Black: {}", taken_by(white))),
+            ])
+            .block(Block::bordered().title(" Captured ")),
+            captured,
+        );
+
+        self.draw_moves(frame, moves);
+    }
+
+    fn draw_moves(&self, frame: &mut Frame, area: Rect) {
+        let start = self.game.start_position();
+        let shown = self.shown_ply();
+        let black_first = start.side_to_move == Color::Black;
+
+        let mut lines: Vec<Line> = vec![];
+        let mut current_line = 0;
+        let mut number = start.fullmove_number;
+        let mut spans: Vec<Span> = vec![];
+
+        for (ply, san) in self.game.san_moves().iter().enumerate() {
+            let white_move = (ply % 2 == 0) != black_first;
+            if white_move || ply == 0 {
+                spans.push(Span::raw(format!("{:>3}. ", number)));
+            }
+            if !white_move && ply == 0 {
+                spans.push(Span::raw(format!("{:<7}", "...")));
+            }
+
+            let style = if ply + 1 == shown {
+                current_line = lines.len();
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            spans.push(Span::styled(format!("{:<7}", san), style));
+
+            if !white_move {
+                lines.push(Line::from(std::mem::take(&mut spans)));
+                number += 1;
+            }
+        }
+        if !spans.is_empty() {
+            lines.push(Line::from(spans));
+        }
+        if let Some(outcome) = &self.outcome {
+            lines.push(Line::from(format!("     {}", outcome.result)));
+        }
+
+        // Keep the current move in view.
+        let visible = area.height.saturating_sub(2) as usize;
+        let scroll = match self.view {
+            Some(_) => current_line.saturating_sub(visible.saturating_sub(1)),
+            None => lines.len().saturating_sub(visible),
+        };
+
+        frame.render_widget(
+            Paragraph::new(lines)
+                .scroll((scroll as u16, 0))
+                .block(Block::bordered().title(" Moves ")),
+            area,
+        );
+    }
+}
+
+fn win_for(color: Color) -> &'static str {
+    match color {
+        Color::White => "1-0",
+        Color::Black => "0-1",
+    }
+}
+
+// `y = 0` is rank 8.
+fn rank_label(y: usize) -> &'static str {
+    ["8", "7", "6", "5", "4", "3", "2", "1"][y]
+}
+
+fn file_label(x: usize) -> &'static str {
+    ["a", "b", "c", "d", "e", "f", "g", "h"][x]
+}
+
+// m:ss, with tenths in the last ten seconds.
+fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
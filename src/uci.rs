@@ -0,0 +1,406 @@
+//! Universal Chess Interface front end for the search.
+//!
+//! [`Uci`] handles one command line at a time and writes replies to any `Write`. Searches run on
+//! a background thread so `stop`, `isready` and `quit` are answered while the engine thinks.
+//! [`run`] drives it from a reader, which is all the `rs_chess-uci` binary does.
+
+use std::io::{BufRead, Write};
+use std::str::SplitWhitespace;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::book::Book;
+use crate::movegen::ChessMove;
+use crate::player::Color;
+use crate::position::{Position, STARTING_FEN};
+use crate::search::{SearchLimits, SearchResult, Searcher, StopFlag};
+use crate::syzygy::Tablebases;
+use crate::time::Clock;
+use crate::tt::{DEFAULT_SIZE_MB, TranspositionTable};
+
+pub(crate) const ENGINE_NAME: &str = concat!("rs_chess ", env!("CARGO_PKG_VERSION"));
+const ENGINE_AUTHOR: &str = "the rs_chess developers";
+const MAX_HASH_MB: usize = 4096;
+pub(crate) const MAX_THREADS: usize = 256;
+
+/// UCI engine state: the current position, engine options and the running search, if any.
+#[derive(Debug)]
+pub struct Uci<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    position: Position,
+    // Hashes of the positions the last `position` command went through, ending with `position`,
+    // so the search can tell repetitions.
+    hashes: Vec<u64>,
+    table: Arc<TranspositionTable>,
+    book: Option<Book>,
+    tablebases: Option<Arc<Tablebases>>,
+    threads: usize,
+    stop: StopFlag,
+    search: Option<JoinHandle<()>>,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(out: W) -> Self {
+        Uci {
+            out: Arc::new(Mutex::new(out)),
+            position: Position::default(),
+            hashes: vec![],
+            table: Arc::new(TranspositionTable::new(DEFAULT_SIZE_MB)),
+            book: None,
+            tablebases: None,
+            threads: 1,
+            stop: StopFlag::new(),
+            search: None,
+        }
+    }
+
+    /// Handles one line of input. Returns `false` once the engine should exit.
+    ///
+    /// Unknown commands and malformed arguments are reported with `info string` and otherwise
+    /// ignored, as the protocol asks.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("uci") => self.identify(),
+            Some("isready") => send(&self.out, "readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.table.clear();
+                self.position = Position::default();
+                self.hashes.clear();
+            }
+            Some("setoption") => self.set_option(tokens),
+            Some("position") => {
+                self.stop_search();
+                match parse_position(tokens) {
+                    Ok((position, hashes)) => {
+                        self.position = position;
+                        self.hashes = hashes;
+                    }
+                    Err(message) => send(&self.out, &format!("info string {}", message)),
+                }
+            }
+            Some("go") => self.go(tokens),
+            Some("stop") | Some("ponderhit") => self.stop_search(),
+            Some("quit") => {
+                self.stop_search();
+                return false;
+            }
+            Some("debug") | Some("register") | None => (),
+            Some(command) => send(
+                &self.out,
+                &format!("info string unknown command {}", command),
+            ),
+        }
+
+        true
+    }
+
+    fn identify(&self) {
+        send(&self.out, &format!("id name {}", ENGINE_NAME));
+        send(&self.out, &format!("id author {}", ENGINE_AUTHOR));
+        send(
+            &self.out,
+            &format!(
+                "option name Hash type spin default {} min 1 max {}",
+                DEFAULT_SIZE_MB, MAX_HASH_MB
+            ),
+        );
+        send(
+            &self.out,
+            &format!(
+                "option name Threads type spin default 1 min 1 max {}",
+                MAX_THREADS
+            ),
+        );
+        send(
+            &self.out,
+            "option name BookFile type string default <empty>",
+        );
+        send(
+            &self.out,
+            "option name SyzygyPath type string default <empty>",
+        );
+        send(&self.out, "uciok");
+    }
+
+    // setoption name <name> [value <value>]. Option names are case insensitive.
+    fn set_option(&mut self, tokens: SplitWhitespace) {
+        let tokens: Vec<&str> = tokens.collect();
+        let value_at = tokens.iter().position(|t| *t == "value");
+        let name = tokens[..value_at.unwrap_or(tokens.len())]
+            .iter()
+            .skip_while(|t| **t == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = value_at.map(|at| tokens[at + 1..].join(" "));
+
+        let parsed = value.as_deref().and_then(|v| v.parse::<usize>().ok());
+
+        if name.eq_ignore_ascii_case("bookfile") {
+            self.book = None;
+            match value.as_deref() {
+                None | Some("") | Some("<empty>") => (),
+                Some(path) => match Book::open(path) {
+                    Ok(book) => self.book = Some(book),
+                    Err(error) => send(
+                        &self.out,
+                        &format!("info string cannot open book {}: {}", path, error),
+                    ),
+                },
+            }
+            return;
+        }
+
+        if name.eq_ignore_ascii_case("syzygypath") {
+            self.stop_search();
+            self.tablebases = None;
+            match value.as_deref() {
+                None | Some("") | Some("<empty>") => (),
+                Some(paths) => self.tablebases = self.open_tablebases(paths),
+            }
+            return;
+        }
+
+        match (name.to_ascii_lowercase().as_str(), parsed) {
+            ("hash", Some(megabytes)) => {
+                self.stop_search();
+                self.table = Arc::new(TranspositionTable::new(megabytes.clamp(1, MAX_HASH_MB)));
+            }
+            ("threads", Some(threads)) if (1..=MAX_THREADS).contains(&threads) => {
+                self.stop_search();
+                self.threads = threads;
+            }
+            _ => send(
+                &self.out,
+                &format!("info string cannot set option {} to {:?}", name, value),
+            ),
+        }
+    }
+
+    // Directories are separated as in PATH. Unreadable ones are reported and skipped.
+    fn open_tablebases(&self, paths: &str) -> Option<Arc<Tablebases>> {
+        let mut tablebases = Tablebases::new();
+
+        for dir in std::env::split_paths(paths) {
+            if let Err(error) = tablebases.add_directory(&dir) {
+                send(
+                    &self.out,
+                    &format!(
+                        "info string cannot read tablebases in {}: {}",
+                        dir.display(),
+                        error
+                    ),
+                );
+            }
+        }
+
+        send(
+            &self.out,
+            &format!(
+                "info string found {}-piece tablebases",
+                tablebases.max_pieces()
+            ),
+        );
+
+        (tablebases.max_pieces() > 0).then(|| Arc::new(tablebases))
+    }
+
+    fn go(&mut self, tokens: SplitWhitespace) {
+        self.stop_search();
+        self.stop.reset();
+
+        let (limits, wait_for_stop) = parse_go(tokens, &self.position);
+
+        // Book moves are played straight away, unless the GUI wants to analyse.
+        if !wait_for_stop
+            && let Some(book) = &self.book
+            && let Some(book_move) = book.weighted_move(&self.position, fastrand::u64(..))
+        {
+            send(&self.out, &format!("bestmove {}", book_move));
+            return;
+        }
+
+        let mut searcher = Searcher::with_table(limits, self.stop.clone(), self.table.clone());
+        searcher.set_tablebases(self.tablebases.clone());
+        searcher.set_threads(self.threads);
+        searcher.set_game_hashes(self.hashes.clone());
+        let position = self.position.clone();
+        let table = self.table.clone();
+        let stop = self.stop.clone();
+        let out = self.out.clone();
+
+        self.search = Some(thread::spawn(move || {
+            let result = searcher.search_with(&position, |iteration| {
+                send(&out, &info_line(iteration, table.hashfull()));
+            });
+
+            // `go infinite` and `go ponder` must not answer before being told to stop.
+            while wait_for_stop && !stop.is_stopped() {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            match result.best_move {
+                Some(best_move) => send(&out, &format!("bestmove {}", best_move)),
+                None => send(&out, "bestmove 0000"),
+            }
+        }));
+    }
+
+    // Stops the running search, if any, and waits for its `bestmove`.
+    fn stop_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.stop.stop();
+            let _ = search.join();
+        }
+    }
+}
+
+/// Reads UCI commands from `input` until `quit` or end of input, writing replies to `output`.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut uci = Uci::new(output);
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !uci.handle(&line) {
+            return;
+        }
+    }
+
+    uci.handle("quit");
+}
+
+// Writes one line. A closed output means the GUI is gone, so write errors are dropped.
+pub(crate) fn send<W: Write>(out: &Mutex<W>, line: &str) {
+    if let Ok(mut out) = out.lock() {
+        let _ = writeln!(out, "{}", line);
+        let _ = out.flush();
+    }
+}
+
+// position [startpos | fen <fen>] [moves <move>...]
+// Returns the position reached and the hashes of every position on the way.
+fn parse_position(mut tokens: SplitWhitespace) -> Result<(Position, Vec<u64>), String> {
+    let position = match tokens.next() {
+        Some("startpos") => Position::from_fen(STARTING_FEN).map_err(|e| e.to_string())?,
+        Some("fen") => {
+            let fen: Vec<&str> = tokens.by_ref().take_while(|t| *t != "moves").collect();
+            let position = Position::from_fen(&fen.join(" ")).map_err(|e| e.to_string())?;
+            position
+                .validate()
+                .map_err(|violations| format!("illegal position: {}", violations[0]))?;
+
+            // take_while consumed "moves" already.
+            return apply_moves(position, tokens);
+        }
+        other => return Err(format!("expected startpos or fen, got {:?}", other)),
+    };
+
+    match tokens.next() {
+        Some("moves") | None => apply_moves(position, tokens),
+        Some(other) => Err(format!("expected moves, got {}", other)),
+    }
+}
+
+fn apply_moves(
+    mut position: Position,
+    tokens: SplitWhitespace,
+) -> Result<(Position, Vec<u64>), String> {
+    let mut hashes = vec![position.hash()];
+    for text in tokens {
+        let chess_move = ChessMove::from_uci(text)
+            .filter(|m| position.is_legal(m))
+            .ok_or_else(|| format!("illegal move {}", text))?;
+        position = position.make_move(&chess_move);
+        hashes.push(position.hash());
+    }
+
+    Ok((position, hashes))
+}
+
+// go [searchmoves <move>...] [ponder] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>]
+//    [movestogo <n>] [depth <n>] [nodes <n>] [mate <n>] [movetime <ms>] [infinite]
+// Returns the limits and whether the answer has to wait for `stop`.
+fn parse_go(tokens: SplitWhitespace, position: &Position) -> (SearchLimits, bool) {
+    let mut limits = SearchLimits::default();
+    let mut wait_for_stop = false;
+    let mut clocks: [Clock; 2] = [Clock::default(); 2];
+    let mut has_clock = false;
+
+    let tokens: Vec<&str> = tokens.collect();
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let number = tokens.get(index + 1).and_then(|t| t.parse::<u64>().ok());
+        let millis = Duration::from_millis(number.unwrap_or(0));
+
+        match tokens[index] {
+            "infinite" | "ponder" => wait_for_stop = true,
+            "searchmoves" => {
+                while let Some(chess_move) =
+                    tokens.get(index + 1).and_then(|t| ChessMove::from_uci(t))
+                {
+                    limits.search_moves.push(chess_move);
+                    index += 1;
+                }
+            }
+            "wtime" => {
+                clocks[0].remaining = millis;
+                has_clock = true;
+            }
+            "btime" => {
+                clocks[1].remaining = millis;
+                has_clock = true;
+            }
+            "winc" => clocks[0].increment = millis,
+            "binc" => clocks[1].increment = millis,
+            "movestogo" => {
+                let moves_to_go = number.map(|n| n as u32);
+                clocks[0].moves_to_go = moves_to_go;
+                clocks[1].moves_to_go = moves_to_go;
+            }
+            "depth" => limits.depth = number.map(|n| n as u32),
+            "nodes" => limits.nodes = number,
+            "mate" => limits.mate = number.map(|n| n as u32),
+            "movetime" => limits.movetime = number.map(Duration::from_millis),
+            _ => (),
+        }
+
+        index += 1;
+    }
+
+    if has_clock && !wait_for_stop {
+        limits.clock = Some(match position.side_to_move {
+            Color::White => clocks[0],
+            Color::Black => clocks[1],
+        });
+    }
+
+    (limits, wait_for_stop)
+}
+
+fn info_line(result: &SearchResult, hashfull: u32) -> String {
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} hashfull {} tbhits {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.nps(),
+        result.elapsed.as_millis(),
+        hashfull,
+        result.tb_hits,
+        pv.join(" ")
+    )
+}
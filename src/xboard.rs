@@ -0,0 +1,462 @@
+//! Chess Engine Communication Protocol (xboard / WinBoard) front end.
+//!
+//! [`Xboard`] keeps the game in a [`Game`] and thinks with the same [`Searcher`] as the UCI
+//! front end, on a background thread so `?`, `ping` and clock updates are handled while the
+//! engine thinks. The engine binary switches to this mode when its first command is `xboard`.
+
+use std::io::{BufRead, Write};
+use std::str::SplitWhitespace;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::game::{Game, GameStatus, position_status};
+use crate::movegen::ChessMove;
+use crate::player::Color;
+use crate::position::{Position, STARTING_FEN};
+use crate::repl::{new_game, status_outcome};
+use crate::search::{SearchLimits, SearchResult, Searcher, StopFlag};
+use crate::syzygy::Tablebases;
+use crate::time::Clock;
+use crate::tt::{DEFAULT_SIZE_MB, TranspositionTable};
+use crate::uci::{ENGINE_NAME, MAX_THREADS, send};
+
+// Scores at or beyond this distance from zero are mates in xboard thinking output.
+const XBOARD_MATE_SCORE: i32 = 100_000;
+
+/// Time control set with `level` or `st`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeControl {
+    /// `moves` moves per session (0 for the whole game), plus `increment` after every move.
+    Conventional { moves: u32, increment: Duration },
+    /// A fixed time for every move.
+    PerMove(Duration),
+}
+
+// A search running on its own thread. Its move, and the result if the move ends the game, are
+// printed by the thread itself unless the search was cancelled, and played on the game once the
+// thread is joined.
+#[derive(Debug)]
+struct PendingSearch {
+    handle: JoinHandle<Option<ChessMove>>,
+    cancelled: Arc<AtomicBool>,
+    // Set as the move is printed, as the interface may answer it before the thread ends.
+    sent: Arc<AtomicBool>,
+}
+
+/// xboard engine state: the game, who the engine plays, time control and the running search.
+#[derive(Debug)]
+pub struct Xboard<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    game: Game,
+    // `None` in force mode.
+    engine_color: Option<Color>,
+    game_over: bool,
+    post: bool,
+    time_control: TimeControl,
+    depth: Option<u32>,
+    engine_time: Duration,
+    table: Arc<TranspositionTable>,
+    tablebases: Option<Arc<Tablebases>>,
+    threads: usize,
+    stop: StopFlag,
+    search: Option<PendingSearch>,
+}
+
+impl<W: Write + Send + 'static> Xboard<W> {
+    pub fn new(out: W) -> Self {
+        let start = Position::from_fen(STARTING_FEN).expect("the starting FEN is valid");
+
+        Xboard {
+            out: Arc::new(Mutex::new(out)),
+            game: new_game(&start),
+            engine_color: Some(Color::Black),
+            game_over: false,
+            post: false,
+            time_control: TimeControl::Conventional {
+                moves: 40,
+                increment: Duration::ZERO,
+            },
+            depth: None,
+            engine_time: Duration::from_secs(300),
+            table: Arc::new(TranspositionTable::new(DEFAULT_SIZE_MB)),
+            tablebases: None,
+            threads: 1,
+            stop: StopFlag::new(),
+            search: None,
+        }
+    }
+
+    /// The game as the engine sees it.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Handles one line of input. Returns `false` once the engine should exit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        self.collect_search();
+
+        let mut tokens = line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return true;
+        };
+
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "ics" | "otim" | "white" | "black" => (),
+            "protover" => self.features(),
+            "new" => {
+                self.cancel_search();
+                self.table.clear();
+                self.set_start(Position::from_fen(STARTING_FEN).expect("the start FEN is valid"));
+                self.engine_color = Some(Color::Black);
+                self.depth = None;
+            }
+            "setboard" => {
+                self.cancel_search();
+                let fen = tokens.collect::<Vec<_>>().join(" ");
+                match Position::from_fen(&fen) {
+                    Ok(position) if position.validate().is_ok() => self.set_start(position),
+                    _ => send(&self.out, "tellusererror Illegal position"),
+                }
+            }
+            "force" => {
+                self.cancel_search();
+                self.engine_color = None;
+            }
+            "go" => {
+                self.engine_color = Some(self.side_to_move());
+                self.think();
+            }
+            "usermove" => match tokens.next() {
+                Some(text) => self.user_move(text),
+                None => send(&self.out, "Error (missing move): usermove"),
+            },
+            "?" => {
+                if self.search.is_some() {
+                    self.stop.stop();
+                    self.finish_search();
+                }
+            }
+            "ping" => send(&self.out, &format!("pong {}", tokens.next().unwrap_or(""))),
+            "level" => self.level(tokens),
+            "st" => match tokens.next().and_then(|t| t.parse::<f64>().ok()) {
+                Some(seconds) if seconds > 0.0 => {
+                    self.time_control = TimeControl::PerMove(Duration::from_secs_f64(seconds))
+                }
+                _ => send(&self.out, &format!("Error (bad time): {}", line)),
+            },
+            "sd" => match tokens.next().and_then(|t| t.parse::<u32>().ok()) {
+                Some(depth) => self.depth = Some(depth.max(1)),
+                None => send(&self.out, &format!("Error (bad depth): {}", line)),
+            },
+            "time" => {
+                if let Some(centis) = tokens.next().and_then(|t| t.parse::<u64>().ok()) {
+                    self.engine_time = Duration::from_millis(centis * 10);
+                }
+            }
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "cores" => match tokens.next().and_then(|t| t.parse::<usize>().ok()) {
+                Some(cores) => self.threads = cores.clamp(1, MAX_THREADS),
+                None => send(&self.out, &format!("Error (bad core count): {}", line)),
+            },
+            "egtpath" => match (tokens.next(), tokens.collect::<Vec<_>>().join(" ")) {
+                (Some("syzygy"), paths) => self.set_tablebases(&paths),
+                _ => send(
+                    &self.out,
+                    &format!("Error (unsupported tablebases): {}", line),
+                ),
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "result" => {
+                self.cancel_search();
+                self.game_over = true;
+                self.engine_color = None;
+            }
+            "quit" => {
+                self.cancel_search();
+                return false;
+            }
+            _ => {
+                // Protocol version 1 interfaces send moves without `usermove`.
+                if ChessMove::from_uci(command).is_some() {
+                    self.user_move(command);
+                } else {
+                    send(&self.out, &format!("Error (unknown command): {}", command));
+                }
+            }
+        }
+
+        true
+    }
+
+    fn features(&self) {
+        send(
+            &self.out,
+            &format!(
+                "feature ping=1 setboard=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 \
+                 reuse=1 analyze=0 colors=0 san=0 smp=1 egt=\"syzygy\" myname=\"{}\" done=1",
+                ENGINE_NAME
+            ),
+        );
+    }
+
+    // egtpath syzygy PATHS, with directories separated as in PATH.
+    fn set_tablebases(&mut self, paths: &str) {
+        self.cancel_search();
+        let mut tablebases = Tablebases::new();
+
+        for dir in std::env::split_paths(paths) {
+            if let Err(error) = tablebases.add_directory(&dir) {
+                send(
+                    &self.out,
+                    &format!(
+                        "telluser cannot read tablebases in {}: {}",
+                        dir.display(),
+                        error
+                    ),
+                );
+            }
+        }
+
+        self.tablebases = (tablebases.max_pieces() > 0).then(|| Arc::new(tablebases));
+    }
+
+    // level MPS BASE INC, where BASE is minutes or minutes:seconds and INC is seconds.
+    fn level(&mut self, mut tokens: SplitWhitespace) {
+        let moves = tokens.next().and_then(|t| t.parse::<u32>().ok());
+        let base = tokens.next().and_then(parse_minutes);
+        let increment = tokens.next().and_then(|t| t.parse::<f64>().ok());
+
+        match (moves, base, increment) {
+            (Some(moves), Some(base), Some(increment)) if increment >= 0.0 => {
+                self.time_control = TimeControl::Conventional {
+                    moves,
+                    increment: Duration::from_secs_f64(increment),
+                };
+                self.engine_time = base;
+            }
+            _ => send(&self.out, "Error (bad time control): level"),
+        }
+    }
+
+    fn user_move(&mut self, text: &str) {
+        if self.search.is_some() || self.game_over || self.game.is_over() {
+            send(&self.out, &format!("Illegal move: {}", text));
+            return;
+        }
+
+        let legal = ChessMove::from_uci(text).filter(|m| self.game.legal_moves().contains(m));
+        let Some(chess_move) = legal else {
+            send(&self.out, &format!("Illegal move: {}", text));
+            return;
+        };
+
+        if let Some(result) = self.play(chess_move) {
+            send(&self.out, &result);
+        }
+        self.think();
+    }
+
+    // Plays a move on the game. Returns the result if it ended the game.
+    fn play(&mut self, chess_move: ChessMove) -> Option<String> {
+        self.game.make_move(&chess_move);
+
+        let repetitions = self.game.repetitions();
+        let result = game_result(self.game.status(), &self.game.to_position(), repetitions);
+        self.game_over = result.is_some();
+        result
+    }
+
+    // Starts a search if it is the engine's turn.
+    fn think(&mut self) {
+        if self.game_over || self.search.is_some() || self.engine_color != Some(self.side_to_move())
+        {
+            return;
+        }
+
+        self.stop.reset();
+
+        let position = self.game.to_position();
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            ..Default::default()
+        };
+        match self.time_control {
+            TimeControl::PerMove(movetime) => limits.movetime = Some(movetime),
+            TimeControl::Conventional { moves, increment } => {
+                let played = position.fullmove_number.saturating_sub(1);
+                limits.clock = Some(Clock {
+                    remaining: self.engine_time,
+                    increment,
+                    moves_to_go: (moves > 0).then(|| moves - played % moves),
+                });
+            }
+        }
+
+        let mut searcher = Searcher::with_table(limits, self.stop.clone(), self.table.clone());
+        searcher.set_tablebases(self.tablebases.clone());
+        searcher.set_threads(self.threads);
+        searcher.set_game_hashes(self.game.position_hashes().to_vec());
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = cancelled.clone();
+        let sent = Arc::new(AtomicBool::new(false));
+        let thread_sent = sent.clone();
+        let hashes = self.game.position_hashes().to_vec();
+        let out = self.out.clone();
+        let post = self.post;
+
+        let handle = thread::spawn(move || {
+            let result = searcher.search_with(&position, |iteration| {
+                if post {
+                    send(&out, &thinking_line(iteration));
+                }
+            });
+
+            // Checked under the output lock, so a cancelled search never prints its move.
+            let Ok(mut writer) = out.lock() else {
+                return None;
+            };
+            if thread_cancelled.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            let best_move = result.best_move?;
+            // Set before the move is written: the interface can only answer it after that.
+            thread_sent.store(true, Ordering::SeqCst);
+            let _ = writeln!(writer, "move {}", best_move);
+
+            let next = position.make_move(&best_move);
+            let repetitions = 1 + hashes.iter().filter(|&&hash| hash == next.hash()).count();
+            let status = position_status(&next, repetitions);
+            if let Some(result) = game_result(status, &next, repetitions) {
+                let _ = writeln!(writer, "{}", result);
+            }
+            let _ = writer.flush();
+
+            Some(best_move)
+        });
+
+        self.search = Some(PendingSearch {
+            handle,
+            cancelled,
+            sent,
+        });
+    }
+
+    // Plays the engine's move once its search thread has printed it.
+    fn collect_search(&mut self) {
+        if self
+            .search
+            .as_ref()
+            .is_some_and(|search| search.sent.load(Ordering::SeqCst) || search.handle.is_finished())
+        {
+            self.finish_search();
+        }
+    }
+
+    // Waits for the running search and plays its move. The thread already sent any result.
+    fn finish_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+
+        if let Ok(Some(chess_move)) = search.handle.join() {
+            self.play(chess_move);
+        }
+    }
+
+    // Stops the running search without printing or playing its move.
+    fn cancel_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+
+        // Taking the output lock orders the flag against the thread's check.
+        if let Ok(_writer) = self.out.lock() {
+            search.cancelled.store(true, Ordering::SeqCst);
+        }
+        self.stop.stop();
+        let _ = search.handle.join();
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        self.cancel_search();
+
+        for _ in 0..plies {
+            self.game.take_back();
+        }
+        self.game_over = self.game.is_over();
+    }
+
+    fn set_start(&mut self, position: Position) {
+        self.game = new_game(&position);
+        self.game_over = self.game.is_over();
+    }
+
+    fn side_to_move(&self) -> Color {
+        self.game.current_player.color
+    }
+}
+
+/// Reads xboard commands from `input` until `quit` or end of input, writing replies to `output`.
+pub fn run<R: BufRead, W: Write + Send + 'static>(input: R, output: W) {
+    let mut xboard = Xboard::new(output);
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !xboard.handle(&line) {
+            return;
+        }
+    }
+
+    xboard.handle("quit");
+}
+
+// The result line for a game at `status`, with `position` on the board for the `repetitions`th
+// time. Draws by repetition and the 50-move rule are claimed at once.
+fn game_result(status: GameStatus, position: &Position, repetitions: usize) -> Option<String> {
+    let (result, reason) = match status_outcome(status) {
+        Some(outcome) => (outcome.result, outcome.reason),
+        None if position.halfmove_clock >= 100 => ("1/2-1/2", "50 move rule".to_string()),
+        None if repetitions >= 3 => ("1/2-1/2", "3-fold repetition".to_string()),
+        None => return None,
+    };
+
+    Some(format!("{} {{{}}}", result, reason))
+}
+
+// Minutes, or minutes:seconds.
+fn parse_minutes(text: &str) -> Option<Duration> {
+    let (minutes, seconds) = match text.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds.parse::<u64>().ok()?),
+        None => (text.parse::<u64>().ok()?, 0),
+    };
+
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+// ply score time nodes pv, with the time in centiseconds.
+fn thinking_line(result: &SearchResult) -> String {
+    let score = match result.mate_in() {
+        Some(moves) if moves > 0 => XBOARD_MATE_SCORE + moves,
+        Some(moves) => -XBOARD_MATE_SCORE + moves,
+        None => result.score,
+    };
+    let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+
+    format!(
+        "{} {} {} {} {}",
+        result.depth,
+        score,
+        result.elapsed.as_millis() / 10,
+        result.nodes,
+        pv.join(" ")
+    )
+}